@@ -16,26 +16,6 @@ use serial_embedded_hal::{PortSettings, Serial};
 use spidriver::SPIDriver;
 use std::path::Path;
 
-const GAMEDUINO_HDMI_720P: config::VideoTimings = config::VideoTimings {
-    sysclk_freq: config::ClockFrequency::F72MHz,
-    pclk_div: 1,
-    pclk_pol: config::ClockPolarity::RisingEdge,
-    horiz: config::VideoTimingDimension {
-        total: 1650,
-        offset: 260,
-        visible: 1280,
-        sync_start: 40,
-        sync_end: 0,
-    },
-    vert: config::VideoTimingDimension {
-        total: 750,
-        offset: 25,
-        visible: 720,
-        sync_start: 5,
-        sync_end: 0,
-    },
-};
-
 fn main() {
     println!("Hello, world!");
 
@@ -102,7 +82,7 @@ fn main() {
     //show_current_dl(&mut ll);
     //return;
 
-    const TIMINGS: config::VideoTimings = GAMEDUINO_HDMI_720P;
+    const TIMINGS: config::VideoTimings = config::presets::GAMEDUINO_HDMI_720P;
 
     //let mut eve = evegfx::BT815::new(eve_interface);
     let mut eve = EVE::new(evegfx::BT815, eve_interface);
@@ -330,7 +310,12 @@ fn show_current_dl<M: Model, I: Interface>(ll: &mut evegfx::low_level::LowLevel<
         let v = ll
             .rd32(M::DisplayListMem::ptr(offset))
             .unwrap_or(0xf33df4c3);
-        println!("{:#06x}: {:#010x}", offset, v);
+        println!(
+            "{:#06x}: {:#010x}  {}",
+            offset,
+            v,
+            evegfx::display_list::DLCmd::from_raw(v)
+        );
         if (v & 0xff000000) == 0 {
             // DISPLAY command ends the display list.
             return;
@@ -0,0 +1,51 @@
+use embedded_hal_async::spi::{Operation, SpiDevice};
+use evegfx::interface::AsyncInterface;
+
+/// `EVEHALAsyncSPIInterface` is an implementation of
+/// `evegfx.AsyncInterface` that communicates over SPI using the
+/// `embedded-hal-async` `SpiDevice` trait.
+///
+/// Unlike [`EVEHALSPIInterface`](crate::EVEHALSPIInterface), this type
+/// doesn't manage the chip select signal itself: the `SpiDevice`
+/// implementation asserts chip select for the duration of each transaction.
+pub struct EVEHALAsyncSPIInterface<SPI: SpiDevice> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice> EVEHALAsyncSPIInterface<SPI> {
+    /// Create a new EVE interface in terms of the given SPI device.
+    pub fn new(spi: SPI) -> Self {
+        Self { spi: spi }
+    }
+
+    /// Consumes the interface and returns the SPI device it was wrapping.
+    pub fn take_spi(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI: SpiDevice> AsyncInterface for EVEHALAsyncSPIInterface<SPI> {
+    type Error = SPI::Error;
+
+    async fn write(&mut self, addr: u32, v: &[u8]) -> Result<(), Self::Error> {
+        let mut header: [u8; 3] = [0; 3];
+        self.build_write_header(addr, &mut header);
+        self.spi
+            .transaction(&mut [Operation::Write(&header), Operation::Write(v)])
+            .await
+    }
+
+    async fn read(&mut self, addr: u32, into: &mut [u8]) -> Result<(), Self::Error> {
+        let mut header: [u8; 4] = [0; 4];
+        self.build_read_header(addr, &mut header);
+        self.spi
+            .transaction(&mut [Operation::Write(&header), Operation::Read(into)])
+            .await
+    }
+
+    async fn host_cmd(&mut self, cmd: u8, a0: u8, a1: u8) -> Result<(), Self::Error> {
+        let mut msg: [u8; 3] = [0; 3];
+        self.build_host_cmd_msg(cmd, a0, a1, &mut msg);
+        self.spi.write(&msg).await
+    }
+}
@@ -0,0 +1,282 @@
+use core::cell::RefCell;
+use embedded_hal::digital::{ErrorKind, ErrorType, InputPin, OutputPin, StatefulOutputPin};
+use evegfx::commands::waiter::Waiter;
+use evegfx::commands::Coprocessor;
+use evegfx::gpio::{GpioDirection, GpioPin};
+use evegfx::interface::Interface;
+use evegfx::models::Model;
+use evegfx::EVE;
+
+/// `EveGpio` provides the general-purpose I/O pins of an EVE chip as
+/// separate objects implementing the `embedded-hal` 1.0 digital pin traits,
+/// so that they can be passed to other drivers.
+///
+/// The pins share access to either an `EVE` object or a `Coprocessor`
+/// object through a `RefCell`, and each pin changes only its own bit in the
+/// GPIO registers. While a pin is using the shared object, any other
+/// attempt to use it returns [`EveGpioError::Busy`](EveGpioError::Busy)
+/// rather than panicking.
+///
+/// Once an application has started using the coprocessor it no longer has
+/// access to the `EVE` object, so it should share the `Coprocessor` object
+/// instead. In that case each pin operation waits for the coprocessor to
+/// complete all of the commands issued so far.
+pub struct EveGpio<'a, C: GpioController> {
+    pub gpio0: EveGpioPin<'a, C>,
+    pub gpio1: EveGpioPin<'a, C>,
+    pub gpio2: EveGpioPin<'a, C>,
+    pub gpio3: EveGpioPin<'a, C>,
+    pub disp: EveGpioPin<'a, C>,
+}
+
+impl<'a, C: GpioController> EveGpio<'a, C> {
+    /// Returns the pins of the given EVE chip.
+    ///
+    /// This doesn't change the direction of any of the pins. Use
+    /// [`EveGpioPin::into_output`](EveGpioPin::into_output) or
+    /// [`EveGpioPin::into_input`](EveGpioPin::into_input) to select one.
+    pub fn new(eve: &'a RefCell<C>) -> Self {
+        Self {
+            gpio0: EveGpioPin::new(eve, GpioPin::GPIO0),
+            gpio1: EveGpioPin::new(eve, GpioPin::GPIO1),
+            gpio2: EveGpioPin::new(eve, GpioPin::GPIO2),
+            gpio3: EveGpioPin::new(eve, GpioPin::GPIO3),
+            disp: EveGpioPin::new(eve, GpioPin::DISP),
+        }
+    }
+}
+
+/// A single EVE general-purpose I/O pin. See [`EveGpio`](EveGpio).
+pub struct EveGpioPin<'a, C: GpioController> {
+    eve: &'a RefCell<C>,
+    pin: GpioPin,
+}
+
+impl<'a, C: GpioController> EveGpioPin<'a, C> {
+    /// Returns an object for just the given pin of the given EVE chip.
+    pub fn new(eve: &'a RefCell<C>, pin: GpioPin) -> Self {
+        Self { eve: eve, pin: pin }
+    }
+
+    /// Returns which pin this object controls.
+    pub fn pin(&self) -> GpioPin {
+        self.pin
+    }
+
+    /// Configures the pin as an output, first setting its level to `high`
+    /// so that it doesn't briefly output the previous level.
+    pub fn into_output(self, high: bool) -> Result<Self, EveGpioError<C::Error>> {
+        let pin = self.pin;
+        self.with_eve(|eve| {
+            eve.gpio_write(pin, high)?;
+            eve.gpio_set_direction(pin, GpioDirection::Output)
+        })?;
+        Ok(self)
+    }
+
+    /// Configures the pin as an input.
+    pub fn into_input(self) -> Result<Self, EveGpioError<C::Error>> {
+        let pin = self.pin;
+        self.with_eve(|eve| eve.gpio_set_direction(pin, GpioDirection::Input))?;
+        Ok(self)
+    }
+
+    fn with_eve<R, F>(&self, f: F) -> Result<R, EveGpioError<C::Error>>
+    where
+        F: FnOnce(&mut C) -> Result<R, C::Error>,
+    {
+        let mut eve = self.eve.try_borrow_mut().map_err(|_| EveGpioError::Busy)?;
+        f(&mut eve).map_err(EveGpioError::EVE)
+    }
+}
+
+impl<'a, C: GpioController> ErrorType for EveGpioPin<'a, C>
+where
+    C::Error: core::fmt::Debug,
+{
+    type Error = EveGpioError<C::Error>;
+}
+
+impl<'a, C: GpioController> OutputPin for EveGpioPin<'a, C>
+where
+    C::Error: core::fmt::Debug,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let pin = self.pin;
+        self.with_eve(|eve| eve.gpio_write(pin, false))
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let pin = self.pin;
+        self.with_eve(|eve| eve.gpio_write(pin, true))
+    }
+}
+
+impl<'a, C: GpioController> StatefulOutputPin for EveGpioPin<'a, C>
+where
+    C::Error: core::fmt::Debug,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        let pin = self.pin;
+        self.with_eve(|eve| eve.gpio_read(pin))
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_set_high().map(|high| !high)
+    }
+}
+
+impl<'a, C: GpioController> InputPin for EveGpioPin<'a, C>
+where
+    C::Error: core::fmt::Debug,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let pin = self.pin;
+        self.with_eve(|eve| eve.gpio_read(pin))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// The error type for [`EveGpioPin`](EveGpioPin).
+#[derive(Debug)]
+pub enum EveGpioError<E> {
+    /// Another pin, or some other code, was already using the shared
+    /// object.
+    Busy,
+
+    /// The EVE chip couldn't be accessed.
+    EVE(E),
+}
+
+impl<E: core::fmt::Debug> embedded_hal::digital::Error for EveGpioError<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Implementations of `GpioController` are the objects that
+/// [`EveGpioPin`](EveGpioPin) can use to access the EVE GPIO registers.
+///
+/// This is implemented for both [`EVE`](evegfx::EVE) and
+/// [`Coprocessor`](evegfx::commands::Coprocessor).
+pub trait GpioController {
+    type Error;
+
+    fn gpio_set_direction(&mut self, pin: GpioPin, dir: GpioDirection) -> Result<(), Self::Error>;
+    fn gpio_write(&mut self, pin: GpioPin, high: bool) -> Result<(), Self::Error>;
+    fn gpio_read(&mut self, pin: GpioPin) -> Result<bool, Self::Error>;
+}
+
+impl<M: Model, I: Interface> GpioController for EVE<M, I> {
+    type Error = evegfx::Error<I>;
+
+    fn gpio_set_direction(&mut self, pin: GpioPin, dir: GpioDirection) -> Result<(), Self::Error> {
+        EVE::gpio_set_direction(self, pin, dir)
+    }
+
+    fn gpio_write(&mut self, pin: GpioPin, high: bool) -> Result<(), Self::Error> {
+        EVE::gpio_write(self, pin, high)
+    }
+
+    fn gpio_read(&mut self, pin: GpioPin) -> Result<bool, Self::Error> {
+        EVE::gpio_read(self, pin)
+    }
+}
+
+impl<M: Model, I: Interface, W: Waiter<M, I>, const N: usize> GpioController
+    for Coprocessor<M, I, W, N>
+{
+    type Error = evegfx::CoprocessorError<M, I, W>;
+
+    fn gpio_set_direction(&mut self, pin: GpioPin, dir: GpioDirection) -> Result<(), Self::Error> {
+        self.block_gpio_set_direction(pin, dir)
+    }
+
+    fn gpio_write(&mut self, pin: GpioPin, high: bool) -> Result<(), Self::Error> {
+        self.block_gpio_write(pin, high)
+    }
+
+    fn gpio_read(&mut self, pin: GpioPin) -> Result<bool, Self::Error> {
+        self.block_gpio_read(pin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evegfx::interface::fake::Interface as FakeInterface;
+    use evegfx::low_level::Register;
+    use evegfx::models::fake::Model as FakeModel;
+
+    #[test]
+    fn test_eve_pins() {
+        let mut regs = [0u32; 1024];
+        regs[Register::GPIOX_DIR.index()] = 0x0008;
+        regs[Register::GPIO_X.index()] = 0x0001;
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let eve = RefCell::new(EVE::new(FakeModel, ei));
+
+        let pins = EveGpio::new(&eve);
+        let mut gpio2 = pins.gpio2.into_output(true).unwrap();
+        let mut disp = pins.disp.into_output(false).unwrap();
+        let mut gpio0 = pins.gpio0.into_input().unwrap();
+
+        assert!(gpio2.is_set_high().unwrap());
+        assert!(!gpio2.is_set_low().unwrap());
+        assert!(disp.is_set_low().unwrap());
+        gpio2.set_low().unwrap();
+        assert!(gpio2.is_set_low().unwrap());
+        disp.set_high().unwrap();
+        assert!(disp.is_set_high().unwrap());
+
+        assert!(gpio0.is_high().unwrap());
+        assert!(!gpio0.is_low().unwrap());
+
+        // Each pin changes only its own bits.
+        let mut eve = eve.into_inner();
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::GPIOX_DIR)).unwrap(), 0x800c);
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::GPIO_X)).unwrap(), 0x8001);
+    }
+
+    #[test]
+    fn test_busy() {
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let eve = RefCell::new(EVE::new(FakeModel, ei));
+
+        let mut pin = EveGpioPin::new(&eve, GpioPin::GPIO1);
+        let _borrowed = eve.borrow_mut();
+        let result = pin.set_high();
+        assert!(
+            matches!(result, Err(EveGpioError::Busy)),
+            "wrong result {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_coprocessor_pins() {
+        let mut regs = [0u32; 1024];
+        regs[Register::CMDB_SPACE.index()] = 4092;
+        regs[Register::GPIO_X.index()] = 0x0002;
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let cp: Coprocessor<FakeModel, _, _> = Coprocessor::new_polling(ei).unwrap();
+        let cp = RefCell::new(cp);
+
+        let pins = EveGpio::new(&cp);
+        let mut gpio3 = pins.gpio3.into_output(false).unwrap();
+        let mut gpio1 = pins.gpio1.into_input().unwrap();
+
+        assert!(gpio3.is_set_low().unwrap());
+        gpio3.set_high().unwrap();
+        assert!(gpio3.is_set_high().unwrap());
+        assert!(gpio1.is_high().unwrap());
+
+        assert_eq!(regs[Register::GPIOX_DIR.index()], 0x0008);
+        assert_eq!(regs[Register::GPIO_X.index()], 0x000a);
+    }
+}
@@ -0,0 +1,245 @@
+use embedded_hal::spi::{Operation, SpiDevice};
+use evegfx::interface::Interface;
+use evegfx::low_level::Register;
+use evegfx::models::Model;
+
+/// `EVEHALSPIDeviceInterface` is an implementation of `evegfx.Interface`
+/// that communicates over SPI using the `embedded-hal` 1.0 `SpiDevice`
+/// trait, which takes care of bus sharing and chip select.
+///
+/// `SpiDevice` can't keep chip select asserted between calls, so each call
+/// to `continue_write` or `continue_read` becomes its own SPI transaction,
+/// sending a new header for the address where the previous call left off.
+/// The data itself is passed directly to the SPI device, without any
+/// additional buffering.
+///
+/// The interface needs to know which EVE model it's talking to in order to
+/// recognize writes to `REG_CMDB_WRITE`, which don't advance the address.
+pub struct EVEHALSPIDeviceInterface<SPI: SpiDevice, M: Model> {
+    spi: SPI,
+    next_addr: u32,
+    increment: bool,
+    _model: core::marker::PhantomData<M>,
+}
+
+impl<SPI: SpiDevice, M: Model> EVEHALSPIDeviceInterface<SPI, M> {
+    /// Create a new EVE interface in terms of the given SPI device, for
+    /// communicating with the given EVE model.
+    pub fn new(spi: SPI, _model: M) -> Self {
+        Self {
+            spi: spi,
+            next_addr: 0,
+            increment: true,
+            _model: core::marker::PhantomData,
+        }
+    }
+
+    /// Consumes the interface and returns the SPI device it was wrapping.
+    pub fn take_spi(self) -> SPI {
+        self.spi
+    }
+
+    fn begin(&mut self, addr: u32) {
+        self.next_addr = addr;
+        // The EVE chip doesn't advance the address during a burst write to
+        // REG_CMDB_WRITE, so that callers can stream a whole series of
+        // commands to it.
+        self.increment = addr != M::reg_ptr(Register::CMDB_WRITE).to_raw();
+    }
+
+    fn advance(&mut self, len: usize) {
+        if self.increment {
+            self.next_addr += len as u32;
+        }
+    }
+}
+
+impl<SPI: SpiDevice, M: Model> Interface for EVEHALSPIDeviceInterface<SPI, M> {
+    type Error = SPI::Error;
+
+    fn begin_write(&mut self, addr: u32) -> Result<(), Self::Error> {
+        self.begin(addr);
+        Ok(())
+    }
+
+    fn continue_write(&mut self, v: &[u8]) -> Result<(), Self::Error> {
+        let mut header: [u8; 3] = [0; 3];
+        self.build_write_header(self.next_addr, &mut header);
+        self.spi
+            .transaction(&mut [Operation::Write(&header), Operation::Write(v)])?;
+        self.advance(v.len());
+        Ok(())
+    }
+
+    fn end_write(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn begin_read(&mut self, addr: u32) -> Result<(), Self::Error> {
+        self.begin(addr);
+        Ok(())
+    }
+
+    fn continue_read(&mut self, into: &mut [u8]) -> Result<(), Self::Error> {
+        let mut header: [u8; 4] = [0; 4];
+        self.build_read_header(self.next_addr, &mut header);
+        let len = into.len();
+        self.spi
+            .transaction(&mut [Operation::Write(&header), Operation::Read(into)])?;
+        self.advance(len);
+        Ok(())
+    }
+
+    fn end_read(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn host_cmd(&mut self, cmd: u8, a0: u8, a1: u8) -> Result<(), Self::Error> {
+        let mut msg: [u8; 3] = [0; 3];
+        self.build_host_cmd_msg(cmd, a0, a1, &mut msg);
+        self.spi.write(&msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use embedded_hal::spi::ErrorType;
+    use evegfx::BT815;
+    use std::vec;
+    use std::vec::Vec;
+
+    // A single recorded operation: the bytes written, or the number of
+    // bytes read.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Recorded {
+        Write(Vec<u8>),
+        Read(usize),
+    }
+
+    #[derive(Default)]
+    struct FakeSpiDevice {
+        transactions: Vec<Vec<Recorded>>,
+        next_byte: u8,
+    }
+
+    impl ErrorType for FakeSpiDevice {
+        type Error = core::convert::Infallible;
+    }
+
+    impl SpiDevice for FakeSpiDevice {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+            let mut recorded = Vec::new();
+            for op in operations.iter_mut() {
+                match op {
+                    Operation::Write(v) => recorded.push(Recorded::Write(v.to_vec())),
+                    Operation::Read(into) => {
+                        for b in into.iter_mut() {
+                            *b = self.next_byte;
+                            self.next_byte += 1;
+                        }
+                        recorded.push(Recorded::Read(into.len()));
+                    }
+                    _ => unimplemented!("unsupported operation {:?}", op),
+                }
+            }
+            self.transactions.push(recorded);
+            Ok(())
+        }
+    }
+
+    fn new_interface() -> EVEHALSPIDeviceInterface<FakeSpiDevice, BT815> {
+        EVEHALSPIDeviceInterface::new(FakeSpiDevice::default(), BT815)
+    }
+
+    #[test]
+    fn test_write() {
+        let mut ei = new_interface();
+        ei.begin_write(0x001000).unwrap();
+        ei.continue_write(&[1, 2, 3, 4]).unwrap();
+        ei.continue_write(&[5, 6]).unwrap();
+        ei.end_write().unwrap();
+
+        let spi = ei.take_spi();
+        assert_eq!(
+            spi.transactions,
+            vec![
+                vec![
+                    Recorded::Write(vec![0x80, 0x10, 0x00]),
+                    Recorded::Write(vec![1, 2, 3, 4]),
+                ],
+                vec![
+                    Recorded::Write(vec![0x80, 0x10, 0x04]),
+                    Recorded::Write(vec![5, 6]),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_cmdb() {
+        let mut ei = new_interface();
+        let addr = BT815::reg_ptr(Register::CMDB_WRITE).to_raw();
+        ei.begin_write(addr).unwrap();
+        ei.continue_write(&[1, 2, 3, 4]).unwrap();
+        ei.continue_write(&[5, 6, 7, 8]).unwrap();
+        ei.end_write().unwrap();
+
+        // Both writes must go to REG_CMDB_WRITE, because the chip doesn't
+        // advance the address when writing to it.
+        let spi = ei.take_spi();
+        assert_eq!(
+            spi.transactions,
+            vec![
+                vec![
+                    Recorded::Write(vec![0xb0, 0x25, 0x78]),
+                    Recorded::Write(vec![1, 2, 3, 4]),
+                ],
+                vec![
+                    Recorded::Write(vec![0xb0, 0x25, 0x78]),
+                    Recorded::Write(vec![5, 6, 7, 8]),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        let mut ei = new_interface();
+        let mut got = [0u8; 3];
+        ei.begin_read(0x302000).unwrap();
+        ei.continue_read(&mut got[..2]).unwrap();
+        ei.continue_read(&mut got[2..]).unwrap();
+        ei.end_read().unwrap();
+        assert_eq!(got, [0, 1, 2]);
+
+        let spi = ei.take_spi();
+        assert_eq!(
+            spi.transactions,
+            vec![
+                vec![
+                    Recorded::Write(vec![0x30, 0x20, 0x00, 0x00]),
+                    Recorded::Read(2),
+                ],
+                vec![
+                    Recorded::Write(vec![0x30, 0x20, 0x02, 0x00]),
+                    Recorded::Read(1),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_host_cmd() {
+        let mut ei = new_interface();
+        ei.host_cmd(0x44, 0, 0).unwrap();
+
+        let spi = ei.take_spi();
+        assert_eq!(
+            spi.transactions,
+            vec![vec![Recorded::Write(vec![0x44, 0x00, 0x00])]]
+        );
+    }
+}
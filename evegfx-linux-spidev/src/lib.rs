@@ -0,0 +1,466 @@
+//! An adapter for using the `evegfx` crate with an EVE chip connected to a
+//! Linux system's SPI bus, via the `spidev` userspace interface, with its
+//! `PD#` pin connected to a GPIO line accessed via the GPIO character
+//! device interface.
+
+use evegfx::interface::{Interface, SpiWidth, SpiWidthInterface};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use spidev::{Spidev, SpidevOptions};
+use std::path::Path;
+use std::time::Duration;
+
+mod transport;
+pub use spidev::SpiModeFlags;
+pub use transport::{PowerDownPin, Transfer, Transport};
+
+/// `EVELinuxSpidevInterface` is an implementation of `evegfx.Interface`
+/// that communicates over a Linux spidev device, or over some other
+/// implementation of [`Transport`](Transport).
+///
+/// Each call to `continue_write` or `continue_read` becomes a single batch
+/// of transfers, with the first one also sending the transaction header.
+/// Chip select remains asserted between batches until the transaction
+/// ends, so this interface assumes that nothing else is using the same SPI
+/// device concurrently.
+///
+/// The interface uses only a single data line unless it's created with
+/// [`with_max_spi_width`](Self::with_max_spi_width), because the kernel
+/// can't report whether the SPI controller and wiring support more.
+pub struct EVELinuxSpidevInterface<T: Transport, PD: PowerDownPin> {
+    transport: T,
+    pd: PD,
+    mode: SpiModeFlags,
+    width: SpiWidth,
+    max_width: SpiWidth,
+    header: [u8; 4],
+    header_len: usize,
+    selected: bool,
+}
+
+/// `Settings` describes how to configure the SPI device when opening it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub speed_hz: u32,
+    pub mode: SpiModeFlags,
+
+    /// The widest SPI mode the controller and wiring can support, for
+    /// use with [`EVE::set_spi_width`](evegfx::EVE::set_spi_width).
+    pub max_spi_width: SpiWidth,
+}
+
+impl Default for Settings {
+    /// Returns settings suitable for an EVE chip that hasn't yet started
+    /// its system clock, which can accept SPI clock speeds of up to 11MHz.
+    fn default() -> Self {
+        Self {
+            speed_hz: 10_000_000,
+            mode: SpiModeFlags::SPI_MODE_0,
+            max_spi_width: SpiWidth::Single,
+        }
+    }
+}
+
+impl EVELinuxSpidevInterface<Spidev, LineHandle> {
+    /// Opens the given spidev device (e.g. `/dev/spidev0.0`) and requests
+    /// the given line on the given GPIO chip (e.g. `/dev/gpiochip0`) as an
+    /// output to drive the EVE `PD#` pin.
+    pub fn open<SP, GP>(
+        spi_path: SP,
+        settings: Settings,
+        gpio_chip_path: GP,
+        pd_line: u32,
+    ) -> Result<Self, OpenError>
+    where
+        SP: AsRef<Path>,
+        GP: AsRef<Path>,
+    {
+        let mut spi = Spidev::open(spi_path).map_err(OpenError::SPI)?;
+        spi.configure(
+            &SpidevOptions::new()
+                .bits_per_word(8)
+                .max_speed_hz(settings.speed_hz)
+                .mode(settings.mode)
+                .build(),
+        )
+        .map_err(OpenError::SPI)?;
+
+        let mut chip = Chip::new(gpio_chip_path).map_err(OpenError::GPIO)?;
+        let pd = chip
+            .get_line(pd_line)
+            .and_then(|line| line.request(LineRequestFlags::OUTPUT, 1, "evegfx"))
+            .map_err(OpenError::GPIO)?;
+
+        Ok(Self::new(spi, pd)
+            .with_mode(settings.mode)
+            .with_max_spi_width(settings.max_spi_width))
+    }
+
+    /// Changes the SPI clock speed, such as to switch to a faster speed
+    /// once the EVE system clock is running.
+    pub fn set_speed_hz(&mut self, speed_hz: u32) -> std::io::Result<()> {
+        self.transport
+            .configure(&SpidevOptions::new().max_speed_hz(speed_hz).build())
+    }
+}
+
+impl<T: Transport, PD: PowerDownPin> EVELinuxSpidevInterface<T, PD> {
+    /// Creates a new interface from an already-configured transport and
+    /// `PD#` pin.
+    ///
+    /// The interface assumes that the transport is in SPI mode 0. Use
+    /// [`with_mode`](Self::with_mode) if it isn't.
+    pub fn new(transport: T, pd: PD) -> Self {
+        Self {
+            transport: transport,
+            pd: pd,
+            mode: SpiModeFlags::SPI_MODE_0,
+            width: SpiWidth::Single,
+            max_width: SpiWidth::Single,
+            header: [0; 4],
+            header_len: 0,
+            selected: false,
+        }
+    }
+
+    /// Records the SPI mode the transport is already configured to use, so
+    /// that switching the SPI width can preserve it.
+    pub fn with_mode(self, mode: SpiModeFlags) -> Self {
+        Self { mode: mode, ..self }
+    }
+
+    /// Allows [`EVE::set_spi_width`](evegfx::EVE::set_spi_width) to switch
+    /// to the given width, or any narrower one.
+    pub fn with_max_spi_width(self, max: SpiWidth) -> Self {
+        Self {
+            max_width: max,
+            ..self
+        }
+    }
+
+    /// Changes the SPI mode, keeping the flags for the current SPI width.
+    pub fn set_mode(&mut self, mode: SpiModeFlags) -> Result<(), T::Error> {
+        self.mode = mode;
+        self.transport.set_mode(mode | width_mode_flags(self.width))
+    }
+
+    /// Consumes the interface and returns the transport and pin it was
+    /// wrapping.
+    pub fn take_parts(self) -> (T, PD) {
+        (self.transport, self.pd)
+    }
+
+    fn continue_transaction(
+        &mut self,
+        data: Transfer<'_>,
+    ) -> Result<(), <Self as Interface>::Error> {
+        let width = self.width;
+        let data = data.keep_cs().with_width(width);
+        let header_len = self.header_len;
+        self.header_len = 0;
+        let result = if header_len > 0 {
+            let header = Transfer::write(&self.header[..header_len]).with_width(width);
+            self.transport.transfer(&mut [header, data])
+        } else {
+            self.transport.transfer(&mut [data])
+        };
+        self.selected = true;
+        result.map_err(Error::Transport)
+    }
+
+    fn end_transaction(&mut self) -> Result<(), <Self as Interface>::Error> {
+        self.header_len = 0;
+        if !self.selected {
+            // We didn't send anything yet, so there's nothing to release.
+            return Ok(());
+        }
+        self.selected = false;
+        self.transport
+            .transfer(&mut [Transfer::release().with_width(self.width)])
+            .map_err(Error::Transport)
+    }
+
+    fn set_powered(&mut self, powered: bool) -> Result<(), <Self as Interface>::Error> {
+        self.pd.set_powered(powered).map_err(Error::PowerDown)
+    }
+}
+
+impl<T: Transport, PD: PowerDownPin> Interface for EVELinuxSpidevInterface<T, PD> {
+    type Error = Error<T::Error, PD::Error>;
+
+    fn reset(&mut self) -> Result<(), Self::Error> {
+        self.end_transaction()?;
+        self.set_powered(false)?;
+        std::thread::sleep(Duration::from_millis(20));
+        self.set_powered(true)?;
+        std::thread::sleep(Duration::from_millis(20));
+        // Resetting the chip returns it to single mode, so we must follow.
+        if self.width != SpiWidth::Single {
+            self.width = SpiWidth::Single;
+            self.transport
+                .set_mode(self.mode)
+                .map_err(Error::Transport)?;
+        }
+        Ok(())
+    }
+
+    fn begin_write(&mut self, addr: u32) -> Result<(), Self::Error> {
+        let mut header: [u8; 3] = [0; 3];
+        self.build_write_header(addr, &mut header);
+        self.header[..3].copy_from_slice(&header);
+        self.header_len = 3;
+        Ok(())
+    }
+
+    fn continue_write(&mut self, v: &[u8]) -> Result<(), Self::Error> {
+        self.continue_transaction(Transfer::write(v))
+    }
+
+    fn end_write(&mut self) -> Result<(), Self::Error> {
+        self.end_transaction()
+    }
+
+    fn begin_read(&mut self, addr: u32) -> Result<(), Self::Error> {
+        let mut header: [u8; 4] = [0; 4];
+        self.build_read_header(addr, &mut header);
+        self.header = header;
+        self.header_len = 4;
+        Ok(())
+    }
+
+    fn continue_read(&mut self, into: &mut [u8]) -> Result<(), Self::Error> {
+        self.continue_transaction(Transfer::read(into))
+    }
+
+    fn end_read(&mut self) -> Result<(), Self::Error> {
+        self.end_transaction()
+    }
+
+    fn host_cmd(&mut self, cmd: u8, a0: u8, a1: u8) -> Result<(), Self::Error> {
+        let mut msg: [u8; 3] = [0; 3];
+        self.build_host_cmd_msg(cmd, a0, a1, &mut msg);
+        self.transport
+            .transfer(&mut [Transfer::write(&msg).with_width(self.width)])
+            .map_err(Error::Transport)
+    }
+}
+
+impl<T: Transport, PD: PowerDownPin> SpiWidthInterface for EVELinuxSpidevInterface<T, PD> {
+    fn supports_spi_width(&self, width: SpiWidth) -> bool {
+        width <= self.max_width
+    }
+
+    fn set_spi_width(&mut self, width: SpiWidth) -> Result<(), Self::Error> {
+        self.transport
+            .set_mode(self.mode | width_mode_flags(width))
+            .map_err(Error::Transport)?;
+        self.width = width;
+        Ok(())
+    }
+}
+
+fn width_mode_flags(width: SpiWidth) -> SpiModeFlags {
+    match width {
+        SpiWidth::Single => SpiModeFlags::empty(),
+        SpiWidth::Dual => SpiModeFlags::SPI_TX_DUAL | SpiModeFlags::SPI_RX_DUAL,
+        SpiWidth::Quad => SpiModeFlags::SPI_TX_QUAD | SpiModeFlags::SPI_RX_QUAD,
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<TransportError, PinError> {
+    Transport(TransportError),
+    PowerDown(PinError),
+}
+
+#[derive(Debug)]
+pub enum OpenError {
+    SPI(std::io::Error),
+    GPIO(gpio_cdev::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A single recorded transfer: the bytes sent (if any), the number of
+    // bytes received, and whether chip select was kept asserted.
+    type Recorded = (Option<Vec<u8>>, usize, bool);
+
+    #[derive(Default)]
+    struct FakeTransport {
+        batches: Vec<Vec<Recorded>>,
+        widths: Vec<SpiWidth>,
+        modes: Vec<SpiModeFlags>,
+        next_byte: u8,
+    }
+
+    impl Transport for FakeTransport {
+        type Error = ();
+
+        fn transfer(&mut self, batch: &mut [Transfer<'_>]) -> Result<(), ()> {
+            let mut recorded = Vec::new();
+            for t in batch.iter_mut() {
+                let rx_len = match t.rx.as_deref_mut() {
+                    Some(rx) => {
+                        for b in rx.iter_mut() {
+                            *b = self.next_byte;
+                            self.next_byte += 1;
+                        }
+                        rx.len()
+                    }
+                    None => 0,
+                };
+                recorded.push((t.tx.map(|tx| tx.to_vec()), rx_len, t.keep_cs));
+                self.widths.push(t.width);
+            }
+            self.batches.push(recorded);
+            Ok(())
+        }
+
+        fn set_mode(&mut self, mode: SpiModeFlags) -> Result<(), ()> {
+            self.modes.push(mode);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct FakePin {
+        history: Vec<bool>,
+    }
+
+    impl PowerDownPin for FakePin {
+        type Error = ();
+
+        fn set_powered(&mut self, powered: bool) -> Result<(), ()> {
+            self.history.push(powered);
+            Ok(())
+        }
+    }
+
+    fn new_interface() -> EVELinuxSpidevInterface<FakeTransport, FakePin> {
+        EVELinuxSpidevInterface::new(FakeTransport::default(), FakePin::default())
+    }
+
+    #[test]
+    fn test_write() {
+        let mut ei = new_interface();
+        ei.begin_write(0x302578).unwrap();
+        ei.continue_write(&[1, 2, 3, 4]).unwrap();
+        ei.continue_write(&[5, 6, 7, 8]).unwrap();
+        ei.end_write().unwrap();
+
+        let (transport, _) = ei.take_parts();
+        assert_eq!(
+            transport.batches,
+            vec![
+                vec![
+                    (Some(vec![0xb0, 0x25, 0x78]), 0, false),
+                    (Some(vec![1, 2, 3, 4]), 0, true),
+                ],
+                vec![(Some(vec![5, 6, 7, 8]), 0, true)],
+                vec![(None, 0, false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        let mut ei = new_interface();
+        let mut got = [0u8; 3];
+        ei.read(0x302000, &mut got).unwrap();
+        assert_eq!(got, [0, 1, 2]);
+
+        let (transport, _) = ei.take_parts();
+        assert_eq!(
+            transport.batches,
+            vec![
+                vec![
+                    (Some(vec![0x30, 0x20, 0x00, 0x00]), 0, false),
+                    (None, 3, true),
+                ],
+                vec![(None, 0, false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_transaction() {
+        let mut ei = new_interface();
+        ei.begin_write(0).unwrap();
+        ei.end_write().unwrap();
+
+        let (transport, _) = ei.take_parts();
+        assert_eq!(transport.batches, Vec::<Vec<Recorded>>::new());
+    }
+
+    #[test]
+    fn test_host_cmd() {
+        let mut ei = new_interface();
+        ei.host_cmd(0x44, 0, 0).unwrap();
+
+        let (transport, _) = ei.take_parts();
+        assert_eq!(
+            transport.batches,
+            vec![vec![(Some(vec![0x44, 0x00, 0x00]), 0, false)]]
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut ei = new_interface();
+        ei.begin_write(0).unwrap();
+        ei.continue_write(&[1]).unwrap();
+        ei.reset().unwrap();
+
+        let (transport, pd) = ei.take_parts();
+        assert_eq!(
+            transport.batches,
+            vec![
+                vec![
+                    (Some(vec![0x80, 0x00, 0x00]), 0, false),
+                    (Some(vec![1]), 0, true)
+                ],
+                vec![(None, 0, false)],
+            ]
+        );
+        assert_eq!(pd.history, vec![false, true]);
+    }
+
+    #[test]
+    fn test_set_spi_width() {
+        let mut ei = new_interface()
+            .with_mode(SpiModeFlags::SPI_MODE_3)
+            .with_max_spi_width(SpiWidth::Dual);
+        assert!(ei.supports_spi_width(SpiWidth::Dual));
+        assert!(!ei.supports_spi_width(SpiWidth::Quad));
+
+        ei.set_spi_width(SpiWidth::Dual).unwrap();
+        ei.write(0, &[1]).unwrap();
+        ei.host_cmd(0x44, 0, 0).unwrap();
+
+        let (transport, _) = ei.take_parts();
+        assert_eq!(
+            transport.modes,
+            vec![SpiModeFlags::SPI_MODE_3 | SpiModeFlags::SPI_TX_DUAL | SpiModeFlags::SPI_RX_DUAL]
+        );
+        assert_eq!(transport.widths, vec![SpiWidth::Dual; 4]);
+    }
+
+    #[test]
+    fn test_reset_spi_width() {
+        let mut ei = new_interface().with_max_spi_width(SpiWidth::Quad);
+        ei.set_spi_width(SpiWidth::Quad).unwrap();
+        ei.reset().unwrap();
+        ei.host_cmd(0x44, 0, 0).unwrap();
+
+        let (transport, _) = ei.take_parts();
+        assert_eq!(
+            transport.modes,
+            vec![
+                SpiModeFlags::SPI_MODE_0 | SpiModeFlags::SPI_TX_QUAD | SpiModeFlags::SPI_RX_QUAD,
+                SpiModeFlags::SPI_MODE_0,
+            ]
+        );
+        assert_eq!(transport.widths, vec![SpiWidth::Single]);
+    }
+}
@@ -0,0 +1,136 @@
+use evegfx::interface::SpiWidth;
+use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+
+/// A single step in a batch of transfers sent to a [`Transport`].
+///
+/// This is a platform-neutral mirror of the transfer descriptors accepted
+/// by the Linux spidev ioctl interface, so that the logic for building
+/// batches can be tested without a real device.
+#[derive(Debug)]
+pub struct Transfer<'a> {
+    /// Bytes to send, or `None` to send zeros.
+    pub tx: Option<&'a [u8]>,
+
+    /// Buffer to populate with the bytes received, or `None` to discard
+    /// them.
+    pub rx: Option<&'a mut [u8]>,
+
+    /// If set on the final transfer in a batch, chip select remains
+    /// asserted after the batch completes so that the next batch continues
+    /// the same EVE transaction.
+    pub keep_cs: bool,
+
+    /// The number of data lines to use for this transfer.
+    pub width: SpiWidth,
+}
+
+impl<'a> Transfer<'a> {
+    pub fn write(tx: &'a [u8]) -> Self {
+        Self {
+            tx: Some(tx),
+            rx: None,
+            keep_cs: false,
+            width: SpiWidth::Single,
+        }
+    }
+
+    pub fn read(rx: &'a mut [u8]) -> Self {
+        Self {
+            tx: None,
+            rx: Some(rx),
+            keep_cs: false,
+            width: SpiWidth::Single,
+        }
+    }
+
+    /// Returns an empty transfer, whose only effect is to release chip
+    /// select at the end of its batch.
+    pub fn release() -> Self {
+        Self {
+            tx: None,
+            rx: None,
+            keep_cs: false,
+            width: SpiWidth::Single,
+        }
+    }
+
+    pub fn keep_cs(self) -> Self {
+        Self {
+            keep_cs: true,
+            ..self
+        }
+    }
+
+    pub fn with_width(self, width: SpiWidth) -> Self {
+        Self {
+            width: width,
+            ..self
+        }
+    }
+}
+
+/// Implementations of `Transport` send batches of transfers to an SPI
+/// device, asserting chip select for the duration of each batch.
+///
+/// The main implementation is for [`Spidev`](spidev::Spidev), where each
+/// batch is a single `SPI_IOC_MESSAGE` ioctl call.
+pub trait Transport {
+    type Error;
+
+    fn transfer(&mut self, batch: &mut [Transfer<'_>]) -> Result<(), Self::Error>;
+
+    /// Reconfigures the SPI mode, including the flags that allow dual and
+    /// quad transfers.
+    fn set_mode(&mut self, mode: SpiModeFlags) -> Result<(), Self::Error>;
+}
+
+impl Transport for Spidev {
+    type Error = std::io::Error;
+
+    fn transfer(&mut self, batch: &mut [Transfer<'_>]) -> Result<(), Self::Error> {
+        let mut raw: std::vec::Vec<SpidevTransfer> = batch
+            .iter_mut()
+            .map(|t| {
+                let mut r = match (t.tx, t.rx.as_deref_mut()) {
+                    (Some(tx), Some(rx)) => SpidevTransfer::read_write(tx, rx),
+                    (Some(tx), None) => SpidevTransfer::write(tx),
+                    (None, Some(rx)) => SpidevTransfer::read(rx),
+                    (None, None) => SpidevTransfer::write(&[]),
+                };
+                r.cs_change = t.keep_cs as u8;
+                // The spidev crate doesn't name the kernel's tx_nbits and
+                // rx_nbits fields, which are the first two bytes of what it
+                // calls "pad".
+                let nbits = match t.width {
+                    SpiWidth::Single => 1,
+                    SpiWidth::Dual => 2,
+                    SpiWidth::Quad => 4,
+                };
+                r.pad = u32::from_ne_bytes([nbits, nbits, 0, 0]);
+                r
+            })
+            .collect();
+        self.transfer_multiple(&mut raw)
+    }
+
+    fn set_mode(&mut self, mode: SpiModeFlags) -> Result<(), Self::Error> {
+        self.configure(&SpidevOptions::new().mode(mode).build())
+    }
+}
+
+/// Implementations of `PowerDownPin` control the EVE chip's `PD#` signal,
+/// which the interface pulses in order to reset the chip.
+pub trait PowerDownPin {
+    type Error;
+
+    /// Drives `PD#` high if `powered` is true, or low otherwise.
+    fn set_powered(&mut self, powered: bool) -> Result<(), Self::Error>;
+}
+
+impl PowerDownPin for gpio_cdev::LineHandle {
+    type Error = gpio_cdev::Error;
+
+    fn set_powered(&mut self, powered: bool) -> Result<(), Self::Error> {
+        self.set_value(powered as u8)
+    }
+}
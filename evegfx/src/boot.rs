@@ -0,0 +1,407 @@
+//! A helper for taking an EVE chip from reset through to a running
+//! coprocessor.
+//!
+//! Starting up an EVE chip requires a series of steps in a particular
+//! order. [`EVE::boot`](crate::EVE::boot) returns a [`Boot`](Boot) object
+//! whose type changes after each step, so that the steps can only happen
+//! in the right order:
+//!
+//! ```rust
+//! use evegfx::boot::BootConfig;
+//! use evegfx::config::presets;
+//! use evegfx::interface::Interface;
+//! use evegfx::EVE;
+//!
+//! fn boot<I>(ei: I) -> Result<(), evegfx::Error<I>>
+//! where
+//!     I: Interface,
+//!     I::Error: core::fmt::Debug,
+//! {
+//!     let eve = EVE::new(evegfx::BT815, ei);
+//!     let cp = eve
+//!         .boot(BootConfig::new(presets::WVGA_800X480))
+//!         .start_clock()?
+//!         .wait_for_boot()?
+//!         .start_video()?
+//!         .coprocessor_polling()
+//!         .unwrap();
+//!     Ok(())
+//! }
+//! # evegfx::interface::fake::boot_example(|ei| boot(ei).unwrap());
+//! ```
+
+use crate::commands;
+use crate::config::{Brightness, ClockSource, RGBElectricalMode, VideoTimings};
+use crate::error::Error;
+use crate::graphics::RGB;
+use crate::interface::Interface;
+use crate::models::Model;
+use crate::registers::Register;
+use crate::EVE;
+
+/// The settings used by [`Boot`](Boot).
+#[derive(Clone, Copy, Debug)]
+pub struct BootConfig {
+    pub clock_source: ClockSource,
+    pub timings: VideoTimings,
+    pub pins: RGBElectricalMode,
+
+    /// The maximum number of times to poll the chip while waiting for it to
+    /// boot, before giving up with [`Error::BootTimeout`](Error::BootTimeout).
+    pub boot_poll_limit: u32,
+
+    /// If true, the timings must pass
+    /// [`VideoTimings::validate`](VideoTimings::validate) before anything
+    /// is sent to the chip.
+    pub check_timings: bool,
+
+    /// The color for the initial display list, shown as soon as the pixel
+    /// clock starts.
+    pub initial_color: RGB,
+
+    pub backlight: Option<Backlight>,
+    pub touch: Option<TouchConfig>,
+}
+
+impl BootConfig {
+    /// Returns a configuration for the given video timings, using the
+    /// internal clock source and with the backlight and touch registers left
+    /// at their reset values.
+    pub fn new(timings: VideoTimings) -> Self {
+        Self {
+            clock_source: ClockSource::Internal,
+            timings: timings,
+            pins: RGBElectricalMode::new(),
+            boot_poll_limit: 1000,
+            check_timings: true,
+            initial_color: RGB { r: 0, g: 0, b: 0 },
+            backlight: None,
+            touch: None,
+        }
+    }
+}
+
+/// Settings for the backlight PWM output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Backlight {
+    /// The PWM frequency in hertz, between 250 and 10000.
+    pub pwm_hz: u16,
+
+    /// The PWM duty cycle, between 0 (off) and 128 (fully on).
+    pub duty: u8,
+}
+
+/// Settings for the touch screen engine.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TouchConfig {
+    pub mode: TouchMode,
+
+    /// The resistance threshold for detecting a touch on a resistive touch
+    /// screen, or `None` to leave it at its reset value.
+    pub rz_threshold: Option<u16>,
+}
+
+/// Selects how often the touch screen engine samples.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TouchMode {
+    Off,
+    OneShot,
+    Frame,
+    Continuous,
+}
+
+impl TouchMode {
+    pub const fn reg_touch_mode_value(self) -> u8 {
+        match self {
+            TouchMode::Off => 0,
+            TouchMode::OneShot => 1,
+            TouchMode::Frame => 2,
+            TouchMode::Continuous => 3,
+        }
+    }
+}
+
+/// The state of a [`Boot`](Boot) before anything has been sent to the chip.
+pub enum Uninitialized {}
+
+/// The state of a [`Boot`](Boot) once the system clock is running and the
+/// chip has begun booting.
+pub enum ClockRunning {}
+
+/// The state of a [`Boot`](Boot) once the chip has finished booting.
+pub enum Booted {}
+
+/// The state of a [`Boot`](Boot) once the display is active, ready to start
+/// the coprocessor.
+pub enum VideoActive {}
+
+/// Drives the boot sequence for an EVE chip, one step at a time.
+///
+/// The type parameter `S` tracks which steps have already happened. Each
+/// step consumes the object and, if successful, returns a new object
+/// representing the following state. If a step fails, it returns a
+/// [`BootError`](BootError) from which you can recover the `EVE` object,
+/// but the chip is in an unknown state and so the boot must start again
+/// from the beginning.
+pub struct Boot<M: Model, I: Interface, S> {
+    eve: EVE<M, I>,
+    config: BootConfig,
+    _state: core::marker::PhantomData<S>,
+}
+
+impl<M: Model, I: Interface, S> Boot<M, I, S> {
+    fn next<S2>(self) -> Boot<M, I, S2> {
+        Boot {
+            eve: self.eve,
+            config: self.config,
+            _state: core::marker::PhantomData,
+        }
+    }
+
+    /// Abandons the boot sequence, returning the `EVE` object.
+    pub fn into_eve(self) -> EVE<M, I> {
+        self.eve
+    }
+
+    // Runs the given step, moving to state S2 if it succeeds.
+    fn step<S2, F>(mut self, f: F) -> Result<Boot<M, I, S2>, BootError<M, I>>
+    where
+        F: FnOnce(&mut EVE<M, I>, &BootConfig) -> Result<(), Error<I>>,
+    {
+        match f(&mut self.eve, &self.config) {
+            Ok(()) => Ok(self.next()),
+            Err(err) => Err(BootError {
+                error: err,
+                eve: self.eve,
+            }),
+        }
+    }
+}
+
+impl<M: Model, I: Interface> Boot<M, I, Uninitialized> {
+    pub(crate) fn new(eve: EVE<M, I>, config: BootConfig) -> Self {
+        Self {
+            eve: eve,
+            config: config,
+            _state: core::marker::PhantomData,
+        }
+    }
+
+    /// Resets the chip and then starts its system clock, which also begins
+    /// the chip's boot process.
+    ///
+    /// If the configuration calls for checking the video timings, this
+    /// returns [`Error::InvalidTimings`](Error::InvalidTimings) before
+    /// sending anything to the chip if they have problems.
+    pub fn start_clock(self) -> Result<Boot<M, I, ClockRunning>, BootError<M, I>> {
+        self.step(|eve, config| {
+            if config.check_timings {
+                config.timings.validate().map_err(Error::InvalidTimings)?;
+            }
+            eve.start_system_clock(config.clock_source, &config.timings)
+        })
+    }
+}
+
+impl<M: Model, I: Interface> Boot<M, I, ClockRunning> {
+    /// Polls the chip until it reports that it has finished booting,
+    /// returning [`Error::BootTimeout`](Error::BootTimeout) if that takes
+    /// longer than the configured poll limit.
+    pub fn wait_for_boot(self) -> Result<Boot<M, I, Booted>, BootError<M, I>> {
+        self.step(|eve, config| {
+            if !eve.poll_for_boot(config.boot_poll_limit)? {
+                return Err(Error::BootTimeout);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<M: Model, I: Interface> Boot<M, I, Booted> {
+    /// Configures the video pins, loads an initial display list, starts
+    /// the pixel clock, and then configures the backlight and touch screen
+    /// if requested.
+    pub fn start_video(self) -> Result<Boot<M, I, VideoActive>, BootError<M, I>> {
+        self.step(|eve, config| {
+            eve.configure_video_pins(&config.pins)?;
+            eve.new_display_list(|b| {
+                use crate::display_list::Builder;
+                b.clear_color_rgb(config.initial_color)?;
+                b.clear_all()?;
+                b.display()
+            })?;
+            eve.start_video(&config.timings)?;
+
+            if let Some(backlight) = config.backlight {
+                eve.set_backlight_frequency(backlight.pwm_hz)?;
+                eve.set_backlight(Brightness::Duty(backlight.duty))?;
+            }
+            let ll = eve.borrow_low_level();
+            if let Some(touch) = config.touch {
+                if let Some(threshold) = touch.rz_threshold {
+                    ll.wr16(M::reg_ptr(Register::TOUCH_RZTHRESH), threshold)?;
+                }
+                ll.wr8(
+                    M::reg_ptr(Register::TOUCH_MODE),
+                    touch.mode.reg_touch_mode_value(),
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<M: Model, I: Interface> Boot<M, I, VideoActive> {
+    /// Completes the boot sequence by starting the coprocessor, using the
+    /// given waiter. See [`EVE::coprocessor`](crate::EVE::coprocessor).
+    pub fn coprocessor<W: commands::waiter::Waiter<M, I>>(
+        self,
+        waiter: W,
+    ) -> commands::Result<commands::Coprocessor<M, I, W>, M, I, W> {
+        self.eve.coprocessor(waiter)
+    }
+
+    /// Completes the boot sequence by starting the coprocessor with a
+    /// busy-polling waiter. See
+    /// [`EVE::coprocessor_polling`](crate::EVE::coprocessor_polling).
+    pub fn coprocessor_polling(
+        self,
+    ) -> commands::Result<
+        commands::Coprocessor<M, I, commands::waiter::PollingWaiter<M, I>>,
+        M,
+        I,
+        commands::waiter::PollingWaiter<M, I>,
+    > {
+        self.eve.coprocessor_polling()
+    }
+}
+
+/// The error type for the steps of [`Boot`](Boot), which returns the `EVE`
+/// object along with the error so that the caller can try again.
+///
+/// This converts to [`Error`](Error) using `?`, discarding the `EVE` object.
+pub struct BootError<M: Model, I: Interface> {
+    pub error: Error<I>,
+    pub eve: EVE<M, I>,
+}
+
+impl<M: Model, I: Interface> BootError<M, I> {
+    /// Returns the `EVE` object, so that the boot can start again.
+    pub fn into_eve(self) -> EVE<M, I> {
+        self.eve
+    }
+}
+
+impl<M: Model, I: Interface> From<BootError<M, I>> for Error<I> {
+    fn from(err: BootError<M, I>) -> Self {
+        err.error
+    }
+}
+
+impl<M: Model, I: Interface> core::fmt::Debug for BootError<M, I>
+where
+    I::Error: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BootError")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::presets;
+    use crate::interface::fake::{Interface as FakeInterface, PowerState, TestBuffers};
+    use crate::models::fake::Model as FakeModel;
+
+    fn eve(bufs: &mut TestBuffers) -> EVE<FakeModel, FakeInterface<'_, FakeModel, &mut [u32]>> {
+        EVE::new(FakeModel, bufs.interface().with_host_simulation())
+    }
+
+    #[test]
+    fn test_boot() {
+        let mut bufs = TestBuffers::new();
+        let config = BootConfig {
+            backlight: Some(Backlight {
+                pwm_hz: 1000,
+                duty: 64,
+            }),
+            touch: Some(TouchConfig {
+                mode: TouchMode::Continuous,
+                rz_threshold: Some(1200),
+            }),
+            ..BootConfig::new(presets::WVGA_800X480)
+        };
+        {
+            let mut cp = eve(&mut bufs)
+                .boot(config)
+                .start_clock()
+                .unwrap()
+                .wait_for_boot()
+                .unwrap()
+                .start_video()
+                .unwrap()
+                .coprocessor_polling()
+                .unwrap();
+            cp.block_until_idle().unwrap();
+        }
+
+        let reg = |r: Register| bufs.regs[r.index()];
+        assert_eq!(reg(Register::FREQUENCY), 60_000_000);
+        assert_eq!(reg(Register::HSIZE), 800);
+        assert_eq!(reg(Register::VSIZE), 480);
+        assert_eq!(reg(Register::PCLK), 2);
+        assert_eq!(reg(Register::PWM_HZ), 1000);
+        assert_eq!(reg(Register::PWM_DUTY), 64);
+        assert_eq!(reg(Register::TOUCH_RZTHRESH), 1200);
+        assert_eq!(reg(Register::TOUCH_MODE), 3);
+    }
+
+    #[test]
+    fn test_boot_timeout() {
+        let mut bufs = TestBuffers::new();
+        let config = BootConfig {
+            boot_poll_limit: 1,
+            ..BootConfig::new(presets::WVGA_800X480)
+        };
+        let err = eve(&mut bufs)
+            .boot(config)
+            .start_clock()
+            .unwrap()
+            .wait_for_boot()
+            .err()
+            .unwrap();
+        assert!(
+            matches!(err.error, Error::BootTimeout),
+            "wrong error {:?}",
+            err
+        );
+
+        // The chip does eventually boot, so we can try again.
+        let config = BootConfig::new(presets::WVGA_800X480);
+        err.into_eve()
+            .boot(config)
+            .start_clock()
+            .unwrap()
+            .wait_for_boot()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_boot_invalid_timings() {
+        let mut bufs = TestBuffers::new();
+        let config = BootConfig::new(presets::GAMEDUINO_HDMI_720P);
+        let err = eve(&mut bufs).boot(config).start_clock().err().unwrap();
+        assert!(
+            matches!(err.error, Error::InvalidTimings(_)),
+            "wrong error {:?}",
+            err
+        );
+
+        // Nothing was sent to the chip, so it's still asleep.
+        let ei = err.into_eve().take_interface();
+        assert_eq!(ei.power_state(), Some(PowerState::Sleep));
+    }
+}
@@ -37,15 +37,31 @@
 //! # });
 //! ```
 
+pub(crate) mod async_coprocessor;
+pub mod command_list;
 pub(crate) mod coprocessor;
+pub mod decode;
 pub mod options;
 pub mod strfmt;
 pub mod waiter;
 
+mod batch;
 mod command_word;
+mod encode;
 
 #[doc(inline)]
-pub use coprocessor::{Coprocessor, Error, Result};
+pub use coprocessor::{Coprocessor, DisplayListFragment, Error, FaultKind, FaultMessage, Result};
+
+#[doc(inline)]
+pub use async_coprocessor::{
+    AsyncCoprocessor, DisplayListFragmentStart, Error as AsyncError, Result as AsyncResult,
+};
+
+#[doc(inline)]
+pub use command_list::CommandList;
+
+#[doc(inline)]
+pub use decode::{decode_commands, DecodedCommand};
 
 #[cfg(test)]
 mod tests {
@@ -87,6 +103,12 @@ mod tests {
                 Error::Unsupported => {
                     std::panic!("unsupported feature");
                 }
+                Error::Overflow => {
+                    std::panic!("overflow");
+                }
+                Error::Timeout => {
+                    std::panic!("timeout");
+                }
             },
         }
     }
@@ -184,6 +206,44 @@ mod tests {
         debug_assert_eq!(&got[..], &want[..]);
     }
 
+    #[test]
+    fn test_fade_backlight() {
+        use crate::config::Brightness;
+
+        let mut cp = test_obj(|_| {});
+
+        unwrap_copro(cp.fade_backlight(
+            Brightness::Duty(10),
+            Brightness::Duty(8),
+            core::time::Duration::from_millis(1),
+        ));
+
+        let ei = unwrap_copro(cp.take_interface());
+        let got = ei.calls();
+        let want = vec![
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::Write(0xFFFFFF1A), // CMD_MEMWRITE
+            MockInterfaceCall::Write(0x3020d4),   // REG_PWM_DUTY
+            MockInterfaceCall::Write(4),          // Number of bytes to write
+            MockInterfaceCall::Write(10),         // Initial brightness
+            MockInterfaceCall::Write(0xFFFFFF65), // CMD_WAIT
+            MockInterfaceCall::Write(500),        // Half of the duration
+            MockInterfaceCall::Write(0xFFFFFF1A), // CMD_MEMWRITE
+            MockInterfaceCall::Write(0x3020d4),   // REG_PWM_DUTY
+            MockInterfaceCall::Write(4),          // Number of bytes to write
+            MockInterfaceCall::Write(9),          // First step
+            MockInterfaceCall::Write(0xFFFFFF65), // CMD_WAIT
+            MockInterfaceCall::Write(500),        // Half of the duration
+            MockInterfaceCall::Write(0xFFFFFF1A), // CMD_MEMWRITE
+            MockInterfaceCall::Write(0x3020d4),   // REG_PWM_DUTY
+            MockInterfaceCall::Write(4),          // Number of bytes to write
+            MockInterfaceCall::Write(8),          // Final brightness
+            MockInterfaceCall::StopStream,
+        ];
+        debug_assert_eq!(&got[..], &want[..]);
+    }
+
     #[test]
     fn test_cold_start() {
         let mut cp = test_obj(|_| {});
@@ -453,6 +513,60 @@ mod tests {
         debug_assert_eq!(&got[..], &want[..]);
     }
 
+    #[test]
+    fn test_block_until_idle_fault() {
+        // An unaligned value in REG_CMDB_SPACE indicates that the
+        // coprocessor has encountered a fault.
+        let mut cp = test_obj(|ei| {
+            ei.current_space = 0xffa;
+        });
+
+        let result = cp.block_until_idle();
+        assert!(matches!(result, Err(Error::Fault)));
+
+        // The stream must be restarted even though waiting failed, so that
+        // the caller can go on to read the fault message.
+        let ei = unwrap_copro(cp.take_interface());
+        let got = ei.calls();
+        let want = vec![
+            MockInterfaceCall::ReadSpace(0xffa),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::StopStream,
+            MockInterfaceCall::ReadSpace(0xffa),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::StopStream,
+        ];
+        debug_assert_eq!(&got[..], &want[..]);
+    }
+
+    #[test]
+    fn test_block_screen_size() {
+        let mut cp = test_obj(|ei| {
+            ei.current_space = 4092;
+            // REG_HSIZE, REG_VSIZE and then REG_ROTATE selecting portrait.
+            ei.other_read_values = vec![800, 480, 2];
+        });
+
+        let result = unwrap_copro(cp.block_screen_size());
+        assert_eq!(result, (480, 800));
+
+        // The registers are read directly once the coprocessor is idle,
+        // with the stream stopped only once.
+        let ei = unwrap_copro(cp.take_interface());
+        let got = ei.calls();
+        let want = vec![
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::StopStream,
+            MockInterfaceCall::ReadOther(0x00302034, 800), // REG_HSIZE
+            MockInterfaceCall::ReadOther(0x00302048, 480), // REG_VSIZE
+            MockInterfaceCall::ReadOther(0x00302058, 2),   // REG_ROTATE
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::StopStream,
+        ];
+        debug_assert_eq!(&got[..], &want[..]);
+    }
+
     #[test]
     fn test_block_read_register() {
         let mut cp = test_obj(|ei| {
@@ -484,7 +598,7 @@ mod tests {
             MockInterfaceCall::StopStream,
             MockInterfaceCall::ReadWritePtr(12), // Faked pointer to end of command
             MockInterfaceCall::ReadSpace(4092),
-            MockInterfaceCall::ReadOther(0x00300008, 0xf33df4c3), // Address of the result
+            MockInterfaceCall::ReadOther(0x00308008, 0xf33df4c3), // Address of the result
             MockInterfaceCall::StartStream,
             MockInterfaceCall::StopStream,
         ];
@@ -497,6 +611,25 @@ mod tests {
         debug_assert_eq!(result, 0xf33df4c3);
     }
 
+    #[test]
+    fn test_block_read_register_wrapped() {
+        let mut cp = test_obj(|ei| {
+            ei.current_space = 4092;
+
+            // REG_CMD_WRITE has just wrapped around to the start of the
+            // ring buffer, so the result is in the buffer's final word.
+            ei.reg_cmd_write_value = 0;
+            ei.other_read_value = 0xf33df4c3;
+        });
+
+        let result = unwrap_copro(cp.block_read_register(crate::registers::Register::HCYCLE));
+
+        let ei = unwrap_copro(cp.take_interface());
+        let got = ei.calls();
+        debug_assert_eq!(got[8], MockInterfaceCall::ReadOther(0x00308ffc, 0xf33df4c3));
+        debug_assert_eq!(result, 0xf33df4c3);
+    }
+
     #[test]
     fn test_block_for_memory_crc() {
         let mut cp = test_obj(|ei| {
@@ -531,7 +664,7 @@ mod tests {
             MockInterfaceCall::StopStream,
             MockInterfaceCall::ReadWritePtr(12), // Faked pointer to end of command
             MockInterfaceCall::ReadSpace(4092),
-            MockInterfaceCall::ReadOther(0x00300008, 0xf33df4c3), // Address of the result
+            MockInterfaceCall::ReadOther(0x00308008, 0xf33df4c3), // Address of the result
             MockInterfaceCall::StartStream,
             MockInterfaceCall::StopStream,
         ];
@@ -580,6 +713,398 @@ mod tests {
         debug_assert_eq!(&got[..], &want[..]);
     }
 
+    #[test]
+    fn test_batch_buffer() {
+        let cp = test_obj(|_| {});
+        let mut cp = unwrap_copro(cp.with_batch_buffer::<4>());
+
+        unwrap_copro(cp.start_display_list());
+        unwrap_copro(cp.wait_microseconds(127));
+        // The buffer is now full, so it should've been flushed automatically.
+        unwrap_copro(cp.display_list_swap());
+        // This one stays in the buffer until we flush.
+        unwrap_copro(cp.append_raw_word(0xf4ce0001));
+        unwrap_copro(cp.flush());
+        // A second flush with nothing buffered shouldn't write anything.
+        unwrap_copro(cp.flush());
+        unwrap_copro(cp.start_display_list());
+        unwrap_copro(cp.display_list_swap());
+        // Blocking must send the buffered commands before waiting.
+        unwrap_copro(cp.block_until_idle());
+
+        let ei = unwrap_copro(cp.take_interface());
+        let got = ei.calls();
+        let want = vec![
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::Burst(4),
+            MockInterfaceCall::Write(0xffffff00), // CMD_DLSTART
+            MockInterfaceCall::Write(0xffffff65), // CMD_WAIT
+            MockInterfaceCall::Write(127),        // the duration value from above
+            MockInterfaceCall::Write(0xffffff01), // CMD_SWAP
+            MockInterfaceCall::Write(0xf4ce0001), // Junk data, flushed explicitly
+            MockInterfaceCall::Burst(2),
+            MockInterfaceCall::Write(0xffffff00), // CMD_DLSTART
+            MockInterfaceCall::Write(0xffffff01), // CMD_SWAP
+            MockInterfaceCall::StopStream,
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::StopStream,
+        ];
+        debug_assert_eq!(&got[..], &want[..]);
+    }
+
+    #[test]
+    fn test_command_list_replay() {
+        use crate::display_list::Builder;
+        use options::Options as _;
+        use strfmt::{Argument, Message};
+
+        let msg_args = [Argument::UInt(0xf33df4c3)];
+        let msg = || Message::new(b"hello %x!\0", &msg_args);
+
+        let mut direct = test_obj(|_| {});
+        unwrap_copro(direct.start_display_list());
+        unwrap_copro(direct.clear_all());
+        unwrap_copro(direct.draw_button(
+            (10, 20, 100, 12),
+            msg(),
+            options::FontRef::new_raw(31),
+            options::Button::new().style(options::WidgetStyle::Flat),
+        ));
+        unwrap_copro(direct.display());
+        unwrap_copro(direct.display_list_swap());
+        let direct_calls = unwrap_copro(direct.take_interface()).calls();
+
+        let mut buf = [0u32; 13];
+        let mut list = CommandList::<Exhaustive, _>::new(&mut buf[..]);
+        list.start_display_list().unwrap();
+        list.clear_all().unwrap();
+        list.draw_button(
+            (10, 20, 100, 12),
+            msg(),
+            options::FontRef::new_raw(31),
+            options::Button::new().style(options::WidgetStyle::Flat),
+        )
+        .unwrap();
+        list.display().unwrap();
+        list.display_list_swap().unwrap();
+        // There's only one word of space left now, so a two-word command
+        // must be rejected without recording any part of it.
+        assert_eq!(list.len(), 12);
+        assert_eq!(list.wait_microseconds(1), Err(command_list::Error::Full));
+        assert_eq!(list.len(), 12);
+
+        let mut replayed = test_obj(|_| {});
+        unwrap_copro(replayed.append_command_list(&list));
+        unwrap_copro(replayed.append_command_list(&list));
+        let replayed_calls = unwrap_copro(replayed.take_interface()).calls();
+
+        // The replayed list is sent as a single burst per replay, but
+        // the words themselves must be identical to the direct calls.
+        let mut want = vec![
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::Burst(12),
+        ];
+        let direct_words = direct_calls.iter().filter(|c| match c {
+            MockInterfaceCall::Write(_) => true,
+            _ => false,
+        });
+        want.extend(direct_words.clone().cloned());
+        want.push(MockInterfaceCall::Burst(12));
+        want.extend(direct_words.cloned());
+        want.push(MockInterfaceCall::StopStream);
+        debug_assert_eq!(&replayed_calls[..], &want[..]);
+    }
+
+    #[test]
+    fn test_command_list_replay_all_commands() {
+        use crate::config::Brightness;
+        use core::time::Duration;
+        use options::Options as _;
+
+        type MainMem = <Exhaustive as Model>::MainMem;
+        let frag = MainMem::ptr(0x400).slice_length(64);
+
+        let mut direct = test_obj(|_| {});
+        unwrap_copro(direct.trigger_cmdflag_interrupt(Duration::from_millis(5)));
+        unwrap_copro(direct.cold_start());
+        unwrap_copro(direct.append_display_list_from_main_mem(frag));
+        unwrap_copro(direct.write_register(crate::registers::Register::PWM_HZ, 250));
+        unwrap_copro(direct.write_gpio_levels(0x8003));
+        unwrap_copro(direct.write_memory(MainMem::ptr(0x10), b"hello"));
+        unwrap_copro(direct.write_memory_inflate(MainMem::ptr(0x20), b"\x78\x9c\x03\x00"));
+        unwrap_copro(direct.write_memory_image(
+            MainMem::ptr(0x30),
+            b"\xff\xd8\xff",
+            options::LoadImage::new(),
+        ));
+        unwrap_copro(direct.fade_backlight(
+            Brightness::Duty(10),
+            Brightness::Duty(13),
+            Duration::from_micros(300),
+        ));
+        let direct_words: Vec<u32> = unwrap_copro(direct.take_interface())
+            .calls()
+            .iter()
+            .filter_map(|c| match c {
+                MockInterfaceCall::Write(v) => Some(*v),
+                _ => None,
+            })
+            .collect();
+
+        let mut buf = [0u32; 64];
+        let mut list = CommandList::<Exhaustive, _>::new(&mut buf[..]);
+        list.trigger_cmdflag_interrupt(Duration::from_millis(5))
+            .unwrap();
+        list.cold_start().unwrap();
+        list.append_display_list_from_main_mem(frag).unwrap();
+        list.write_register(crate::registers::Register::PWM_HZ, 250)
+            .unwrap();
+        list.write_gpio_levels(0x8003).unwrap();
+        list.write_memory(MainMem::ptr(0x10), b"hello").unwrap();
+        list.write_memory_inflate(MainMem::ptr(0x20), b"\x78\x9c\x03\x00")
+            .unwrap();
+        list.write_memory_image(
+            MainMem::ptr(0x30),
+            b"\xff\xd8\xff",
+            options::LoadImage::new(),
+        )
+        .unwrap();
+        let before_fade = list.len();
+
+        // There isn't room for the whole fade, so none of it is recorded.
+        let result = list.fade_backlight(
+            Brightness::Duty(10),
+            Brightness::Duty(20),
+            Duration::from_micros(300),
+        );
+        assert_eq!(result, Err(command_list::Error::Full));
+        assert_eq!(list.len(), before_fade);
+
+        list.fade_backlight(
+            Brightness::Duty(10),
+            Brightness::Duty(13),
+            Duration::from_micros(300),
+        )
+        .unwrap();
+        assert_eq!(list.as_words(), &direct_words[..]);
+    }
+
+    #[test]
+    fn test_command_list_full_during_message() {
+        use options::Options;
+
+        let mut buf = [0u32; 6];
+        let mut list = CommandList::<Exhaustive, _>::new(&mut buf[..]);
+        list.start_display_list().unwrap();
+
+        // The header fits in the remaining five words, but the string
+        // doesn't, so nothing at all is recorded for this command.
+        let result = list.draw_text(
+            (10, 20),
+            strfmt::Message::new_literal(b"hello world!\0"),
+            options::FontRef::new_raw(31),
+            options::Text::new(),
+        );
+        assert_eq!(result, Err(command_list::Error::Full));
+        assert_eq!(list.len(), 1);
+
+        // A command that fits can still be recorded afterwards.
+        list.draw_text(
+            (10, 20),
+            strfmt::Message::new_literal(b"hi\0"),
+            options::FontRef::new_raw(31),
+            options::Text::new(),
+        )
+        .unwrap();
+        assert_eq!(list.len(), 5);
+    }
+
+    #[test]
+    fn test_command_list_replay_large() {
+        let mut buf = [0u32; 1500];
+        let mut list = CommandList::<Exhaustive, _>::new(&mut buf[..]);
+        for i in 0..1500 {
+            list.append_raw_word(i).unwrap();
+        }
+
+        let mut cp = test_obj(|_| {});
+        unwrap_copro(cp.append_command_list(&list));
+        let got = unwrap_copro(cp.take_interface()).calls();
+
+        // The list is too big to fit in the ring buffer all at once, so
+        // it must be split into two bursts with a wait for space between.
+        // Within each burst, the words are written 64 at a time while the
+        // stream stays open.
+        let writes = |want: &mut Vec<MockInterfaceCall>, words: core::ops::Range<u32>| {
+            for start in words.clone().step_by(64) {
+                let end = core::cmp::min(start + 64, words.end);
+                want.push(MockInterfaceCall::Burst((end - start) as usize));
+                want.extend((start..end).map(MockInterfaceCall::Write));
+            }
+        };
+        let mut want = vec![
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+        ];
+        writes(&mut want, 0..1023);
+        want.push(MockInterfaceCall::StopStream);
+        want.push(MockInterfaceCall::ReadSpace(4092));
+        want.push(MockInterfaceCall::StartStream);
+        writes(&mut want, 1023..1500);
+        want.push(MockInterfaceCall::StopStream);
+        debug_assert_eq!(&got[..], &want[..]);
+    }
+
+    #[test]
+    fn test_capture_display_list_fragment() {
+        use crate::display_list::Builder;
+        use crate::memory::MemoryRegion;
+
+        let mut cp = test_obj(|ei| {
+            ei.current_space = 4092;
+            ei.reg_cmd_write_value = 12;
+            // The two REG_CMD_DL values, before and after the closure runs.
+            ei.other_read_values = vec![8, 16];
+        });
+
+        let dest = <Exhaustive as Model>::MainMem::ptr(0x1000).slice_length(64);
+        let frag = unwrap_copro(cp.capture_display_list_fragment(dest, |cp| {
+            cp.point_size(160)?;
+            cp.vertex_2f((100, 100))
+        }));
+        assert_eq!(frag.len(), 8);
+        assert_eq!(
+            frag.slice(),
+            <Exhaustive as Model>::MainMem::ptr(0x1000).slice_length(8)
+        );
+        unwrap_copro(cp.append_display_list_from_main_mem(frag));
+
+        let ei = unwrap_copro(cp.take_interface());
+        let got = ei.calls();
+        let want = vec![
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::Write(0xFFFFFF19), // CMD_REGREAD
+            MockInterfaceCall::Write(0x00302100), // address of REG_CMD_DL
+            MockInterfaceCall::Write(0xf0f0f0f0), // placeholder data for result
+            MockInterfaceCall::StopStream,
+            MockInterfaceCall::ReadWritePtr(12),
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::ReadOther(0x00308008, 8), // REG_CMD_DL before
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::Write(0x0d0000a0), // POINT_SIZE(160)
+            MockInterfaceCall::Write(0x40320064), // VERTEX2F(100, 100)
+            MockInterfaceCall::Write(0xFFFFFF19), // CMD_REGREAD
+            MockInterfaceCall::Write(0x00302100), // address of REG_CMD_DL
+            MockInterfaceCall::Write(0xf0f0f0f0), // placeholder data for result
+            MockInterfaceCall::StopStream,
+            MockInterfaceCall::ReadWritePtr(12),
+            MockInterfaceCall::ReadSpace(4092),
+            MockInterfaceCall::ReadOther(0x00308008, 16), // REG_CMD_DL after
+            MockInterfaceCall::StartStream,
+            MockInterfaceCall::Write(0xFFFFFF1D), // CMD_MEMCPY
+            MockInterfaceCall::Write(0x00001000), // destination address
+            MockInterfaceCall::Write(0x00300008), // source address in RAM_DL
+            MockInterfaceCall::Write(8),          // length
+            MockInterfaceCall::Write(0xFFFFFF1E), // CMD_APPEND
+            MockInterfaceCall::Write(0x00001000), // fragment address
+            MockInterfaceCall::Write(8),          // fragment length
+            MockInterfaceCall::StopStream,
+        ];
+        debug_assert_eq!(&got[..], &want[..]);
+    }
+
+    #[test]
+    fn test_capture_display_list_fragment_overflow() {
+        use crate::memory::MemoryRegion;
+
+        let mut cp = test_obj(|ei| {
+            ei.current_space = 4092;
+            ei.reg_cmd_write_value = 12;
+            ei.other_read_values = vec![8, 32];
+        });
+
+        let dest = <Exhaustive as Model>::MainMem::ptr(0x1000).slice_length(16);
+        let result = cp.capture_display_list_fragment(dest, |_| Ok(()));
+        assert!(matches!(result, Err(Error::Overflow)));
+    }
+
+    #[test]
+    fn test_fault_kind() {
+        assert_eq!(
+            FaultKind::from_bytes(b"display list overflow"),
+            FaultKind::DisplayListOverflow
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"unsupported JPEG"),
+            FaultKind::UnsupportedJpeg
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"flash not attached"),
+            FaultKind::FlashNotAttached
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"something else"),
+            FaultKind::Other("something else")
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"bad \xff byte"),
+            FaultKind::Other("bad ")
+        );
+
+        // The text from a real chip may differ in prefix, case, spacing,
+        // added detail and truncation.
+        assert_eq!(
+            FaultKind::from_bytes(b"ERROR: display list must be empty\0\0"),
+            FaultKind::DisplayListMustBeEmpty
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"error:invalid  data size"),
+            FaultKind::InvalidDataSize
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"Invalid size"),
+            FaultKind::InvalidSize
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"ERROR: bad inflate data at 0x1234"),
+            FaultKind::BadInflateData
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"ERROR: flash not bl"),
+            FaultKind::FlashNotBlank
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"mediafifo underflow."),
+            FaultKind::MediaFifoUnderflow
+        );
+        assert_eq!(
+            FaultKind::from_bytes(b"ERROR: coprocessor exception"),
+            FaultKind::CoprocessorException
+        );
+        // A truncated message that could be more than one known message
+        // isn't guessed.
+        assert_eq!(
+            FaultKind::from_bytes(b"ERROR: display list"),
+            FaultKind::Other("ERROR: display list")
+        );
+        // Matching is by whole words, apart from a truncated final word.
+        assert_eq!(
+            FaultKind::from_bytes(b"invalid basement"),
+            FaultKind::Other("invalid basement")
+        );
+        assert_eq!(FaultKind::from_bytes(b"ERROR:"), FaultKind::Other("ERROR:"));
+
+        assert_eq!(
+            std::format!("{}", FaultKind::InvalidFormatCharacter),
+            "invalid format character"
+        );
+        assert_eq!(std::format!("{}", FaultKind::Other("hmm")), "hmm");
+    }
+
     /// A test double for `trait Interface`, available only in test mode.
     pub struct MockInterface {
         write_addr: Option<u32>,
@@ -589,6 +1114,11 @@ mod tests {
         pub(crate) reg_cmd_write_value: u32,
         pub(crate) other_read_value: u32,
 
+        // If other_read_values is non-empty then each "other" read consumes
+        // the first element, which then also becomes the new
+        // other_read_value.
+        pub(crate) other_read_values: Vec<u32>,
+
         // calls_ is the call log. Each call to a mock method appends one
         // entry to this vector, including any that fail.
         calls_: Vec<MockInterfaceCall>,
@@ -600,6 +1130,7 @@ mod tests {
         ReadWritePtr(u32),
         ReadOther(u32, u32),
         Write(u32),
+        Burst(usize),
         StartStream,
         StopStream,
     }
@@ -616,6 +1147,7 @@ mod tests {
                     write!(f, "ReadOther({:#010x?}, {:#x?})", addr, v)
                 }
                 MockInterfaceCall::Write(v) => write!(f, "Write({:#010x?})", v),
+                MockInterfaceCall::Burst(n) => write!(f, "Burst({})", n),
                 MockInterfaceCall::StartStream => write!(f, "StartStream"),
                 MockInterfaceCall::StopStream => write!(f, "StopStream"),
             }
@@ -634,6 +1166,7 @@ mod tests {
                 current_space: 0xffc,
                 reg_cmd_write_value: 0,
                 other_read_value: 0xffffffff,
+                other_read_values: Vec::new(),
                 calls_: Vec::new(),
             }
         }
@@ -672,14 +1205,24 @@ mod tests {
             match self.write_addr {
                 Some(addr) => {
                     if addr == Self::WRITE_ADDR {
-                        if buf.len() != 4 {
-                            return Err(MockError("must write to REG_CMDB_WRITE using wr32"));
+                        if buf.is_empty() || buf.len() % 4 != 0 {
+                            return Err(MockError(
+                                "must write to REG_CMDB_WRITE in whole 32-bit words",
+                            ));
+                        }
+                        if buf.len() > 4 {
+                            // A multi-word write is a batch of commands sent
+                            // all at once, so we note the burst size before
+                            // logging the individual words.
+                            self.calls_.push(MockInterfaceCall::Burst(buf.len() / 4));
+                        }
+                        for buf in buf.chunks_exact(4) {
+                            let v = (buf[0] as u32)
+                                | (buf[1] as u32) << 8
+                                | (buf[2] as u32) << 16
+                                | (buf[3] as u32) << 24;
+                            self.calls_.push(MockInterfaceCall::Write(v));
                         }
-                        let v = (buf[0] as u32)
-                            | (buf[1] as u32) << 8
-                            | (buf[2] as u32) << 16
-                            | (buf[3] as u32) << 24;
-                        self.calls_.push(MockInterfaceCall::Write(v));
                     }
                     // We ignore all other writes because they aren't relevant
                     // to our coprocessor testing.
@@ -744,6 +1287,9 @@ mod tests {
                             into[3] = (self.reg_cmd_write_value >> 24) as u8;
                         }
                         _ => {
+                            if !self.other_read_values.is_empty() {
+                                self.other_read_value = self.other_read_values.remove(0);
+                            }
                             match into.len() {
                                 1 => {
                                     self.calls_.push(MockInterfaceCall::ReadOther(
@@ -835,6 +1381,13 @@ mod tests {
                         false
                     }
                 }
+                MockInterfaceCall::Burst(self_n) => {
+                    if let MockInterfaceCall::Burst(other_n) = other {
+                        *self_n == *other_n
+                    } else {
+                        false
+                    }
+                }
                 MockInterfaceCall::StartStream => {
                     if let MockInterfaceCall::StartStream = other {
                         true
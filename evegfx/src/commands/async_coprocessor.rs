@@ -0,0 +1,850 @@
+use crate::commands::coprocessor::{DisplayListFragment, FaultMessage, FaultMessageRaw};
+use crate::commands::encode;
+use crate::commands::waiter::{AsyncWaiter, WaiterError};
+use crate::commands::{options, strfmt};
+use crate::display_list::DLCmd;
+use crate::interface::AsyncInterface;
+use crate::memory::{MemoryRegion, Ptr, Slice};
+use crate::models::Model;
+use crate::registers::Register;
+
+// The maximum number of bytes we'll send to REG_CMDB_WRITE in a single
+// transaction. This must be a multiple of four.
+const CHUNK_LEN: usize = 256;
+
+/// The `async` equivalent of [`Coprocessor`](crate::commands::Coprocessor),
+/// for use on systems with an async executor.
+///
+/// This supports most of the methods of `Coprocessor`, with the same
+/// meaning but as `async fn`. Methods that would take a closure, such as
+/// [`Coprocessor::capture_display_list_fragment`](crate::commands::Coprocessor::capture_display_list_fragment),
+/// are instead split into a pair of methods to call before and after the
+/// commands in question. It uses an
+/// [`AsyncInterface`](crate::interface::AsyncInterface) to communicate with
+/// the EVE chip and an [`AsyncWaiter`](crate::commands::waiter::AsyncWaiter)
+/// to wait for space in the command ring buffer, so that other tasks can run
+/// while the coprocessor is busy.
+///
+/// Because `AsyncInterface` deals only in whole transactions, each command
+/// is sent to `REG_CMDB_WRITE` in a transaction of its own, rather than as
+/// part of a long-lived write burst.
+pub struct AsyncCoprocessor<M: Model, I: AsyncInterface, W: AsyncWaiter<M, I>> {
+    ei: I,
+    wait: W,
+    known_space: u16,
+    _model: core::marker::PhantomData<M>,
+}
+
+impl<M: Model, I: AsyncInterface, W: AsyncWaiter<M, I>> AsyncCoprocessor<M, I, W> {
+    /// Consumes the given interface and waiter and returns an interface to
+    /// the coprocessor via the given interface.
+    ///
+    /// Like [`Coprocessor::new`](crate::commands::Coprocessor::new), this
+    /// begins by resetting the coprocessor so that it's in a known state.
+    pub async fn new(ei: I, wait: W) -> Result<Self, M, I, W> {
+        let mut ret = Self {
+            ei: ei,
+            wait: wait,
+            known_space: 0,
+            _model: core::marker::PhantomData,
+        };
+        ret.write_reg(Register::CPURESET, &[0b001]).await?;
+        ret.write_reg(Register::CPURESET, &[0b000]).await?;
+        ret.synchronize().await?;
+        Ok(ret)
+    }
+
+    /// Consumes the coprocessor object and returns the interface it was
+    /// created with.
+    pub fn take_interface(self) -> I {
+        self.ei
+    }
+
+    /// Runs the given closure with access to the underlying interface, and
+    /// then resynchronizes with the coprocessor state in case the closure
+    /// changed it.
+    pub async fn with_interface<R, F: FnOnce(&mut I) -> R>(&mut self, f: F) -> Result<R, M, I, W> {
+        let result = f(&mut self.ei);
+        self.synchronize().await?;
+        Ok(result)
+    }
+
+    #[inline]
+    pub fn space_when_empty() -> u16 {
+        4092
+    }
+
+    pub async fn append_raw_word(&mut self, word: u32) -> Result<(), M, I, W> {
+        self.write_words(&[word]).await
+    }
+
+    pub async fn append_display_list(&mut self, cmd: DLCmd) -> Result<(), M, I, W> {
+        self.write_words(&[cmd.as_raw()]).await
+    }
+
+    /// Sends just the coprocessor command to begin a new display list.
+    ///
+    /// See [`Coprocessor::start_display_list`](crate::commands::Coprocessor::start_display_list).
+    pub async fn start_display_list(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::start_display_list()).await
+    }
+
+    /// Sends just the coprocessor command to swap in the newly-populated
+    /// display list commands.
+    pub async fn display_list_swap(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::display_list_swap()).await
+    }
+
+    /// Waits for at least the given delay and then has the coprocessor
+    /// trigger the EVE interrupt `CMDFLAG`.
+    ///
+    /// See [`Coprocessor::trigger_cmdflag_interrupt`](crate::commands::Coprocessor::trigger_cmdflag_interrupt).
+    pub async fn trigger_cmdflag_interrupt(
+        &mut self,
+        delay: core::time::Duration,
+    ) -> Result<(), M, I, W> {
+        self.write_words(&encode::trigger_cmdflag_interrupt(delay))
+            .await
+    }
+
+    /// Resets the coprocessor's state to the boot-time defaults before
+    /// continuing with later commands.
+    pub async fn cold_start(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::cold_start()).await
+    }
+
+    /// Appends display list commands from the given slice of main memory.
+    ///
+    /// See [`Coprocessor::append_display_list_from_main_mem`](crate::commands::Coprocessor::append_display_list_from_main_mem).
+    pub async fn append_display_list_from_main_mem<S: Into<Slice<M::MainMem>>>(
+        &mut self,
+        slice: S,
+    ) -> Result<(), M, I, W> {
+        self.write_words(&encode::append_display_list_from_main_mem(slice.into()))
+            .await
+    }
+
+    /// Begins capturing a display list fragment, returning a token to pass
+    /// to [`end_display_list_fragment`](Self::end_display_list_fragment)
+    /// once all of the fragment's commands have been sent.
+    ///
+    /// Together these have the same meaning as
+    /// [`Coprocessor::capture_display_list_fragment`](crate::commands::Coprocessor::capture_display_list_fragment),
+    /// including waiting until the coprocessor has completed all of the
+    /// commands issued so far.
+    pub async fn begin_display_list_fragment(
+        &mut self,
+    ) -> Result<DisplayListFragmentStart, M, I, W> {
+        let start = self.block_read_register(Register::CMD_DL).await?;
+        Ok(DisplayListFragmentStart { start: start })
+    }
+
+    /// Completes capturing a display list fragment started by
+    /// [`begin_display_list_fragment`](Self::begin_display_list_fragment),
+    /// copying the display list commands generated since then into the
+    /// given slice of main memory.
+    ///
+    /// If the generated commands don't fit in the slice then this returns
+    /// [`Error::Overflow`](crate::error::AsyncCoprocessorError::Overflow)
+    /// and leaves the slice unchanged.
+    pub async fn end_display_list_fragment<S: Into<Slice<M::MainMem>>>(
+        &mut self,
+        start: DisplayListFragmentStart,
+        dest: S,
+    ) -> Result<DisplayListFragment<M>, M, I, W> {
+        let dest: Slice<M::MainMem> = dest.into();
+        let end = self.block_read_register(Register::CMD_DL).await?;
+        let len = end.wrapping_sub(start.start);
+        if len > dest.len() {
+            return Err(Error::Overflow);
+        }
+        self.write_words(&encode::copy_display_list_fragment::<M>(
+            dest,
+            start.start,
+            len,
+        ))
+        .await?;
+        Ok(DisplayListFragment {
+            slice: dest.start().slice_length(len),
+        })
+    }
+
+    pub async fn write_register(&mut self, reg: Register, v: u32) -> Result<(), M, I, W> {
+        self.write_words(&encode::write_register::<M>(reg, v)).await
+    }
+
+    /// Sets the output levels of all of the GPIO pins at once. See
+    /// [`Coprocessor::write_gpio_levels`](crate::commands::Coprocessor::write_gpio_levels).
+    pub async fn write_gpio_levels(&mut self, levels: u16) -> Result<(), M, I, W> {
+        self.write_words(&encode::write_gpio_levels::<M>(levels))
+            .await
+    }
+
+    /// Writes raw data from host memory into locations in the
+    /// directly-addressable part of the EVE memory space.
+    ///
+    /// See [`Coprocessor::write_memory`](crate::commands::Coprocessor::write_memory).
+    pub async fn write_memory<R>(&mut self, to: Ptr<R>, from: &[u8]) -> Result<(), M, I, W>
+    where
+        R: crate::memory::MemoryRegion + crate::memory::HostAccessible,
+    {
+        self.write_words(&encode::write_memory(to.to_raw(), from.len() as u32))
+            .await?;
+        self.write_padded_bytes(from).await
+    }
+
+    // Writes the given data following a command header, padded to a multiple
+    // of four bytes.
+    async fn write_padded_bytes(&mut self, from: &[u8]) -> Result<(), M, I, W> {
+        let mut buf = [0u8; CHUNK_LEN];
+        for chunk in from.chunks(CHUNK_LEN) {
+            // The coprocessor expects the data to be padded to a multiple
+            // of four bytes, and only the final chunk can be unaligned.
+            let len = (chunk.len() + 3) & !3;
+            buf[..chunk.len()].copy_from_slice(chunk);
+            for b in buf[chunk.len()..len].iter_mut() {
+                *b = 0;
+            }
+            self.write_bytes(&buf[..len]).await?;
+        }
+        Ok(())
+    }
+
+    /// Similar to [`write_memory`](Self::write_memory), but for compressed
+    /// data.
+    ///
+    /// See [`Coprocessor::write_memory_inflate`](crate::commands::Coprocessor::write_memory_inflate).
+    pub async fn write_memory_inflate<R>(&mut self, to: Ptr<R>, from: &[u8]) -> Result<(), M, I, W>
+    where
+        R: crate::memory::MemoryRegion + crate::memory::HostAccessible,
+    {
+        self.write_words(&encode::write_memory_inflate(to.to_raw()))
+            .await?;
+        self.write_padded_bytes(from).await
+    }
+
+    /// Similar to [`write_memory`](Self::write_memory), but specifically for
+    /// JPEG or PNG images.
+    ///
+    /// See [`Coprocessor::write_memory_image`](crate::commands::Coprocessor::write_memory_image).
+    pub async fn write_memory_image<R>(
+        &mut self,
+        to: Ptr<R>,
+        from: &[u8],
+        opts: options::LoadImage,
+    ) -> Result<(), M, I, W>
+    where
+        R: crate::memory::MemoryRegion + crate::memory::HostAccessible,
+    {
+        self.write_words(&encode::write_memory_image(to.to_raw(), opts))
+            .await?;
+        self.write_padded_bytes(from).await
+    }
+
+    pub async fn show_testcard(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::show_testcard()).await
+    }
+
+    pub async fn show_manufacturer_logo(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::show_manufacturer_logo()).await
+    }
+
+    pub async fn start_spinner(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::start_spinner()).await
+    }
+
+    /// Has the coprocessor draw a button. See
+    /// [`Coprocessor::draw_button`](crate::commands::Coprocessor::draw_button).
+    pub async fn draw_button<Rect: Into<crate::graphics::WidgetRect>>(
+        &mut self,
+        rect: Rect,
+        msg: strfmt::Message<'_, '_, M::MainMem>,
+        font: options::FontRef,
+        options: options::Button,
+    ) -> Result<(), M, I, W> {
+        let words = encode::draw_button(rect.into(), &msg, font, options);
+        self.write_words_with_message(&words, &msg).await
+    }
+
+    /// Has the coprocessor draw a text message. See
+    /// [`Coprocessor::draw_text`](crate::commands::Coprocessor::draw_text).
+    pub async fn draw_text<Pos: Into<crate::graphics::WidgetPos>>(
+        &mut self,
+        pos: Pos,
+        msg: strfmt::Message<'_, '_, M::MainMem>,
+        font: options::FontRef,
+        options: options::Text,
+    ) -> Result<(), M, I, W> {
+        let words = encode::draw_text(pos.into(), &msg, font, options);
+        self.write_words_with_message(&words, &msg).await
+    }
+
+    /// Sends all of the commands previously recorded in the given
+    /// [`CommandList`](super::CommandList).
+    ///
+    /// See [`Coprocessor::append_command_list`](crate::commands::Coprocessor::append_command_list).
+    pub async fn append_command_list<S: super::command_list::Storage>(
+        &mut self,
+        list: &super::CommandList<M, S>,
+    ) -> Result<(), M, I, W> {
+        self.write_word_iter(list.words()).await
+    }
+
+    pub async fn wait_microseconds(&mut self, delay: u32) -> Result<(), M, I, W> {
+        self.write_words(&encode::wait_microseconds(delay)).await
+    }
+
+    /// Changes the orientation of the display. See
+    /// [`Coprocessor::set_rotation`](super::Coprocessor::set_rotation).
+    pub async fn set_rotation(
+        &mut self,
+        rotation: crate::graphics::Rotation,
+    ) -> Result<(), M, I, W> {
+        self.write_words(&encode::set_rotation(rotation)).await
+    }
+
+    pub async fn wait_video_scanout(&mut self) -> Result<(), M, I, W> {
+        self.write_words(&encode::wait_video_scanout()).await
+    }
+
+    /// Sends commands that have the coprocessor fade the display backlight
+    /// from one brightness to another over the given duration.
+    ///
+    /// See [`Coprocessor::fade_backlight`](crate::commands::Coprocessor::fade_backlight).
+    pub async fn fade_backlight(
+        &mut self,
+        from: crate::config::Brightness,
+        to: crate::config::Brightness,
+        duration: core::time::Duration,
+    ) -> Result<(), M, I, W> {
+        let fade = encode::fade_backlight(from, to, duration);
+        self.write_register(Register::PWM_DUTY, fade.initial)
+            .await?;
+        let step_delay = fade.step_delay;
+        for duty in fade {
+            self.wait_microseconds(step_delay).await?;
+            self.write_register(Register::PWM_DUTY, duty).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits until the coprocessor buffer is empty, signalling that the
+    /// coprocessor has completed all of the commands issued so far.
+    pub async fn block_until_idle(&mut self) -> Result<(), M, I, W> {
+        self.ensure_space(Self::space_when_empty()).await
+    }
+
+    /// Waits until the coprocessor has completed all of the commands issued
+    /// so far and then returns the logical width and height of the screen,
+    /// taking into account the current rotation.
+    pub async fn block_screen_size(&mut self) -> Result<(u16, u16), M, I, W> {
+        self.block_until_idle().await?;
+
+        // Once the coprocessor is idle we can read the registers directly,
+        // rather than asking the coprocessor to read each one for us.
+        let w = self.read_reg16(Register::HSIZE).await?;
+        let h = self.read_reg16(Register::VSIZE).await?;
+        let rotation = self.read_reg16(Register::ROTATE).await?;
+        let rotation = crate::graphics::Rotation::from_raw(rotation as u8);
+        Ok(rotation.logical_size((w, h)))
+    }
+
+    /// Waits until EVE has finished scanning out the current frame.
+    ///
+    /// See [`Coprocessor::block_until_video_scanout`](crate::commands::Coprocessor::block_until_video_scanout).
+    pub async fn block_until_video_scanout(&mut self) -> Result<(), M, I, W> {
+        self.wait_video_scanout().await?;
+        self.block_until_idle().await
+    }
+
+    /// Waits until the coprocessor has completed all of the commands issued
+    /// so far and then returns the value of the given system register.
+    pub async fn block_read_register(&mut self, reg: Register) -> Result<u32, M, I, W> {
+        self.write_words(&encode::read_register::<M>(reg)).await?;
+        self.block_for_output_value().await
+    }
+
+    /// Waits until the coprocessor has completed all of the commands issued
+    /// so far and then calculates the CRC32 checksum of the memory covered
+    /// by the given slice.
+    pub async fn block_for_memory_crc<R, S>(&mut self, region: S) -> Result<u32, M, I, W>
+    where
+        R: crate::memory::MemoryRegion,
+        S: Into<Slice<R>>,
+    {
+        self.write_words(&encode::memory_crc(region.into())).await?;
+        self.block_for_output_value().await
+    }
+
+    // Waits until the coprocessor has completed the most recently-written
+    // command and then returns the value that it wrote over that command's
+    // final word in the ring buffer.
+    async fn block_for_output_value(&mut self) -> Result<u32, M, I, W> {
+        let write_addr = self.read_reg(Register::CMD_WRITE).await?;
+        self.block_until_idle().await?;
+
+        // REG_CMD_WRITE might have just wrapped around, in which case the
+        // final word is at the very end of the ring buffer.
+        let ring_len = M::CommandMem::LENGTH;
+        let result_offset = write_addr.wrapping_sub(4) & (ring_len - 1);
+        let mut raw = [0u8; 4];
+        let ptr = M::CommandMem::ptr(result_offset).to_raw();
+        Self::interface_result(self.ei.read(ptr, &mut raw).await)?;
+        Ok(u32::from_le_bytes(raw))
+    }
+
+    async fn write_words(&mut self, words: &[u32]) -> Result<(), M, I, W> {
+        self.write_word_iter(words.iter().copied()).await
+    }
+
+    // Like `write_words`, but also writes the format string and arguments
+    // from the given message after the given words.
+    async fn write_words_with_message<R: crate::memory::MainMem>(
+        &mut self,
+        words: &[u32],
+        msg: &strfmt::Message<'_, '_, R>,
+    ) -> Result<(), M, I, W> {
+        let words = words
+            .iter()
+            .copied()
+            .chain(encode::message_format(msg))
+            .chain(encode::message_arguments(msg));
+        self.write_word_iter(words).await
+    }
+
+    // Writes the words from the given iterator in transactions of up to
+    // CHUNK_LEN bytes each.
+    async fn write_word_iter<Iter>(&mut self, words: Iter) -> Result<(), M, I, W>
+    where
+        Iter: Iterator<Item = u32>,
+    {
+        let mut buf = [0u8; CHUNK_LEN];
+        let mut len = 0;
+        for word in words {
+            buf[len..len + 4].copy_from_slice(&word.to_le_bytes());
+            len += 4;
+            if len == CHUNK_LEN {
+                self.write_bytes(&buf).await?;
+                len = 0;
+            }
+        }
+        if len > 0 {
+            self.write_bytes(&buf[..len]).await?;
+        }
+        Ok(())
+    }
+
+    // Writes the given bytes, whose length must be a multiple of four, to
+    // REG_CMDB_WRITE once there's enough space for them.
+    async fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), M, I, W> {
+        let len = bytes.len() as u16;
+        self.ensure_space(len).await?;
+        let ptr = M::reg_ptr(Register::CMDB_WRITE).to_raw();
+        Self::interface_result(self.ei.write(ptr, bytes).await)?;
+        self.known_space -= len;
+        Ok(())
+    }
+
+    async fn ensure_space(&mut self, need: u16) -> Result<(), M, I, W> {
+        if self.known_space >= need {
+            return Ok(());
+        }
+        match self.wait.wait_for_space(&mut self.ei, need).await {
+            Ok(known_space) => {
+                self.known_space = known_space;
+                Ok(())
+            }
+            Err(err) => {
+                // We don't know how much space we have, so we'll set it
+                // to zero to force calling the waiter again next time.
+                self.known_space = 0;
+                Err(match err {
+                    WaiterError::Comm(err) => Error::Waiter(err),
+                    WaiterError::Fault => Error::Fault,
+                    WaiterError::Timeout => Error::Timeout,
+                })
+            }
+        }
+    }
+
+    async fn synchronize(&mut self) -> Result<(), M, I, W> {
+        self.known_space = self.read_reg16(Register::CMDB_SPACE).await?;
+        Ok(())
+    }
+
+    async fn read_reg16(&mut self, reg: Register) -> Result<u16, M, I, W> {
+        let mut raw = [0u8; 2];
+        let ptr = M::reg_ptr(reg).to_raw();
+        Self::interface_result(self.ei.read(ptr, &mut raw).await)?;
+        Ok(u16::from_le_bytes(raw))
+    }
+
+    async fn read_reg(&mut self, reg: Register) -> Result<u32, M, I, W> {
+        let mut raw = [0u8; 4];
+        let ptr = M::reg_ptr(reg).to_raw();
+        Self::interface_result(self.ei.read(ptr, &mut raw).await)?;
+        Ok(u32::from_le_bytes(raw))
+    }
+
+    async fn write_reg(&mut self, reg: Register, data: &[u8]) -> Result<(), M, I, W> {
+        let ptr = M::reg_ptr(reg).to_raw();
+        Self::interface_result(self.ei.write(ptr, data).await)
+    }
+
+    fn interface_result<T>(result: core::result::Result<T, I::Error>) -> Result<T, M, I, W> {
+        result.map_err(Error::Interface)
+    }
+}
+
+/// These methods are available only when working with a model that has a
+/// coprocessor error message memory space.
+impl<M, I, W> AsyncCoprocessor<M, I, W>
+where
+    M: Model + crate::models::WithCommandErrMem,
+    I: AsyncInterface,
+    W: AsyncWaiter<M, I>,
+{
+    /// Returns the fault message currently available in the EVE coprocessor's
+    /// fault message memory space.
+    ///
+    /// See [`Coprocessor::coprocessor_fault_msg`](crate::commands::Coprocessor::coprocessor_fault_msg).
+    pub async fn coprocessor_fault_msg(
+        &mut self,
+    ) -> Result<FaultMessage<M::CommandErrMem>, M, I, W> {
+        use crate::memory::CommandErrMem;
+        use crate::models::WithCommandErrMem;
+
+        let mut raw = <<M as WithCommandErrMem>::CommandErrMem as CommandErrMem>::RawMessage::new();
+        let ptr = <<M as WithCommandErrMem>::CommandErrMem as MemoryRegion>::ptr(0).to_raw();
+        Self::interface_result(self.ei.read(ptr, raw.as_storage_bytes()).await)?;
+        Ok(FaultMessage::new(raw))
+    }
+
+    /// Recovers from a coprocessor fault, returning the fault message that
+    /// the coprocessor reported.
+    ///
+    /// See [`Coprocessor::recover_from_fault`](crate::commands::Coprocessor::recover_from_fault).
+    pub async fn recover_from_fault(&mut self) -> Result<FaultMessage<M::CommandErrMem>, M, I, W> {
+        let msg = self.coprocessor_fault_msg().await?;
+
+        let patch_ptr = self.read_reg16(Register::COPRO_PATCH_PTR).await?;
+        self.write_reg(Register::CPURESET, &[0b001]).await?;
+        self.write_reg(Register::CMD_READ, &[0, 0, 0, 0]).await?;
+        self.write_reg(Register::CMD_WRITE, &[0, 0, 0, 0]).await?;
+        self.write_reg(Register::CMD_DL, &[0, 0, 0, 0]).await?;
+        self.write_reg(Register::CPURESET, &[0b000]).await?;
+        self.write_reg(Register::COPRO_PATCH_PTR, &patch_ptr.to_le_bytes())
+            .await?;
+
+        self.synchronize().await?;
+        Ok(msg)
+    }
+}
+
+/// Marks the start of a display list fragment, as returned by
+/// [`AsyncCoprocessor::begin_display_list_fragment`].
+#[derive(Debug)]
+pub struct DisplayListFragmentStart {
+    start: u32,
+}
+
+#[doc(inline)]
+pub use crate::error::AsyncCoprocessorError as Error;
+
+pub type Result<T, M, I, W> = core::result::Result<T, Error<M, I, W>>;
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::commands::waiter::YieldingWaiter;
+    use crate::commands::{CommandList, FaultKind};
+    use crate::graphics::Rotation;
+    use crate::interface::fake::{Interface as FakeInterface, TestBuffers};
+    use crate::models::fake::{DisplayListMem, MainMem, Model as FakeModel};
+    use options::Options;
+    use std::vec::Vec;
+
+    type TestInterface<'a> = FakeInterface<'a, FakeModel, &'a mut [u32]>;
+    type TestWaiter<'a> = YieldingWaiter<FakeModel, TestInterface<'a>>;
+    type TestError<'a> = Error<FakeModel, TestInterface<'a>, TestWaiter<'a>>;
+    type TestCoprocessor<'a> = AsyncCoprocessor<
+        FakeModel,
+        TestInterface<'a>,
+        YieldingWaiter<FakeModel, TestInterface<'a>>,
+    >;
+
+    fn coprocessor(bufs: &mut TestBuffers) -> TestCoprocessor<'_> {
+        let (cp, _) = block_on(AsyncCoprocessor::new(
+            bufs.interface(),
+            YieldingWaiter::new(),
+        ));
+        cp.unwrap()
+    }
+
+    // Runs the given future to completion, returning its result along with
+    // the number of times it returned `Pending`.
+    fn block_on<F: core::future::Future>(f: F) -> (F::Output, usize) {
+        let mut f = core::pin::pin!(f);
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        let mut pending = 0;
+        loop {
+            if let core::task::Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                return (v, pending);
+            }
+            pending += 1;
+        }
+    }
+
+    #[test]
+    fn test_display_list() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = coprocessor(&mut bufs);
+        let (result, _) = block_on(async {
+            cp.start_display_list().await?;
+            cp.append_display_list(DLCmd::CLEAR_ALL).await?;
+            cp.append_display_list(DLCmd::DISPLAY).await?;
+            cp.display_list_swap().await?;
+            cp.block_until_idle().await
+        });
+        result.unwrap();
+
+        let mut ei = cp.take_interface();
+        let mut got = [0u8; 8];
+        crate::interface::Interface::read(&mut ei, DisplayListMem::BASE_ADDR, &mut got).unwrap();
+        let mut want = [0u8; 8];
+        want[..4].copy_from_slice(&DLCmd::CLEAR_ALL.as_raw().to_le_bytes());
+        want[4..].copy_from_slice(&DLCmd::DISPLAY.as_raw().to_le_bytes());
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_output_values() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = coprocessor(&mut bufs);
+        let (result, _) = block_on(async {
+            cp.write_register(Register::MACRO_0, 0x12345678).await?;
+            let reg = cp.block_read_register(Register::MACRO_0).await?;
+            cp.write_memory(MainMem::ptr(0), b"hello").await?;
+            let crc = cp
+                .block_for_memory_crc(MainMem::ptr(0).slice_length(5))
+                .await?;
+            Ok::<_, TestError>((reg, crc))
+        });
+        let (reg, crc) = result.unwrap();
+        assert_eq!(reg, 0x12345678);
+        assert_eq!(crc, 0x3610a686); // CRC32 of "hello"
+    }
+
+    #[test]
+    fn test_waiter_yields() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = coprocessor(&mut bufs);
+        let (result, pending) = block_on(async {
+            // Resynchronizing consumes one of the delayed reads, so the
+            // waiter must then poll three more times.
+            cp.with_interface(|ei| ei.delay_coprocessor_space(3))
+                .await?;
+            cp.append_display_list(DLCmd::DISPLAY).await
+        });
+        result.unwrap();
+        assert_eq!(pending, 2);
+    }
+
+    #[test]
+    fn test_recover_from_fault() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = coprocessor(&mut bufs);
+        let (result, _) = block_on(async {
+            cp.append_raw_word(0xffffff99).await?;
+            cp.block_until_idle().await
+        });
+        assert!(
+            matches!(result, Err(Error::Fault)),
+            "wrong result {:?}",
+            result
+        );
+
+        let (result, _) = block_on(async {
+            let msg = cp.recover_from_fault().await?;
+            assert_eq!(msg.kind(), FaultKind::Other("unknown command 0xffffff99"));
+            cp.write_register(Register::MACRO_0, 5).await?;
+            cp.block_read_register(Register::MACRO_0).await
+        });
+        assert_eq!(result.unwrap(), 5);
+    }
+
+    #[test]
+    fn test_same_words_as_command_list() {
+        use strfmt::{Argument, Message};
+        let args = [Argument::Int(-3)];
+        let msg = || Message::new(b"count %d\0", &args);
+
+        let mut buf = [0u32; 64];
+        let mut list: CommandList<FakeModel, _> = CommandList::new(&mut buf[..]);
+        list.show_testcard().unwrap();
+        list.draw_text(
+            (10, 20),
+            msg(),
+            options::FontRef::new_raw(18),
+            options::Text::new(),
+        )
+        .unwrap();
+        list.draw_button(
+            (1, 2, 3, 4),
+            Message::new_literal(b"ok\0"),
+            options::FontRef::new_raw(20),
+            options::Button::new(),
+        )
+        .unwrap();
+        list.wait_video_scanout().unwrap();
+        list.set_rotation(Rotation::Portrait).unwrap();
+
+        let (result, _) = block_on(async {
+            let mut cp = AsyncCoprocessor::<FakeModel, _, _>::new(
+                RecordingInterface::default(),
+                YieldingWaiter::new(),
+            )
+            .await?;
+            cp.show_testcard().await?;
+            cp.draw_text(
+                (10, 20),
+                msg(),
+                options::FontRef::new_raw(18),
+                options::Text::new(),
+            )
+            .await?;
+            cp.draw_button(
+                (1, 2, 3, 4),
+                Message::new_literal(b"ok\0"),
+                options::FontRef::new_raw(20),
+                options::Button::new(),
+            )
+            .await?;
+            cp.wait_video_scanout().await?;
+            cp.set_rotation(Rotation::Portrait).await?;
+            cp.append_command_list(&list).await?;
+            Ok::<_, Error<_, _, _>>(cp.take_interface())
+        });
+        let ei = result.unwrap();
+
+        // The directly-sent commands are followed by the same commands
+        // replayed from the list.
+        let want: Vec<u8> = list.words().flat_map(u32::to_le_bytes).collect();
+        let want = &want[..];
+        assert_eq!(ei.cmd.len(), want.len() * 2);
+        assert_eq!(&ei.cmd[..want.len()], want);
+        assert_eq!(&ei.cmd[want.len()..], want);
+    }
+
+    #[test]
+    fn test_display_list_fragment() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = coprocessor(&mut bufs);
+        let (result, _) = block_on(async {
+            cp.start_display_list().await?;
+            let start = cp.begin_display_list_fragment().await?;
+            cp.append_display_list(DLCmd::CLEAR_ALL).await?;
+            cp.append_display_list(DLCmd::DISPLAY).await?;
+            let frag = cp
+                .end_display_list_fragment(start, MainMem::ptr(0x400).slice_length(64))
+                .await?;
+            cp.start_display_list().await?;
+            cp.append_display_list_from_main_mem(frag).await?;
+            cp.append_display_list_from_main_mem(frag).await?;
+            cp.block_until_idle().await?;
+
+            let start = cp.begin_display_list_fragment().await?;
+            cp.append_display_list(DLCmd::DISPLAY).await?;
+            let overflow = cp
+                .end_display_list_fragment(start, MainMem::ptr(0x400).slice_length(0))
+                .await;
+            Ok::<_, TestError>((frag, overflow))
+        });
+        let (frag, overflow) = result.unwrap();
+        assert_eq!(frag.len(), 8);
+        assert!(
+            matches!(overflow, Err(Error::Overflow)),
+            "wrong result {:?}",
+            overflow
+        );
+
+        let mut ei = cp.take_interface();
+        let mut got = [0u8; 16];
+        crate::interface::Interface::read(&mut ei, DisplayListMem::BASE_ADDR, &mut got).unwrap();
+        let want: Vec<u8> = [
+            DLCmd::CLEAR_ALL,
+            DLCmd::DISPLAY,
+            DLCmd::CLEAR_ALL,
+            DLCmd::DISPLAY,
+        ]
+        .iter()
+        .flat_map(|cmd| cmd.as_raw().to_le_bytes())
+        .collect();
+        assert_eq!(&got[..], &want[..]);
+    }
+
+    #[test]
+    fn test_block_screen_size() {
+        let mut bufs = TestBuffers::new();
+        bufs.regs[Register::HSIZE.index()] = 800;
+        bufs.regs[Register::VSIZE.index()] = 480;
+        let mut cp = coprocessor(&mut bufs);
+        let (result, _) = block_on(async {
+            let before = cp.block_screen_size().await?;
+            cp.set_rotation(Rotation::Portrait).await?;
+            let after = cp.block_screen_size().await?;
+            Ok::<_, TestError>((before, after))
+        });
+        assert_eq!(result.unwrap(), ((800, 480), (480, 800)));
+    }
+
+    #[test]
+    fn test_fade_backlight() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = coprocessor(&mut bufs);
+        let (result, _) = block_on(async {
+            cp.fade_backlight(
+                crate::config::Brightness::Duty(10),
+                crate::config::Brightness::Duty(12),
+                core::time::Duration::from_micros(100),
+            )
+            .await?;
+            cp.block_read_register(Register::PWM_DUTY).await
+        });
+        assert_eq!(result.unwrap(), 12);
+    }
+
+    // An interface that records everything written to REG_CMDB_WRITE and
+    // reports that the ring buffer is always empty.
+    #[derive(Default)]
+    struct RecordingInterface {
+        cmd: Vec<u8>,
+    }
+
+    impl AsyncInterface for RecordingInterface {
+        type Error = ();
+
+        async fn write(&mut self, addr: u32, v: &[u8]) -> core::result::Result<(), ()> {
+            if addr == FakeModel::reg_ptr(Register::CMDB_WRITE).to_raw() {
+                self.cmd.extend_from_slice(v);
+            }
+            Ok(())
+        }
+
+        async fn read(&mut self, addr: u32, into: &mut [u8]) -> core::result::Result<(), ()> {
+            for b in into.iter_mut() {
+                *b = 0;
+            }
+            if addr == FakeModel::reg_ptr(Register::CMDB_SPACE).to_raw() {
+                into[..2].copy_from_slice(&4092u16.to_le_bytes());
+            }
+            Ok(())
+        }
+
+        async fn host_cmd(&mut self, _cmd: u8, _a0: u8, _a1: u8) -> core::result::Result<(), ()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/// A fixed-size host-side buffer of command words that are waiting to be
+/// sent into the coprocessor ring buffer.
+///
+/// Collecting several words and then sending them with a single
+/// `continue_write` call is considerably more efficient than sending each
+/// word separately on interfaces where each call has a high fixed overhead,
+/// such as USB-to-SPI bridges.
+///
+/// A buffer with `N == 0` can't hold anything, and so the coprocessor
+/// treats that case as "batching disabled" and writes each word directly.
+pub(crate) struct Batch<const N: usize> {
+    // We store the words already encoded in EVE's little-endian byte order,
+    // so that we can pass the whole buffer to the interface as a single
+    // byte slice without any further copying.
+    words: [[u8; 4]; N],
+    len: usize,
+}
+
+impl<const N: usize> Batch<N> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            words: [[0; 4]; N],
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub(crate) fn is_full(&self) -> bool {
+        self.len >= N
+    }
+
+    /// Appends a word to the buffer. The caller must make sure that the
+    /// buffer isn't already full before calling.
+    #[inline]
+    pub(crate) fn push(&mut self, v: u32) {
+        self.words[self.len] = v.to_le_bytes();
+        self.len += 1;
+    }
+
+    /// Returns the bytes of all of the words currently in the buffer, in
+    /// the order they should be written to the coprocessor.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.words[..self.len].as_flattened()
+    }
+
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch() {
+        let mut b: Batch<2> = Batch::new();
+        assert!(b.is_empty());
+        assert!(!b.is_full());
+        assert_eq!(b.as_bytes(), &[]);
+
+        b.push(0x04030201);
+        assert!(!b.is_empty());
+        assert!(!b.is_full());
+        assert_eq!(b.as_bytes(), &[0x01, 0x02, 0x03, 0x04]);
+
+        b.push(0xffffff00);
+        assert!(b.is_full());
+        assert_eq!(
+            b.as_bytes(),
+            &[0x01, 0x02, 0x03, 0x04, 0x00, 0xff, 0xff, 0xff]
+        );
+
+        b.clear();
+        assert!(b.is_empty());
+        assert_eq!(b.as_bytes(), &[]);
+    }
+}
@@ -0,0 +1,486 @@
+//! Recording of coprocessor command sequences for later replay.
+
+use core::marker::PhantomData;
+
+use super::command_word::command_words_for_bytes_iter;
+use super::encode;
+use super::options;
+use super::strfmt;
+use crate::display_list::DLCmd;
+use crate::memory::{HostAccessible, MemoryRegion, Ptr, Slice};
+use crate::models::Model;
+use crate::registers::Register;
+
+/// A recording of coprocessor commands which can be replayed into a
+/// [`Coprocessor`](super::Coprocessor) any number of times.
+///
+/// A `CommandList` offers the same command methods as `Coprocessor`, and
+/// also implements [`Builder`](crate::display_list::Builder), but rather
+/// than sending each command to the EVE chip it just appends the encoded
+/// command words to its storage. You can then pass the finished list to
+/// [`Coprocessor::append_command_list`](super::Coprocessor::append_command_list)
+/// to send all of the recorded words to the coprocessor in a single burst,
+/// which produces exactly the same word stream as calling the corresponding
+/// `Coprocessor` methods directly.
+///
+/// This is useful for screens that are redrawn often but rarely change,
+/// because the encoding work happens only once.
+///
+/// A command list is specific to a particular EVE model, selected by its
+/// type parameter `M`, because some commands refer to model-specific memory
+/// addresses. That type can usually be inferred from how the list is used.
+///
+/// The storage is typically a caller-provided slice of `u32`, which allows
+/// using command lists without any dynamic allocation. If the `std` feature
+/// is enabled then a command list can alternatively be backed by a
+/// growable `Vec<u32>`, using [`new_vec`](CommandList::new_vec).
+///
+/// ```rust
+/// # evegfx::interface::fake::coprocessor_example(|mut cp| {
+/// use evegfx::commands::{options, CommandList};
+/// use options::Options;
+/// use evegfx::display_list::Builder;
+///
+/// let mut buf = [0u32; 64];
+/// let mut list = CommandList::new(&mut buf[..]);
+/// list.start_display_list().unwrap();
+/// list.clear_all().unwrap();
+/// list.draw_text(
+///     (100, 100),
+///     evegfx::format!("hello world"),
+///     options::FontRef::new_raw(18),
+///     options::Text::new(),
+/// ).unwrap();
+/// list.display().unwrap();
+/// list.display_list_swap().unwrap();
+///
+/// // We can now replay the same list as many times as we like.
+/// cp.append_command_list(&list).unwrap();
+/// cp.append_command_list(&list).unwrap();
+/// # });
+/// ```
+pub struct CommandList<M: Model, S: Storage> {
+    storage: S,
+    _model: PhantomData<M>,
+}
+
+impl<'a, M: Model> CommandList<M, SliceStorage<'a>> {
+    /// Creates a new, empty command list which will record commands into
+    /// the given buffer.
+    ///
+    /// The list can record at most `buf.len()` command words. Any command
+    /// that would exceed that limit fails with [`Error::Full`].
+    pub fn new(buf: &'a mut [u32]) -> Self {
+        Self::with_storage(SliceStorage { buf: buf, len: 0 })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<M: Model> CommandList<M, std::vec::Vec<u32>> {
+    /// Creates a new, empty command list whose storage grows as necessary
+    /// to hold all of the recorded commands.
+    ///
+    /// This is available only when the `std` feature is enabled.
+    pub fn new_vec() -> Self {
+        Self::with_storage(std::vec::Vec::new())
+    }
+}
+
+impl<M: Model, S: Storage> CommandList<M, S> {
+    /// Creates a new command list using the given storage, which should
+    /// initially be empty.
+    pub fn with_storage(storage: S) -> Self {
+        Self {
+            storage: storage,
+            _model: PhantomData,
+        }
+    }
+
+    /// Returns the number of command words recorded so far.
+    pub fn len(&self) -> usize {
+        self.storage.as_words().len()
+    }
+
+    /// Returns true if nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Discards all of the recorded commands, so that the list can be
+    /// reused to record something new.
+    pub fn clear(&mut self) {
+        self.storage.clear()
+    }
+
+    /// Returns an iterator over the recorded command words, in the order
+    /// they would be sent to the coprocessor.
+    pub fn words(&self) -> impl Iterator<Item = u32> + '_ {
+        self.storage.as_words().iter().copied()
+    }
+
+    /// Returns the recorded command words, in the order they would be sent
+    /// to the coprocessor.
+    pub fn as_words(&self) -> &[u32] {
+        self.storage.as_words()
+    }
+
+    pub(crate) fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn show_testcard(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::show_testcard())
+    }
+
+    pub fn show_manufacturer_logo(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::show_manufacturer_logo())
+    }
+
+    pub fn start_spinner(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::start_spinner())
+    }
+
+    /// Records the coprocessor command to start a new display list.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::start_display_list`](super::Coprocessor::start_display_list).
+    pub fn start_display_list(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::start_display_list())
+    }
+
+    /// Records the coprocessor command to swap in the newly-populated
+    /// display list.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::display_list_swap`](super::Coprocessor::display_list_swap).
+    pub fn display_list_swap(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::display_list_swap())
+    }
+
+    /// Records the command to raise the `CMDFLAG` interrupt after the given
+    /// delay.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::trigger_cmdflag_interrupt`](super::Coprocessor::trigger_cmdflag_interrupt).
+    pub fn trigger_cmdflag_interrupt(&mut self, delay: core::time::Duration) -> Result<(), Error> {
+        self.write_words(&encode::trigger_cmdflag_interrupt(delay))
+    }
+
+    pub fn cold_start(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::cold_start())
+    }
+
+    /// Records the command to append display list commands from main
+    /// memory, such as a previously-captured
+    /// [`DisplayListFragment`](super::DisplayListFragment).
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::append_display_list_from_main_mem`](super::Coprocessor::append_display_list_from_main_mem).
+    pub fn append_display_list_from_main_mem<Sl: Into<Slice<M::MainMem>>>(
+        &mut self,
+        slice: Sl,
+    ) -> Result<(), Error> {
+        self.write_words(&encode::append_display_list_from_main_mem(slice.into()))
+    }
+
+    pub fn write_register(&mut self, reg: Register, v: u32) -> Result<(), Error> {
+        self.write_words(&encode::write_register::<M>(reg, v))
+    }
+
+    /// Records the command to set the output levels of all of the GPIO pins
+    /// at once.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::write_gpio_levels`](super::Coprocessor::write_gpio_levels).
+    pub fn write_gpio_levels(&mut self, levels: u16) -> Result<(), Error> {
+        self.write_words(&encode::write_gpio_levels::<M>(levels))
+    }
+
+    /// Records the command to write the given data into EVE memory, along
+    /// with a copy of the data itself.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::write_memory`](super::Coprocessor::write_memory).
+    pub fn write_memory<'d, IntoIter, R>(&mut self, to: Ptr<R>, from: IntoIter) -> Result<(), Error>
+    where
+        IntoIter: core::iter::IntoIterator<Item = &'d u8>,
+        IntoIter::IntoIter: core::iter::ExactSizeIterator<Item = &'d u8>,
+        R: MemoryRegion + HostAccessible,
+    {
+        let iter = from.into_iter();
+        let words = encode::write_memory(to.to_raw(), iter.len() as u32);
+        self.write_words_with_bytes(&words, iter)
+    }
+
+    /// Records the command to inflate the given compressed data into EVE
+    /// memory, along with a copy of the compressed data itself.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::write_memory_inflate`](super::Coprocessor::write_memory_inflate),
+    /// except that the data must have a known length so that the list can
+    /// check up front that there's room to record all of it.
+    pub fn write_memory_inflate<'d, IntoIter, R>(
+        &mut self,
+        to: Ptr<R>,
+        from: IntoIter,
+    ) -> Result<(), Error>
+    where
+        IntoIter: core::iter::IntoIterator<Item = &'d u8>,
+        IntoIter::IntoIter: core::iter::ExactSizeIterator<Item = &'d u8>,
+        R: MemoryRegion + HostAccessible,
+    {
+        let words = encode::write_memory_inflate(to.to_raw());
+        self.write_words_with_bytes(&words, from.into_iter())
+    }
+
+    /// Records the command to decode the given JPEG or PNG image into EVE
+    /// memory, along with a copy of the image data itself.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::write_memory_image`](super::Coprocessor::write_memory_image),
+    /// except that the data must have a known length so that the list can
+    /// check up front that there's room to record all of it.
+    pub fn write_memory_image<'d, IntoIter, R>(
+        &mut self,
+        to: Ptr<R>,
+        from: IntoIter,
+        opts: options::LoadImage,
+    ) -> Result<(), Error>
+    where
+        IntoIter: core::iter::IntoIterator<Item = &'d u8>,
+        IntoIter::IntoIter: core::iter::ExactSizeIterator<Item = &'d u8>,
+        R: MemoryRegion + HostAccessible,
+    {
+        let words = encode::write_memory_image(to.to_raw(), opts);
+        self.write_words_with_bytes(&words, from.into_iter())
+    }
+
+    pub fn draw_button<Rect: Into<crate::graphics::WidgetRect>>(
+        &mut self,
+        rect: Rect,
+        msg: strfmt::Message<M::MainMem>,
+        font: options::FontRef,
+        options: options::Button,
+    ) -> Result<(), Error> {
+        let words = encode::draw_button(rect.into(), &msg, font, options);
+        self.write_words_with_message(&words, &msg)
+    }
+
+    pub fn draw_text<Pos: Into<crate::graphics::WidgetPos>>(
+        &mut self,
+        pos: Pos,
+        msg: strfmt::Message<M::MainMem>,
+        font: options::FontRef,
+        options: options::Text,
+    ) -> Result<(), Error> {
+        let words = encode::draw_text(pos.into(), &msg, font, options);
+        self.write_words_with_message(&words, &msg)
+    }
+
+    pub fn append_display_list(&mut self, cmd: DLCmd) -> Result<(), Error> {
+        self.write_words(&[cmd.as_raw()])
+    }
+
+    pub fn append_raw_word(&mut self, word: u32) -> Result<(), Error> {
+        self.write_words(&[word])
+    }
+
+    pub fn wait_microseconds(&mut self, delay: u32) -> Result<(), Error> {
+        self.write_words(&encode::wait_microseconds(delay))
+    }
+
+    pub fn wait_video_scanout(&mut self) -> Result<(), Error> {
+        self.write_words(&encode::wait_video_scanout())
+    }
+
+    pub fn set_rotation(&mut self, rotation: crate::graphics::Rotation) -> Result<(), Error> {
+        self.write_words(&encode::set_rotation(rotation))
+    }
+
+    /// Records the commands to fade the display backlight from one
+    /// brightness to another over the given duration.
+    ///
+    /// This has the same meaning as
+    /// [`Coprocessor::fade_backlight`](super::Coprocessor::fade_backlight).
+    /// The whole fade is recorded, or none of it if there isn't enough room.
+    pub fn fade_backlight(
+        &mut self,
+        from: crate::config::Brightness,
+        to: crate::config::Brightness,
+        duration: core::time::Duration,
+    ) -> Result<(), Error> {
+        let fade = encode::fade_backlight(from, to, duration);
+        let initial = encode::write_register::<M>(Register::PWM_DUTY, fade.initial);
+        let step_len = encode::wait_microseconds(0).len()
+            + encode::write_register::<M>(Register::PWM_DUTY, 0).len();
+        if !self.storage.reserve(initial.len() + fade.len() * step_len) {
+            return Err(Error::Full);
+        }
+        let step_delay = fade.step_delay;
+        self.push_words(&initial);
+        for duty in fade {
+            self.push_words(&encode::wait_microseconds(step_delay));
+            self.push_words(&encode::write_register::<M>(Register::PWM_DUTY, duty));
+        }
+        Ok(())
+    }
+
+    // Appends all of the given words, or none of them if there isn't
+    // enough room, so that a failed call never leaves a partial command
+    // in the list.
+    fn write_words(&mut self, words: &[u32]) -> Result<(), Error> {
+        if !self.storage.reserve(words.len()) {
+            return Err(Error::Full);
+        }
+        self.push_words(words);
+        Ok(())
+    }
+
+    // Appends the given words without checking for space. Callers must
+    // reserve space first.
+    fn push_words(&mut self, words: &[u32]) {
+        for word in words {
+            self.storage.push(*word);
+        }
+    }
+
+    // Like `write_words`, but also appends the given data after the words,
+    // padded to a whole number of words.
+    fn write_words_with_bytes<'d, Iter>(&mut self, words: &[u32], data: Iter) -> Result<(), Error>
+    where
+        Iter: ExactSizeIterator<Item = &'d u8>,
+    {
+        let data_words = command_words_for_bytes_iter(data);
+        if !self.storage.reserve(words.len() + data_words.len()) {
+            return Err(Error::Full);
+        }
+        self.push_words(words);
+        for word in data_words {
+            self.storage.push(word.to_raw());
+        }
+        Ok(())
+    }
+
+    // Like `write_words`, but also appends the format string and arguments
+    // from the given message after the given words. The space for the whole
+    // command is reserved up front, so that a message too long for the
+    // remaining space doesn't leave a partial command in the list.
+    fn write_words_with_message<R: crate::memory::MainMem>(
+        &mut self,
+        words: &[u32],
+        msg: &strfmt::Message<'_, '_, R>,
+    ) -> Result<(), Error> {
+        let fmt_words = encode::message_format(msg);
+        let arg_words = encode::message_arguments(msg);
+        if !self
+            .storage
+            .reserve(words.len() + fmt_words.len() + arg_words.len())
+        {
+            return Err(Error::Full);
+        }
+        for word in words.iter().copied().chain(fmt_words).chain(arg_words) {
+            self.storage.push(word);
+        }
+        Ok(())
+    }
+}
+
+impl<M: Model, S: Storage> crate::display_list::Builder for CommandList<M, S> {
+    type Error = Error;
+    type Model = M;
+
+    fn append_raw_command(&mut self, raw: u32) -> core::result::Result<(), Self::Error> {
+        self.append_raw_word(raw)
+    }
+
+    fn append_command(&mut self, cmd: DLCmd) -> core::result::Result<(), Self::Error> {
+        self.append_display_list(cmd)
+    }
+}
+
+/// Errors that can occur while recording into a [`CommandList`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The list's storage has no room for the command.
+    Full,
+}
+
+/// Implemented by types that can serve as the backing storage for a
+/// [`CommandList`].
+pub trait Storage {
+    /// Returns all of the words pushed so far.
+    fn as_words(&self) -> &[u32];
+
+    /// Returns true if there's room to push at least `n` more words,
+    /// possibly after allocating additional space.
+    fn reserve(&mut self, n: usize) -> bool;
+
+    /// Appends a word. Callers must call `reserve` first.
+    fn push(&mut self, word: u32);
+
+    /// Discards all of the stored words.
+    fn clear(&mut self);
+
+    /// Copies the little-endian bytes of the stored words into `into`,
+    /// starting with the word at index `start` and stopping when either the
+    /// words or the buffer run out. Returns the number of bytes copied,
+    /// which is always a multiple of four.
+    ///
+    /// This is how a recorded list is prepared for writing to the
+    /// coprocessor's ring buffer, and so implementations don't usually need
+    /// to override it.
+    fn copy_bytes(&self, start: usize, into: &mut [u8]) -> usize {
+        let words = &self.as_words()[start..];
+        let mut len = 0;
+        for (word, dest) in words.iter().zip(into.chunks_exact_mut(4)) {
+            dest.copy_from_slice(&word.to_le_bytes());
+            len += 4;
+        }
+        len
+    }
+}
+
+/// [`CommandList`] storage using a fixed-size, caller-provided buffer.
+pub struct SliceStorage<'a> {
+    buf: &'a mut [u32],
+    len: usize,
+}
+
+impl<'a> Storage for SliceStorage<'a> {
+    fn as_words(&self) -> &[u32] {
+        &self.buf[..self.len]
+    }
+
+    fn reserve(&mut self, n: usize) -> bool {
+        self.buf.len() - self.len >= n
+    }
+
+    fn push(&mut self, word: u32) {
+        self.buf[self.len] = word;
+        self.len += 1;
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+#[cfg(feature = "std")]
+impl Storage for std::vec::Vec<u32> {
+    fn as_words(&self) -> &[u32] {
+        &self[..]
+    }
+
+    fn reserve(&mut self, n: usize) -> bool {
+        std::vec::Vec::reserve(self, n);
+        true
+    }
+
+    fn push(&mut self, word: u32) {
+        std::vec::Vec::push(self, word)
+    }
+
+    fn clear(&mut self) {
+        std::vec::Vec::clear(self)
+    }
+}
@@ -30,8 +30,11 @@ impl From<(u16, u16)> for CommandWord {
 impl From<(i16, i16)> for CommandWord {
     #[inline]
     fn from(v: (i16, i16)) -> Self {
-        let a = v.0 as i32;
-        let b = v.1 as i32;
+        // Each value must be truncated to 16 bits before combining, or else
+        // the sign extension of a negative first value would overwrite
+        // the second.
+        let a = v.0 as u16;
+        let b = v.1 as u16;
         CommandWord((a as u32) | (b as u32) << 16)
     }
 }
@@ -142,6 +145,12 @@ mod tests {
         command_words_for_bytes_iter(bytes.into_iter())
     }
 
+    #[test]
+    fn test_from_signed_pair() {
+        debug_assert_eq!(CommandWord::from((-1i16, 2i16)).to_raw(), 0x0002ffff);
+        debug_assert_eq!(CommandWord::from((3i16, -2i16)).to_raw(), 0xfffe0003);
+    }
+
     #[test]
     fn test_byte_to_command_iter_exact() {
         use std::vec::Vec;
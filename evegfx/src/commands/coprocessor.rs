@@ -1,4 +1,6 @@
+use super::batch::Batch;
 use super::command_word::CommandWord;
+use super::encode;
 use super::strfmt;
 use crate::commands::options;
 use crate::commands::waiter::{PollingWaiter, Waiter, WaiterError};
@@ -8,6 +10,10 @@ use crate::memory::{Ptr, Slice};
 use crate::models::Model;
 use crate::registers::Register;
 
+// The size of the buffer `append_command_list` uses to convert the recorded
+// words into bytes for writing.
+const REPLAY_CHUNK_LEN: usize = 256;
+
 /// The result type for coprocessor operations, where the error type is always
 /// [`Error`](Error).
 pub type Result<T, M, I, W> = core::result::Result<T, Error<M, I, W>>;
@@ -17,7 +23,14 @@ pub type Result<T, M, I, W> = core::result::Result<T, Error<M, I, W>>;
 ///
 /// This object encapsulates the handling of the ring buffer and provides an
 /// API for appending
-pub struct Coprocessor<M: Model, I: Interface, W: Waiter<M, I>> {
+///
+/// The const parameter `N` selects the size, in 32-bit words, of an optional
+/// host-side buffer which collects command words and sends them to the EVE
+/// chip in bursts. The default of zero disables that buffer, so that each
+/// word is written immediately. Use
+/// [`with_batch_buffer`](Coprocessor::with_batch_buffer) to select a
+/// different size.
+pub struct Coprocessor<M: Model, I: Interface, W: Waiter<M, I>, const N: usize = 0> {
     ll: LowLevel<M, I>,
     wait: W,
 
@@ -32,6 +45,12 @@ pub struct Coprocessor<M: Model, I: Interface, W: Waiter<M, I>> {
     // the waiter to wait for more space and then update `known_space` with
     // the new free space determined by the waiter.
     known_space: u16,
+
+    // `batch` holds command words that we've accepted but not yet sent to
+    // the chip. Words only get in here after `ensure_space` has reserved
+    // ring buffer space for them, so `known_space` already accounts for
+    // them. It's always empty when `N` is zero.
+    batch: Batch<N>,
 }
 
 /// The methods which submit new commands into the coprocessor ringbuffer.
@@ -46,7 +65,7 @@ pub struct Coprocessor<M: Model, I: Interface, W: Waiter<M, I>> {
 /// run concurrently with code on the host processor. However, there are
 /// some methods which _do_ block for the completion of certain operations,
 /// which all have the name prefix `block_` to indicate that.
-impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
+impl<M: Model, I: Interface, W: Waiter<M, I>, const N: usize> Coprocessor<M, I, W, N> {
     /// Creates a pointer into the main memory ("RAM_G") area of the
     /// EVE address space, with the given offset in bytes.
     pub fn ram_ptr(&self, offset: u32) -> Ptr<M::MainMem> {
@@ -106,16 +125,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         &mut self,
         delay: core::time::Duration,
     ) -> Result<(), M, I, W> {
-        self.write_stream(8, |cp| {
-            let delay = delay.as_millis();
-            let delay = if delay > (core::u32::MAX as u128) {
-                core::u32::MAX
-            } else {
-                delay as u32
-            };
-            cp.write_to_buffer(0xFFFFFF02 as u32)?;
-            cp.write_to_buffer(delay)
-        })
+        self.write_words(&encode::trigger_cmdflag_interrupt(delay))
     }
 
     /// Resets the coprocessor's state to the boot-time defaults before
@@ -123,7 +133,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
     /// currently-selected widget colors and reverts to the default color
     /// scheme.
     pub fn cold_start(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(0xFFFFFF32 as u32))
+        self.write_words(&encode::cold_start())
     }
 
     /// Reads a slice of display list command bytes from a location in the main
@@ -141,25 +151,100 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         &mut self,
         slice: S,
     ) -> Result<(), M, I, W> {
-        let slice: Slice<M::MainMem> = slice.into();
-        let start = slice.start().to_raw();
-        let count = slice.len();
-        self.write_stream(12, |cp| {
-            cp.write_to_buffer(0xFFFFFF1E as u32)?;
-            cp.write_to_buffer(start)?;
-            cp.write_to_buffer(count)
+        self.write_words(&encode::append_display_list_from_main_mem(slice.into()))
+    }
+
+    /// Builds a display list fragment using the given closure and then copies
+    /// the resulting display list commands into the given slice of main
+    /// memory, so that they can be appended to later display lists with
+    /// [`append_display_list_from_main_mem`](Coprocessor::append_display_list_from_main_mem).
+    ///
+    /// The closure is called with the coprocessor object itself, and so the
+    /// fragment can contain both direct display list commands and any
+    /// coprocessor commands that generate display list commands, such as
+    /// widgets. The generated commands are _also_ added to the display list
+    /// currently being built, so it's typical to capture a fragment while
+    /// building the first frame that uses it.
+    ///
+    /// This method blocks until the coprocessor has completed all of the
+    /// commands issued so far, both before and after calling the closure,
+    /// so that it can determine which part of the display list memory the
+    /// closure's commands produced.
+    ///
+    /// The start of the given slice must be an address that is a multiple of
+    /// four. If the generated commands don't fit in the slice then this
+    /// returns [`Error::Overflow`](crate::CoprocessorError::Overflow) and
+    /// leaves the slice unchanged.
+    ///
+    /// ```rust
+    /// # evegfx::interface::fake::coprocessor_example(|mut cp| {
+    /// use evegfx::display_list::Builder;
+    ///
+    /// let dest = cp.ram_ptr(0x1000).slice_length(256);
+    /// cp.start_display_list().unwrap();
+    /// cp.clear_all().unwrap();
+    /// let frag = cp.capture_display_list_fragment(dest, |cp| {
+    ///     cp.point_size(160)?;
+    ///     cp.begin(evegfx::display_list::options::GraphicsPrimitive::Points)?;
+    ///     cp.vertex_2f((100, 100))
+    /// }).unwrap();
+    /// cp.display().unwrap();
+    /// cp.display_list_swap().unwrap();
+    ///
+    /// // Later frames can then just append the captured fragment.
+    /// cp.new_display_list(|cp| {
+    ///     cp.clear_all()?;
+    ///     cp.append_display_list_from_main_mem(frag)?;
+    ///     cp.display()
+    /// }).unwrap();
+    /// # });
+    /// ```
+    pub fn capture_display_list_fragment<S, F>(
+        &mut self,
+        dest: S,
+        f: F,
+    ) -> Result<DisplayListFragment<M>, M, I, W>
+    where
+        S: Into<Slice<M::MainMem>>,
+        F: FnOnce(&mut Self) -> Result<(), M, I, W>,
+    {
+        let dest: Slice<M::MainMem> = dest.into();
+
+        // REG_CMD_DL tracks the offset where the coprocessor will write its
+        // next display list command, so the difference between its values
+        // before and after the closure's commands tells us what they
+        // generated.
+        let start = self.block_read_register(Register::CMD_DL)?;
+        f(self)?;
+        let end = self.block_read_register(Register::CMD_DL)?;
+        let len = end.wrapping_sub(start);
+        if len > dest.len() {
+            return Err(Error::Overflow);
+        }
+
+        self.write_words(&encode::copy_display_list_fragment::<M>(dest, start, len))?;
+
+        Ok(DisplayListFragment {
+            slice: dest.start().slice_length(len),
         })
     }
 
     pub fn write_register(&mut self, reg: Register, v: u32) -> Result<(), M, I, W> {
-        let ptr_raw = reg.ptr::<M>().to_raw();
+        self.write_words(&encode::write_register::<M>(reg, v))
+    }
 
-        self.write_stream(16, |cp| {
-            cp.write_to_buffer(0xFFFFFF1A as u32)?;
-            cp.write_to_buffer(ptr_raw)?;
-            cp.write_to_buffer(4)?;
-            cp.write_to_buffer(v)
-        })
+    /// Queues a command that sets the output levels of all of the GPIO pins
+    /// at once, by writing the given value to `REG_GPIOX`.
+    ///
+    /// Unlike [`block_gpio_write`](Coprocessor::block_gpio_write), this
+    /// doesn't wait for the coprocessor to catch up, and so it can also be
+    /// recorded in a [`CommandList`](super::CommandList). However, the
+    /// coprocessor can only replace the whole register, so `levels` must
+    /// include the desired level of every pin, as given by
+    /// [`GpioPin::reg_gpiox_mask`](crate::gpio::GpioPin::reg_gpiox_mask),
+    /// along with any other settings in `REG_GPIOX`.
+    pub fn write_gpio_levels(&mut self, levels: u16) -> Result<(), M, I, W> {
+        self.write_words(&encode::write_gpio_levels::<M>(levels))
     }
 
     /// Writes raw data from host memory into locations in the
@@ -207,11 +292,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         let len = iter.len() as u32;
 
         // First we'll write out the fixed-size command "header"...
-        self.write_stream(12, |cp| {
-            cp.write_to_buffer(0xFFFFFF1A as u32)?;
-            cp.write_to_buffer(ptr_raw)?;
-            cp.write_to_buffer(len)
-        })?;
+        self.write_words(&encode::write_memory(ptr_raw, len))?;
 
         // ...and now we must write out the given bytes themselves.
         self.write_bytes_chunked(iter)
@@ -249,10 +330,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         let iter = from.into_iter();
 
         // First we'll write out the fixed-size command "header"...
-        self.write_stream(8, |cp| {
-            cp.write_to_buffer(0xFFFFFF22 as u32)?;
-            cp.write_to_buffer(ptr_raw)
-        })?;
+        self.write_words(&encode::write_memory_inflate(ptr_raw))?;
 
         // ...and now we must write out the given bytes themselves.
         self.write_bytes_chunked(iter)
@@ -284,33 +362,22 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         let iter = from.into_iter();
 
         // First we'll write out the fixed-size command "header"...
-        self.write_stream(12, |cp| {
-            cp.write_to_buffer(0xFFFFFF24 as u32)?;
-            cp.write_to_buffer(ptr_raw)?;
-            cp.write_to_buffer(opts.to_raw())
-        })?;
+        self.write_words(&encode::write_memory_image(ptr_raw, opts))?;
 
         // ...and now we must write out the given bytes themselves.
         self.write_bytes_chunked(iter)
     }
 
     pub fn show_testcard(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(0xFFFFFF61 as u32))
+        self.write_words(&encode::show_testcard())
     }
 
     pub fn show_manufacturer_logo(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(0xFFFFFF31 as u32))
+        self.write_words(&encode::show_manufacturer_logo())
     }
 
     pub fn start_spinner(&mut self) -> Result<(), M, I, W> {
-        // TODO: Make the spinner customizable.
-        self.write_stream(20, |cp| {
-            cp.write_to_buffer(0xFFFFFF16 as u32)?;
-            cp.write_to_buffer(1000)?;
-            cp.write_to_buffer(1000)?;
-            cp.write_to_buffer(0)?;
-            cp.write_to_buffer(0)
-        })
+        self.write_words(&encode::start_spinner())
     }
 
     /// Sends just the coprocessor command to start a new display list, which
@@ -323,7 +390,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
     /// handles both starting the display list and swapping it to be visible
     /// all in a single method call.
     pub fn start_display_list(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(0xFFFFFF00 as u32))
+        self.write_words(&encode::start_display_list())
     }
 
     /// Sends just the coprocessor command to swap in the newly-populated
@@ -334,7 +401,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
     /// handles both starting the display list and swapping it to be visible
     /// all in a single method call.
     pub fn display_list_swap(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(0xFFFFFF01 as u32))
+        self.write_words(&encode::display_list_swap())
     }
 
     pub fn draw_button<Rect: Into<crate::graphics::WidgetRect>>(
@@ -344,15 +411,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         font: options::FontRef,
         options: options::Button,
     ) -> Result<(), M, I, W> {
-        let rect: crate::graphics::WidgetRect = rect.into();
-        self.write_stream(28, |cp| {
-            cp.write_to_buffer(0xFFFFFF0D as u32)?;
-            cp.write_to_buffer((rect.x, rect.y))?;
-            cp.write_to_buffer((rect.w, rect.h))?;
-            let font_raw = font.to_raw() as u16;
-            let opts_raw = maybe_opt_format(options.to_raw(), &msg) as u16;
-            cp.write_to_buffer((font_raw, opts_raw))
-        })?;
+        self.write_words(&encode::draw_button(rect.into(), &msg, font, options))?;
         self.write_fmt_message(&msg)
     }
 
@@ -386,34 +445,109 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         font: options::FontRef,
         options: options::Text,
     ) -> Result<(), M, I, W> {
-        let pos: crate::graphics::WidgetPos = pos.into();
-        self.write_stream(28, |cp| {
-            cp.write_to_buffer(0xFFFFFF0C as u32)?;
-            cp.write_to_buffer((pos.x, pos.y))?;
-            let font_raw = font.to_raw() as u16;
-            let opts_raw = maybe_opt_format(options.to_raw(), &msg) as u16;
-            cp.write_to_buffer((font_raw, opts_raw))
-        })?;
+        self.write_words(&encode::draw_text(pos.into(), &msg, font, options))?;
         self.write_fmt_message(&msg)
     }
 
     pub fn append_display_list(&mut self, cmd: crate::display_list::DLCmd) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(cmd.as_raw()))
+        self.write_words(&[cmd.as_raw()])
     }
 
     pub fn append_raw_word(&mut self, word: u32) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(word))
+        self.write_words(&[word])
+    }
+
+    /// Sends all of the commands previously recorded in the given
+    /// [`CommandList`](super::CommandList).
+    ///
+    /// The recorded words are sent together in a single write burst, unless
+    /// the list is larger than the coprocessor's ring buffer, in which case
+    /// it's split into the fewest bursts that will fit. The result is the
+    /// same as if the recorded commands had been called directly on this
+    /// object.
+    pub fn append_command_list<S: super::command_list::Storage>(
+        &mut self,
+        list: &super::CommandList<M, S>,
+    ) -> Result<(), M, I, W> {
+        let max_words = Self::space_when_empty() as usize / 4;
+        let total = list.len();
+        let mut buf = [0u8; REPLAY_CHUNK_LEN];
+        let mut start = 0;
+        while start < total {
+            let end = core::cmp::min(start + max_words, total);
+            self.ensure_space(((end - start) * 4) as u16)?;
+
+            // The stream stays open until the whole burst is written, so
+            // converting the words to bytes through a small buffer doesn't
+            // split the burst.
+            let mut next = start;
+            while next < end {
+                let limit = core::cmp::min(buf.len(), (end - next) * 4);
+                let len = list.storage().copy_bytes(next, &mut buf[..limit]);
+                self.write_bytes_to_buffer(&buf[..len])?;
+                next += len / 4;
+            }
+            start = end;
+        }
+        Ok(())
     }
 
     pub fn wait_microseconds(&mut self, delay: u32) -> Result<(), M, I, W> {
-        self.write_stream(8, |cp| {
-            cp.write_to_buffer(0xFFFFFF65 as u32)?;
-            cp.write_to_buffer(delay)
-        })
+        self.write_words(&encode::wait_microseconds(delay))
     }
 
     pub fn wait_video_scanout(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(4, |cp| cp.write_to_buffer(0xFFFFFF42 as u32))
+        self.write_words(&encode::wait_video_scanout())
+    }
+
+    /// Changes the orientation of the display, using `CMD_SETROTATE`.
+    ///
+    /// As well as setting `REG_ROTATE`, the coprocessor updates the touch
+    /// transform matrix so that touch coordinates match the new orientation.
+    /// The change applies to the next display list, and drawing commands
+    /// then use the logical size reported by
+    /// [`block_screen_size`](Coprocessor::block_screen_size).
+    pub fn set_rotation(&mut self, rotation: crate::graphics::Rotation) -> Result<(), M, I, W> {
+        self.write_words(&encode::set_rotation(rotation))
+    }
+
+    /// Queues commands that have the coprocessor fade the display backlight
+    /// from one brightness to another over the given duration, by setting
+    /// `REG_PWM_DUTY` to the initial brightness and then writing it once for
+    /// each step of the duty cycle, with a wait before each step.
+    ///
+    /// The host doesn't need to take any further action for the fade to
+    /// complete, but any commands issued afterwards will run only once the
+    /// fade has finished.
+    ///
+    /// ```rust
+    /// # evegfx::interface::fake::coprocessor_example(|mut cp| {
+    /// use core::time::Duration;
+    /// use evegfx::config::Brightness;
+    /// cp.fade_backlight(
+    ///     Brightness::OFF,
+    ///     Brightness::Percent(80),
+    ///     Duration::from_millis(500),
+    /// ).unwrap();
+    /// # });
+    /// ```
+    pub fn fade_backlight(
+        &mut self,
+        from: crate::config::Brightness,
+        to: crate::config::Brightness,
+        duration: core::time::Duration,
+    ) -> Result<(), M, I, W> {
+        let fade = encode::fade_backlight(from, to, duration);
+
+        // The backlight starts at the initial brightness immediately, rather
+        // than after the first step's delay.
+        self.write_register(Register::PWM_DUTY, fade.initial)?;
+        let step_delay = fade.step_delay;
+        for duty in fade {
+            self.wait_microseconds(step_delay)?;
+            self.write_register(Register::PWM_DUTY, duty)?;
+        }
+        Ok(())
     }
 }
 
@@ -424,7 +558,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
 /// coprocessor commands have completed, and so applications making heavy
 /// use of these may wish to consider supplying a tailored waiter
 /// implementation that can avoid busy-waiting.
-impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
+impl<M: Model, I: Interface, W: Waiter<M, I>, const N: usize> Coprocessor<M, I, W, N> {
     #[inline]
     pub fn space_when_empty() -> u16 {
         // Perhaps this will vary in future models, but it's always been
@@ -438,6 +572,71 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         self.ensure_space(Self::space_when_empty())
     }
 
+    /// Blocks until the coprocessor has completed all of the commands issued
+    /// so far and then returns the logical width and height of the screen,
+    /// taking into account the current rotation.
+    pub fn block_screen_size(&mut self) -> Result<(u16, u16), M, I, W> {
+        self.block_with_low_level(|ll| crate::graphics::rotation::read_screen_size(ll))
+    }
+
+    /// Blocks until the coprocessor has completed all of the commands issued
+    /// so far and then selects whether the given GPIO pin is an input or an
+    /// output, leaving the other pins unchanged.
+    pub fn block_gpio_set_direction(
+        &mut self,
+        pin: crate::gpio::GpioPin,
+        dir: crate::gpio::GpioDirection,
+    ) -> Result<(), M, I, W> {
+        self.block_with_low_level(|ll| crate::gpio::set_direction(ll, pin, dir))
+    }
+
+    /// Blocks until the coprocessor has completed all of the commands issued
+    /// so far and then returns whether the given GPIO pin is an input or an
+    /// output.
+    pub fn block_gpio_direction(
+        &mut self,
+        pin: crate::gpio::GpioPin,
+    ) -> Result<crate::gpio::GpioDirection, M, I, W> {
+        self.block_with_low_level(|ll| crate::gpio::direction(ll, pin))
+    }
+
+    /// Blocks until the coprocessor has completed all of the commands issued
+    /// so far and then sets the output level of the given GPIO pin, leaving
+    /// the other pins unchanged.
+    pub fn block_gpio_write(
+        &mut self,
+        pin: crate::gpio::GpioPin,
+        high: bool,
+    ) -> Result<(), M, I, W> {
+        self.block_with_low_level(|ll| crate::gpio::write(ll, pin, high))
+    }
+
+    /// Blocks until the coprocessor has completed all of the commands issued
+    /// so far and then returns true if the given GPIO pin is at a high level.
+    pub fn block_gpio_read(&mut self, pin: crate::gpio::GpioPin) -> Result<bool, M, I, W> {
+        self.block_with_low_level(|ll| crate::gpio::read(ll, pin))
+    }
+
+    // Waits for the coprocessor to become idle and then calls `f` with the
+    // stream stopped, so that it can access registers directly rather than
+    // asking the coprocessor to access each one for us, without racing
+    // with any earlier commands.
+    fn block_with_low_level<R, F>(&mut self, f: F) -> Result<R, M, I, W>
+    where
+        F: FnOnce(&mut LowLevel<M, I>) -> core::result::Result<R, crate::error::Error<I>>,
+    {
+        let stopped = self.stop_stream()?;
+        if let Err(err) = self.ensure_space_stopped(&stopped, Self::space_when_empty()) {
+            self.start_stream(stopped)?;
+            return Err(err);
+        }
+
+        let result = f(self.borrow_low_level(&stopped));
+
+        self.start_stream(stopped)?;
+        Error::general_result(result)
+    }
+
     /// Blocks until EVE has finished scanning out the current frame. Callers
     /// can use this as part of a main loop which takes actions synchronized
     /// with the video framerate.
@@ -455,18 +654,9 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
     /// have modified the register value, in order to capture that result
     /// at the correct time.
     pub fn block_read_register(&mut self, reg: crate::registers::Register) -> Result<u32, M, I, W> {
-        let ptr = M::reg_ptr(reg);
-
-        self.write_stream(12, |cp| {
-            cp.write_to_buffer(0xFFFFFF19 as u32)?;
-            cp.write_to_buffer(ptr.to_raw())?;
-            cp.write_to_buffer(0xf0f0f0f0 as u32) // space for the result to be written
-        })?;
+        self.write_words(&encode::read_register::<M>(reg))?;
 
-        self.block_for_output_values(|ll, addr| {
-            let result_ptr = addr - 4;
-            ll.rd32(result_ptr)
-        })
+        self.block_for_output_values(|ll, result_ptr| ll.rd32(result_ptr))
     }
 
     /// Blocks until the coprocessor has completed all of the commands issued
@@ -477,52 +667,67 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         R: crate::memory::MemoryRegion,
         S: Into<Slice<R>>,
     {
-        let region: Slice<R> = region.into();
-        let ptr = region.start();
-        let len = region.len();
-
-        self.write_stream(12, |cp| {
-            cp.write_to_buffer(0xFFFFFF18 as u32)?;
-            cp.write_to_buffer(ptr.to_raw())?;
-            cp.write_to_buffer(len)?;
-            cp.write_to_buffer(0xf0f0f0f0 as u32) // space for the result to be written
-        })?;
-
-        self.block_for_output_values(|ll, addr| {
-            let result_ptr = addr - 4;
-            ll.rd32(result_ptr)
-        })
+        self.write_words(&encode::memory_crc(region.into()))?;
+
+        self.block_for_output_values(|ll, result_ptr| ll.rd32(result_ptr))
     }
 
+    // Blocks until the coprocessor has completed the most recently-written
+    // command, and then calls the given function with a pointer to that
+    // command's final word in the ring buffer, where the coprocessor will
+    // have written its output value.
     fn block_for_output_values<F, R>(&mut self, f: F) -> Result<R, M, I, W>
     where
         R: Sized,
         F: FnOnce(
             &mut LowLevel<M, I>,
-            Ptr<M::DisplayListMem>,
+            Ptr<M::CommandMem>,
         ) -> core::result::Result<R, crate::error::Error<I>>,
     {
-        let ptr_reg = crate::registers::Register::CMD_WRITE;
         let stopped = self.stop_stream()?;
+        let result = self.read_output_values_stopped(&stopped, f);
+        // We restart the stream even if something failed above, so that
+        // the caller can retry or take some other action afterwards.
+        self.start_stream(stopped)?;
+        result
+    }
+
+    // A version of `block_for_output_values` that assumes the stream is
+    // already stopped and will remain stopped after it returns.
+    fn read_output_values_stopped<F, R>(
+        &mut self,
+        stopped: &StoppedStream,
+        f: F,
+    ) -> Result<R, M, I, W>
+    where
+        R: Sized,
+        F: FnOnce(
+            &mut LowLevel<M, I>,
+            Ptr<M::CommandMem>,
+        ) -> core::result::Result<R, crate::error::Error<I>>,
+    {
+        use crate::memory::MemoryRegion;
+
+        let ptr_reg = crate::registers::Register::CMD_WRITE;
         let write_addr = {
-            let ll = self.borrow_low_level(&stopped);
+            let ll = self.borrow_low_level(stopped);
             ll.rd32(M::reg_ptr(ptr_reg))?
         };
 
         // wait for the coprocessor to catch up
-        self.ensure_space_stopped(&stopped, Self::space_when_empty())?;
-
-        let result = {
-            let ll = self.borrow_low_level(&stopped);
-            f(ll, Ptr::new(write_addr))
-        };
+        self.ensure_space_stopped(stopped, Self::space_when_empty())?;
 
-        self.start_stream(stopped)?;
-        Error::general_result(result)
+        // REG_CMD_WRITE is an offset into the command ring buffer, and
+        // so the final word of the command might be at the very end of
+        // the buffer if the offset has just wrapped around.
+        let ring_len = M::CommandMem::LENGTH;
+        let result_offset = write_addr.wrapping_sub(4) & (ring_len - 1);
+        let ll = self.borrow_low_level(stopped);
+        Error::general_result(f(ll, M::CommandMem::ptr(result_offset)))
     }
 }
 
-impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
+impl<M: Model, I: Interface, W: Waiter<M, I>, const N: usize> Coprocessor<M, I, W, N> {
     /// Consumes the given interface and waiter and returns an interface to
     /// the coprocessor via the given interface.
     ///
@@ -543,6 +748,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
             ll: ll,
             wait: wait,
             known_space: 0,
+            batch: Batch::new(),
         };
 
         // We use a "stopped stream" marker to help ensure correct discipline
@@ -572,7 +778,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
     /// implementation that does additional logging or tracking of waiting,
     /// if needed for debugging or development, without needing to first
     /// determine what kind of waiter the object previously had.
-    pub fn with_new_waiter<W2, F>(self, f: F) -> Coprocessor<M, I, W2>
+    pub fn with_new_waiter<W2, F>(self, f: F) -> Coprocessor<M, I, W2, N>
     where
         W2: Waiter<M, I>,
         F: FnOnce(W) -> W2,
@@ -580,6 +786,7 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         let ll = self.ll;
         let old_wait = self.wait;
         let old_known_space = self.known_space;
+        let old_batch = self.batch;
 
         let new_wait = f(old_wait);
 
@@ -587,7 +794,68 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
             ll: ll,
             wait: new_wait,
             known_space: old_known_space,
+            batch: old_batch,
+        }
+    }
+
+    /// Consumes the current coprocessor object and returns a new one that
+    /// collects up to `N2` command words in a host-side buffer before
+    /// sending them to the EVE chip in a single burst.
+    ///
+    /// Batching can considerably improve throughput for interfaces where each
+    /// write call has a high fixed cost, such as USB-to-SPI bridges. The
+    /// buffer is a fixed-size array inside the `Coprocessor` object, so it
+    /// doesn't require any dynamic allocation, but it does make the object
+    /// larger by four bytes per word.
+    ///
+    /// Buffered words are sent automatically whenever the buffer fills up
+    /// and before any operation that waits for or reads from the chip,
+    /// including all of the `block_` methods. Otherwise, the coprocessor
+    /// won't see buffered commands until you call
+    /// [`flush`](Coprocessor::flush), so applications that don't otherwise
+    /// block should typically flush once per frame.
+    ///
+    /// Selecting a size of zero disables the buffer, which is the default.
+    ///
+    /// ```rust
+    /// # evegfx::interface::fake::coprocessor_example(|mut cp| {
+    /// let mut cp = cp.with_batch_buffer::<32>().unwrap();
+    /// cp.wait_video_scanout().unwrap();
+    /// cp.flush().unwrap();
+    /// # });
+    /// ```
+    pub fn with_batch_buffer<const N2: usize>(
+        mut self,
+    ) -> Result<Coprocessor<M, I, W, N2>, M, I, W> {
+        self.flush()?;
+        Ok(Coprocessor {
+            ll: self.ll,
+            wait: self.wait,
+            known_space: self.known_space,
+            batch: Batch::new(),
+        })
+    }
+
+    /// Sends any command words waiting in the host-side batch buffer to the
+    /// EVE chip.
+    ///
+    /// This is necessary only when using a batch buffer, as selected by
+    /// [`with_batch_buffer`](Coprocessor::with_batch_buffer). Otherwise this
+    /// does nothing.
+    ///
+    /// Flushing only delivers the commands into the coprocessor's ring
+    /// buffer. It doesn't wait for the coprocessor to execute them.
+    pub fn flush(&mut self) -> Result<(), M, I, W> {
+        if self.batch.is_empty() {
+            return Ok(());
         }
+        let ei = self.ll.borrow_interface();
+        let result = Self::interface_result(ei.continue_write(self.batch.as_bytes()));
+        // We discard the buffered words even if the write failed, because
+        // we can't tell how many of them reached the chip and so retrying
+        // could duplicate some of them.
+        self.batch.clear();
+        result
     }
 
     /// `take_interface` consumes the coprocessor object and returns its
@@ -600,6 +868,18 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         return Ok(self.ll.take_interface());
     }
 
+    /// `into_eve` blocks until the coprocessor is idle and then consumes the
+    /// coprocessor object, returning an [`EVE`](crate::EVE) object for the
+    /// same chip.
+    ///
+    /// This is necessary before entering a low-power state using
+    /// [`EVE::enter_power_state`](crate::EVE::enter_power_state).
+    pub fn into_eve(mut self) -> Result<crate::EVE<M, I>, M, I, W> {
+        self.block_until_idle()?;
+        let ei = self.take_interface()?;
+        Ok(crate::EVE::new_internal(ei))
+    }
+
     /// `with_interface` runs your given closure with access to the
     /// coprocessor object's underlying `Interface`, temporarily pausing
     /// local coprocessor management so the closure can make use of other
@@ -660,13 +940,32 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
     // stopped the stream and thus the caller can safely perform operations
     // that expect the stream to be stopped.
     fn stop_stream(&mut self) -> Result<StoppedStream, M, I, W> {
+        // Anything still in the batch buffer must reach the chip before we
+        // close the transaction, because whatever we do next is likely to
+        // depend on the coprocessor having seen those commands.
+        let flushed = self.flush();
+
         // This just closes the long-lived write transaction we started in
         // start_stream.
         let ei = self.ll.borrow_interface();
-        Self::interface_result(ei.end_write())?;
+        let ended = Self::interface_result(ei.end_write());
+        flushed?;
+        ended?;
         Ok(StoppedStream)
     }
 
+    // Writes the given command words, which were typically produced by one
+    // of the functions in `encode`, once there's enough space for all of
+    // them.
+    fn write_words(&mut self, words: &[u32]) -> Result<(), M, I, W> {
+        self.write_stream((words.len() * 4) as u16, |cp| {
+            for word in words {
+                cp.write_to_buffer(*word)?;
+            }
+            Ok(())
+        })
+    }
+
     fn write_stream<F: FnOnce(&mut Self) -> Result<(), M, I, W>>(
         &mut self,
         len: u16,
@@ -699,9 +998,12 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         // burst stream in this case, because the waiter will need to make
         // other calls against the EVE chip.
         let stopped = self.stop_stream()?;
-        self.ensure_space_stopped(&stopped, need)?;
+        let result = self.ensure_space_stopped(&stopped, need);
+        // We restart the stream even if waiting failed, so that the caller
+        // can still make other requests, such as to retrieve the fault
+        // message after a coprocessor fault.
         self.start_stream(stopped)?;
-        Ok(())
+        result
     }
 
     // A version of `ensure_space` that assumes the stream is already stopped
@@ -724,22 +1026,32 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
                 return Err(match err {
                     WaiterError::Comm(err) => Error::Waiter(err),
                     WaiterError::Fault => Error::Fault,
+                    WaiterError::Timeout => Error::Timeout,
                 });
             }
         }
         Ok(())
     }
 
-    // Write directly to the output stream. This function doesn't check whether
-    // there's sufficient space in the buffer, so the caller should call
-    // ensure_space first to wait until there's enough space for the full
-    // message it intends to write.
+    // Write directly to the output stream, or into the batch buffer if we
+    // have one. This function doesn't check whether there's sufficient space
+    // in the buffer, so the caller should call ensure_space first to wait
+    // until there's enough space for the full message it intends to write.
     fn write_to_buffer<V: Into<CommandWord>>(&mut self, v: V) -> Result<(), M, I, W> {
         let v: CommandWord = v.into();
         let v = v.to_raw();
-        let data: [u8; 4] = [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8];
-        let ei = self.ll.borrow_interface();
-        let result = Self::interface_result(ei.continue_write(&data));
+        let result = if N == 0 {
+            let data: [u8; 4] = [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8];
+            let ei = self.ll.borrow_interface();
+            Self::interface_result(ei.continue_write(&data))
+        } else {
+            self.batch.push(v);
+            if self.batch.is_full() {
+                self.flush()
+            } else {
+                Ok(())
+            }
+        };
 
         // We assume we consumed some buffer space even if there was an error,
         // because we can't actually tell if we did or not but reducing our
@@ -751,6 +1063,21 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         result
     }
 
+    // Write a sequence of already-encoded command words directly to the output
+    // stream in a single call, after flushing anything in the batch buffer to
+    // preserve the command order. As with write_to_buffer, the caller must
+    // call ensure_space first.
+    fn write_bytes_to_buffer(&mut self, data: &[u8]) -> Result<(), M, I, W> {
+        self.flush()?;
+        let ei = self.ll.borrow_interface();
+        let result = Self::interface_result(ei.continue_write(data));
+
+        // As with write_to_buffer, we assume the space was consumed even if
+        // the write failed.
+        self.known_space = self.known_space.saturating_sub(data.len() as u16);
+        result
+    }
+
     // Write a series of bytes into the output stream in chunks, with null
     // padding at the end to ensure that the message ends on a four-byte
     // word boundary.
@@ -769,20 +1096,16 @@ impl<M: Model, I: Interface, W: Waiter<M, I>> Coprocessor<M, I, W> {
         &mut self,
         msg: &strfmt::Message<'_, '_, R>,
     ) -> Result<(), M, I, W> {
-        use strfmt::Argument::*;
-        self.write_bytes_chunked(msg.fmt.into_iter())?;
-        if let Some(args) = msg.args {
-            let arg_space = (args.len() * 4) as u16;
-            self.ensure_space(arg_space)?;
-            for arg in args {
-                let raw: u32 = match *arg {
-                    Int(v) => unsafe { core::mem::transmute(v) },
-                    UInt(v) => v,
-                    Char(v) => v as u32,
-                    String(ptr) => ptr.to_raw(),
-                };
-                self.write_to_buffer(raw)?;
-            }
+        // The format string could be longer than the whole ring buffer, so
+        // we wait for space one word at a time.
+        for word in encode::message_format(msg) {
+            self.ensure_space(4)?;
+            self.write_to_buffer(word)?;
+        }
+        let args = encode::message_arguments(msg);
+        self.ensure_space((args.len() * 4) as u16)?;
+        for word in args {
+            self.write_to_buffer(word)?;
         }
         Ok(())
     }
@@ -813,7 +1136,7 @@ impl<M: Model, I: Interface> Coprocessor<M, I, PollingWaiter<M, I>> {
 
 /// These methods are available only when working with a model that has a
 /// coprocessor error message memory space.
-impl<M, I, W> Coprocessor<M, I, W>
+impl<M, I, W, const N: usize> Coprocessor<M, I, W, N>
 where
     M: Model + crate::models::WithCommandErrMem,
     I: Interface,
@@ -828,20 +1151,75 @@ where
     ///
     /// The format of the returned message is determined entirely by the
     /// EVE chip, though it is typically a sequence of bytes representing an
-    /// ASCII string.
+    /// ASCII string. Use [`FaultMessage::kind`] to interpret the messages
+    /// that the EVE programming guides describe.
     pub fn coprocessor_fault_msg(&mut self) -> Result<FaultMessage<M::CommandErrMem>, M, I, W> {
+        let stopped = self.stop_stream()?;
+        let result = self.read_fault_msg_stopped(&stopped);
+        self.start_stream(stopped)?;
+        result
+    }
+
+    /// Recovers from a coprocessor fault, returning the fault message that
+    /// the coprocessor reported.
+    ///
+    /// Call this after another coprocessor method returns the error variant
+    /// `Fault`. It follows the recovery sequence from the EVE programming
+    /// guide: it holds the coprocessor in reset while clearing the command
+    /// ring buffer and display list offsets, and it preserves
+    /// `REG_COPRO_PATCH_PTR` so that the coprocessor's ROM patches remain
+    /// active afterwards. The coprocessor object is then ready to accept new
+    /// commands.
+    ///
+    /// Any commands that the coprocessor hadn't executed at the time of the
+    /// fault are discarded, including any waiting in the batch buffer, and
+    /// so you'll typically need to begin a new display list afterwards. If
+    /// your application uses the flash memory then you'll also need to
+    /// attach it again, because the reset detaches it.
+    pub fn recover_from_fault(&mut self) -> Result<FaultMessage<M::CommandErrMem>, M, I, W> {
+        // There's no point in sending the commands waiting in the batch
+        // buffer, because the reset would discard them anyway.
+        self.batch = Batch::new();
+
+        let stopped = self.stop_stream()?;
+        let result = self.recover_from_fault_stopped(&stopped);
+        self.start_stream(stopped)?;
+        result
+    }
+
+    fn recover_from_fault_stopped(
+        &mut self,
+        stopped: &StoppedStream,
+    ) -> Result<FaultMessage<M::CommandErrMem>, M, I, W> {
+        // We read the message before resetting, because the coprocessor
+        // may reuse the fault message memory once it restarts.
+        let msg = self.read_fault_msg_stopped(stopped)?;
+
+        let ll = self.borrow_low_level(stopped);
+        let patch_ptr = ll.rd16(ll.reg_ptr(Register::COPRO_PATCH_PTR))?;
+        ll.wr8(ll.reg_ptr(Register::CPURESET), 0b001)?;
+        ll.wr32(ll.reg_ptr(Register::CMD_READ), 0)?;
+        ll.wr32(ll.reg_ptr(Register::CMD_WRITE), 0)?;
+        ll.wr32(ll.reg_ptr(Register::CMD_DL), 0)?;
+        ll.wr8(ll.reg_ptr(Register::CPURESET), 0b000)?;
+        ll.wr16(ll.reg_ptr(Register::COPRO_PATCH_PTR), patch_ptr)?;
+
+        self.synchronize(stopped)?;
+        Ok(msg)
+    }
+
+    fn read_fault_msg_stopped(
+        &mut self,
+        stopped: &StoppedStream,
+    ) -> Result<FaultMessage<M::CommandErrMem>, M, I, W> {
         use crate::memory::{CommandErrMem, MemoryRegion};
         use crate::models::WithCommandErrMem;
 
-        let stopped = self.stop_stream()?;
         let mut raw = <<M as WithCommandErrMem>::CommandErrMem as CommandErrMem>::RawMessage::new();
-        {
-            let into = raw.as_storage_bytes();
-            let ll = self.borrow_low_level(&stopped);
-            let addr = <<M as WithCommandErrMem>::CommandErrMem as MemoryRegion>::ptr(0);
-            ll.rd8s(addr, into)?;
-        }
-        self.start_stream(stopped)?;
+        let into = raw.as_storage_bytes();
+        let ll = self.borrow_low_level(stopped);
+        let addr = <<M as WithCommandErrMem>::CommandErrMem as MemoryRegion>::ptr(0);
+        ll.rd8s(addr, into)?;
         Ok(FaultMessage::new(raw))
     }
 }
@@ -853,7 +1231,7 @@ where
 /// support the API now referred to as API level 1, they don't support
 /// _selecting_ that version because the idea of API levels was introduced
 /// only with the BT817 and BT818 models.
-impl<M, I, W> Coprocessor<M, I, W>
+impl<M, I, W, const N: usize> Coprocessor<M, I, W, N>
 where
     M: Model + crate::models::WithCommandErrMem,
     I: Interface,
@@ -862,16 +1240,13 @@ where
     /// On models that support multiple API levels, this selects API level 1
     /// which aims to be backward-compatible with the BT815 and BT816 models.
     pub fn use_api_level_1(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(8, |cp| {
-            cp.write_to_buffer(0xFFFFFF63 as u32)?;
-            cp.write_to_buffer(1 as u32)
-        })
+        self.write_words(&encode::use_api_level(1))
     }
 }
 
 /// These methods are available only when working with a model that allows
 /// _selecting_ coprocessor API level 2.
-impl<M, I, W> Coprocessor<M, I, W>
+impl<M, I, W, const N: usize> Coprocessor<M, I, W, N>
 where
     M: Model + crate::models::WithCommandErrMem,
     I: Interface,
@@ -886,10 +1261,7 @@ where
     /// they already are. If you try to use API level 2 features without
     /// first calling this method then the resulting behavior is undefined.
     pub fn use_api_level_2(&mut self) -> Result<(), M, I, W> {
-        self.write_stream(8, |cp| {
-            cp.write_to_buffer(0xFFFFFF63 as u32)?;
-            cp.write_to_buffer(2 as u32)
-        })
+        self.write_words(&encode::use_api_level(2))
     }
 }
 
@@ -901,13 +1273,277 @@ pub use crate::error::CoprocessorError as Error;
 pub struct FaultMessage<R: crate::memory::CommandErrMem>(R::RawMessage);
 
 impl<R: crate::memory::CommandErrMem> FaultMessage<R> {
-    fn new(raw: R::RawMessage) -> Self {
+    pub(crate) fn new(raw: R::RawMessage) -> Self {
         Self(raw)
     }
 
     pub fn as_bytes<'a>(&'a self) -> &'a [u8] {
         self.0.as_bytes()
     }
+
+    /// Returns the cause of the fault, as interpreted from the message.
+    pub fn kind<'a>(&'a self) -> FaultKind<'a> {
+        FaultKind::from_bytes(self.as_bytes())
+    }
+}
+
+impl<R: crate::memory::CommandErrMem> core::fmt::Display for FaultMessage<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.kind(), f)
+    }
+}
+
+/// The cause of a coprocessor fault, as reported in a [`FaultMessage`].
+///
+/// The documented BT81x fault messages each have their own variant below.
+/// The chip may add a prefix such as `ERROR:` and further detail after the
+/// message, and may truncate a long message, so
+/// [`from_bytes`](FaultKind::from_bytes) tolerates those differences. Any
+/// other message is returned as [`FaultKind::Other`] with its raw text,
+/// which is still suitable for showing to a developer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FaultKind<'a> {
+    /// The display list exceeded the size of the display list memory.
+    DisplayListOverflow,
+
+    /// A command that must start with an empty display list was used after
+    /// other commands had already been added to the current one.
+    DisplayListMustBeEmpty,
+
+    /// `CMD_LOADIMAGE` or `CMD_PLAYVIDEO` encountered data in a format it
+    /// doesn't support.
+    ImageTypeNotSupported,
+
+    /// `CMD_SETBASE` was given a base outside of the supported range.
+    InvalidBase,
+
+    /// A format string, such as in `CMD_TEXT` with `OPT_FORMAT`, contained
+    /// an unsupported conversion character.
+    InvalidFormatCharacter,
+
+    /// A command was given an amount of data that isn't valid for it.
+    InvalidDataSize,
+
+    /// A command was given a size argument outside of its supported range.
+    InvalidSize,
+
+    /// `CMD_LOADIMAGE` encountered a JPEG image using features it doesn't
+    /// support, such as progressive encoding.
+    UnsupportedJpeg,
+
+    /// `CMD_INFLATE` or `CMD_INFLATE2` was given data that isn't a valid
+    /// deflate stream.
+    BadInflateData,
+
+    /// A flash command was used while the flash memory wasn't attached.
+    FlashNotAttached,
+
+    /// A flash write targeted an area of flash memory that hasn't been
+    /// erased.
+    FlashNotBlank,
+
+    /// A command reading from the media FIFO ran out of data.
+    MediaFifoUnderflow,
+
+    /// The coprocessor encountered an internal exception.
+    CoprocessorException,
+
+    /// Any other message, with its raw text.
+    ///
+    /// If the message isn't valid UTF-8 then this contains only the valid
+    /// prefix.
+    Other(&'a str),
+}
+
+impl<'a> FaultKind<'a> {
+    // All of the fault kinds that have a fixed message. Any message not
+    // matching one of these becomes FaultKind::Other.
+    const KNOWN: [FaultKind<'static>; 13] = [
+        FaultKind::DisplayListOverflow,
+        FaultKind::DisplayListMustBeEmpty,
+        FaultKind::ImageTypeNotSupported,
+        FaultKind::InvalidBase,
+        FaultKind::InvalidFormatCharacter,
+        FaultKind::InvalidDataSize,
+        FaultKind::InvalidSize,
+        FaultKind::UnsupportedJpeg,
+        FaultKind::BadInflateData,
+        FaultKind::FlashNotAttached,
+        FaultKind::FlashNotBlank,
+        FaultKind::MediaFifoUnderflow,
+        FaultKind::CoprocessorException,
+    ];
+
+    /// Interprets the given raw fault message text.
+    ///
+    /// The comparison with the known messages ignores letter case, an
+    /// `ERROR:` prefix and differences in whitespace. A message that starts
+    /// with a known message and then adds more detail matches that message,
+    /// as does a truncated message of at least two words that is the start
+    /// of exactly one known message.
+    pub fn from_bytes(raw: &'a [u8]) -> Self {
+        let text = match core::str::from_utf8(raw) {
+            Ok(s) => s,
+            // The prefix up to valid_up_to is always valid UTF-8.
+            Err(err) => core::str::from_utf8(&raw[..err.valid_up_to()]).unwrap(),
+        };
+        let normal = normalize_fault_text(text);
+        if normal.is_empty() {
+            return FaultKind::Other(text);
+        }
+
+        let mut truncated = None;
+        let mut truncated_count = 0;
+        for kind in Self::KNOWN.iter() {
+            match match_fault_text(normal, kind.as_str()) {
+                FaultTextMatch::Complete => return *kind,
+                FaultTextMatch::Truncated => {
+                    truncated = Some(*kind);
+                    truncated_count += 1;
+                }
+                FaultTextMatch::None => {}
+            }
+        }
+        // A truncated message that could be the start of more than one
+        // known message is ambiguous, so we don't guess. We also don't
+        // guess from a single word, which is more likely to be an unknown
+        // message than a truncated one.
+        let multi_word = normal.split_whitespace().nth(1).is_some();
+        match truncated {
+            Some(kind) if truncated_count == 1 && multi_word => kind,
+            _ => FaultKind::Other(text),
+        }
+    }
+
+    /// Returns the message text that represents the fault kind.
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            FaultKind::DisplayListOverflow => "display list overflow",
+            FaultKind::DisplayListMustBeEmpty => "display list must be empty",
+            FaultKind::ImageTypeNotSupported => "image type not supported",
+            FaultKind::InvalidBase => "invalid base",
+            FaultKind::InvalidFormatCharacter => "invalid format character",
+            FaultKind::InvalidDataSize => "invalid data size",
+            FaultKind::InvalidSize => "invalid size",
+            FaultKind::UnsupportedJpeg => "unsupported JPEG",
+            FaultKind::BadInflateData => "bad inflate data",
+            FaultKind::FlashNotAttached => "flash not attached",
+            FaultKind::FlashNotBlank => "flash not blank",
+            FaultKind::MediaFifoUnderflow => "mediafifo underflow",
+            FaultKind::CoprocessorException => "coprocessor exception",
+            FaultKind::Other(text) => text,
+        }
+    }
+}
+
+// Removes the parts of a fault message that vary between chips, leaving
+// just the text to compare with the known messages.
+fn normalize_fault_text(text: &str) -> &str {
+    let text = trim_fault_text(text);
+    match text.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("error:") => trim_fault_text(&text[6..]),
+        _ => text,
+    }
+}
+
+fn trim_fault_text(text: &str) -> &str {
+    text.trim_matches(|c: char| c.is_whitespace() || c == '\0' || c == '.' || c == '!')
+}
+
+enum FaultTextMatch {
+    // The text is the known message, possibly followed by more detail.
+    Complete,
+    // The text is a prefix of the known message.
+    Truncated,
+    None,
+}
+
+// Compares a normalized fault message with a known message word by word,
+// ignoring letter case and the amount of whitespace between words.
+fn match_fault_text(text: &str, known: &str) -> FaultTextMatch {
+    let mut text_words = text.split_whitespace().peekable();
+    let mut known_words = known.split_whitespace();
+    loop {
+        match (text_words.next(), known_words.next()) {
+            (_, None) => return FaultTextMatch::Complete,
+            (None, Some(_)) => return FaultTextMatch::Truncated,
+            (Some(got), Some(want)) => {
+                if got.eq_ignore_ascii_case(want) {
+                    continue;
+                }
+                // The message might have been cut off partway through its
+                // final word.
+                let is_last = text_words.peek().is_none();
+                let is_prefix = want
+                    .get(..got.len())
+                    .is_some_and(|p| p.eq_ignore_ascii_case(got));
+                if is_last && is_prefix {
+                    return FaultTextMatch::Truncated;
+                }
+                return FaultTextMatch::None;
+            }
+        }
+    }
+}
+
+impl<'a> core::fmt::Display for FaultKind<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A handle for a display list fragment previously captured into main memory
+/// using
+/// [`Coprocessor::capture_display_list_fragment`](Coprocessor::capture_display_list_fragment).
+///
+/// A fragment converts into a [`Slice`](crate::memory::Slice) of main memory,
+/// and so you can pass it to
+/// [`Coprocessor::append_display_list_from_main_mem`](Coprocessor::append_display_list_from_main_mem)
+/// as many times as you like, for as long as the memory it refers to remains
+/// unchanged.
+pub struct DisplayListFragment<M: Model> {
+    pub(super) slice: Slice<M::MainMem>,
+}
+
+impl<M: Model> DisplayListFragment<M> {
+    /// Returns the main memory slice containing the fragment's display list
+    /// commands.
+    pub fn slice(&self) -> Slice<M::MainMem> {
+        self.slice
+    }
+
+    /// Returns the length of the fragment in bytes.
+    pub fn len(&self) -> u32 {
+        self.slice.len()
+    }
+
+    /// Returns true if the fragment contains no display list commands.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<M: Model> Clone for DisplayListFragment<M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: Model> Copy for DisplayListFragment<M> {}
+
+impl<M: Model> core::fmt::Debug for DisplayListFragment<M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        f.debug_tuple("DisplayListFragment")
+            .field(&self.slice)
+            .finish()
+    }
+}
+
+impl<M: Model> From<DisplayListFragment<M>> for Slice<M::MainMem> {
+    fn from(frag: DisplayListFragment<M>) -> Self {
+        frag.slice
+    }
 }
 
 #[doc(hide)]
@@ -950,7 +1586,7 @@ impl FaultMessageRaw for [u8; 128] {
 // type checker, not relevant at runtime.
 struct StoppedStream;
 
-impl<M, I, W> crate::display_list::Builder for Coprocessor<M, I, W>
+impl<M, I, W, const N: usize> crate::display_list::Builder for Coprocessor<M, I, W, N>
 where
     M: Model,
     I: Interface,
@@ -970,15 +1606,3 @@ where
         self.append_display_list(cmd)
     }
 }
-
-fn maybe_opt_format<R: crate::memory::MainMem>(
-    given: u32,
-    msg: &strfmt::Message<'_, '_, R>,
-) -> u32 {
-    const OPT_FORMAT: u32 = 4096;
-    if msg.needs_format() {
-        given | OPT_FORMAT
-    } else {
-        given
-    }
-}
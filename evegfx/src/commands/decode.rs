@@ -0,0 +1,1482 @@
+//! Decoding of coprocessor command streams.
+//!
+//! The coprocessor ring buffer (`RAM_CMD`) contains a mixture of coprocessor
+//! commands, their variable-length arguments and inline strings, and
+//! display list commands to be copied verbatim into the display list. The
+//! functions in this module can turn a sequence of such words back into
+//! structured commands, which is useful when debugging coprocessor faults
+//! or when writing tests that describe the commands they expect to be sent.
+
+use super::options;
+use crate::display_list::DLCmd;
+use crate::graphics::{WidgetPos, WidgetRect};
+
+/// Returns an iterator over the commands encoded in the given sequence of
+/// coprocessor command words.
+///
+/// The words must be in the same order and format as they would be written
+/// into the coprocessor ring buffer, with the first word being the start of
+/// a command. Words that don't start with the coprocessor command prefix
+/// `0xffffff` are interpreted as display list commands.
+///
+/// ```rust
+/// use evegfx::commands::{decode_commands, DecodedCommand};
+///
+/// let words = [0xffffff00, 0x26000007, 0x00000000, 0xffffff01];
+/// let mut cmds = decode_commands(&words);
+/// assert_eq!(cmds.next(), Some(DecodedCommand::DLStart));
+/// assert_eq!(format!("{}", cmds.next().unwrap()), "CLEAR(1, 1, 1)");
+/// assert_eq!(format!("{}", cmds.next().unwrap()), "DISPLAY()");
+/// assert_eq!(cmds.next(), Some(DecodedCommand::Swap));
+/// assert_eq!(cmds.next(), None);
+/// ```
+pub fn decode_commands(words: &[u32]) -> Commands<'_> {
+    Commands {
+        words: words,
+        offset: 0,
+    }
+}
+
+/// An iterator over the commands in a coprocessor command stream, as
+/// returned by [`decode_commands`].
+///
+/// If the stream ends partway through a command then the final item is
+/// [`DecodedCommand::Truncated`], containing whatever words were left.
+#[derive(Debug, Clone)]
+pub struct Commands<'a> {
+    words: &'a [u32],
+    offset: usize,
+}
+
+impl<'a> Commands<'a> {
+    /// Returns the index, in words from the start of the original stream,
+    /// of the command that will be returned by the next call to `next`.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the words that haven't been decoded yet.
+    pub fn remaining(&self) -> &'a [u32] {
+        self.words
+    }
+
+    // Removes the given number of words from the front of the stream and
+    // returns them, or returns `None` if there aren't enough words left.
+    fn take(&mut self, n: usize) -> Option<&'a [u32]> {
+        if self.words.len() < n {
+            return None;
+        }
+        let (taken, rest) = self.words.split_at(n);
+        self.words = rest;
+        self.offset += n;
+        Some(taken)
+    }
+
+    fn take_all(&mut self) -> &'a [u32] {
+        let n = self.words.len();
+        self.take(n).unwrap()
+    }
+
+    // Removes a null-terminated string from the front of the stream,
+    // including the padding after its terminator.
+    fn take_string(&mut self) -> Option<&'a [u32]> {
+        let n = self
+            .words
+            .iter()
+            .position(|w| w.to_le_bytes().contains(&0))?;
+        self.take(n + 1)
+    }
+
+    // Removes a message from the front of the stream, along with its
+    // format arguments if the options indicate that it will be formatted.
+    fn take_message(&mut self, opts: u32) -> Option<DecodedMessage<'a>> {
+        let text = self.take_string()?;
+        let args = if (opts & options::OPT_FORMAT) != 0 {
+            let count = count_format_args(string_bytes(text));
+            Some(self.take(count)?)
+        } else {
+            None
+        };
+        Some(DecodedMessage {
+            text: text,
+            args: args,
+        })
+    }
+
+    fn decode_coprocessor_command(&mut self, opcode: u32) -> Option<DecodedCommand<'a>> {
+        use DecodedCommand::*;
+        Some(match opcode {
+            0xFFFFFF00 => DLStart,
+            0xFFFFFF01 => Swap,
+            0xFFFFFF02 => Interrupt {
+                ms: self.take(1)?[0],
+            },
+            0xFFFFFF09 => BgColor {
+                rgb: self.take(1)?[0],
+            },
+            0xFFFFFF0A => FgColor {
+                rgb: self.take(1)?[0],
+            },
+            0xFFFFFF0B => {
+                let args = self.take(4)?;
+                let (x0, y0) = split_i16(args[0]);
+                let (x1, y1) = split_i16(args[2]);
+                Gradient {
+                    start: WidgetPos::new(x0, y0),
+                    start_rgb: args[1],
+                    end: WidgetPos::new(x1, y1),
+                    end_rgb: args[3],
+                }
+            }
+            0xFFFFFF0C => {
+                let args = self.take(2)?;
+                let (x, y) = split_i16(args[0]);
+                let (font, opts) = split_u16(args[1]);
+                let opts = opts as u32;
+                Text {
+                    pos: WidgetPos::new(x, y),
+                    font: options::FontRef::new_raw(font as u8),
+                    options: options::Text::from_raw(opts & !options::OPT_FORMAT),
+                    message: self.take_message(opts)?,
+                }
+            }
+            0xFFFFFF0D => {
+                let args = self.take(3)?;
+                let (x, y) = split_i16(args[0]);
+                let (w, h) = split_i16(args[1]);
+                let (font, opts) = split_u16(args[2]);
+                let opts = opts as u32;
+                Button {
+                    rect: WidgetRect::new(x, y, w, h),
+                    font: options::FontRef::new_raw(font as u8),
+                    options: options::Button::from_raw(opts & !options::OPT_FORMAT),
+                    message: self.take_message(opts)?,
+                }
+            }
+            0xFFFFFF0E => {
+                let args = self.take(3)?;
+                let (font, opts) = split_u16(args[2]);
+                Keys {
+                    rect: split_rect(args[0], args[1]),
+                    font: options::FontRef::new_raw(font as u8),
+                    options: opts,
+                    // The keys widget doesn't support formatted strings.
+                    message: self.take_message(0)?,
+                }
+            }
+            0xFFFFFF0F => {
+                let args = self.take(4)?;
+                let (opts, value) = split_u16(args[2]);
+                Progress {
+                    rect: split_rect(args[0], args[1]),
+                    options: opts,
+                    value: value,
+                    range: args[3] as u16,
+                }
+            }
+            0xFFFFFF10 => {
+                let args = self.take(4)?;
+                let (opts, value) = split_u16(args[2]);
+                Slider {
+                    rect: split_rect(args[0], args[1]),
+                    options: opts,
+                    value: value,
+                    range: args[3] as u16,
+                }
+            }
+            0xFFFFFF11 => {
+                let args = self.take(4)?;
+                let (opts, value) = split_u16(args[2]);
+                let (size, range) = split_u16(args[3]);
+                Scrollbar {
+                    rect: split_rect(args[0], args[1]),
+                    options: opts,
+                    value: value,
+                    size: size,
+                    range: range,
+                }
+            }
+            0xFFFFFF12 => {
+                let args = self.take(3)?;
+                let (x, y) = split_i16(args[0]);
+                let (width, font) = split_u16(args[1]);
+                let (opts, state) = split_u16(args[2]);
+                Toggle {
+                    pos: WidgetPos::new(x, y),
+                    width: width as i16,
+                    font: options::FontRef::new_raw(font as u8),
+                    options: opts & !(options::OPT_FORMAT as u16),
+                    state: state,
+                    message: self.take_message(opts as u32)?,
+                }
+            }
+            0xFFFFFF13 => {
+                let args = self.take(4)?;
+                let (x, y) = split_i16(args[0]);
+                let (radius, opts) = split_u16(args[1]);
+                let (major, minor) = split_u16(args[2]);
+                let (value, range) = split_u16(args[3]);
+                Gauge {
+                    pos: WidgetPos::new(x, y),
+                    radius: radius as i16,
+                    options: opts,
+                    major: major,
+                    minor: minor,
+                    value: value,
+                    range: range,
+                }
+            }
+            0xFFFFFF14 => {
+                let args = self.take(4)?;
+                let (x, y) = split_i16(args[0]);
+                let (radius, opts) = split_u16(args[1]);
+                let (h, m) = split_u16(args[2]);
+                let (s, ms) = split_u16(args[3]);
+                Clock {
+                    pos: WidgetPos::new(x, y),
+                    radius: radius as i16,
+                    options: opts,
+                    h: h,
+                    m: m,
+                    s: s,
+                    ms: ms,
+                }
+            }
+            0xFFFFFF16 => {
+                let args = self.take(2)?;
+                let (x, y) = split_i16(args[0]);
+                let (style, scale) = split_u16(args[1]);
+                Spinner {
+                    pos: WidgetPos::new(x, y),
+                    style: style,
+                    scale: scale,
+                }
+            }
+            0xFFFFFF18 => {
+                let args = self.take(3)?;
+                MemCrc {
+                    ptr: args[0],
+                    num: args[1],
+                    result: args[2],
+                }
+            }
+            0xFFFFFF19 => {
+                let args = self.take(2)?;
+                RegRead {
+                    ptr: args[0],
+                    result: args[1],
+                }
+            }
+            0xFFFFFF1A => {
+                let args = self.take(2)?;
+                let (ptr, num) = (args[0], args[1]);
+                MemWrite {
+                    ptr: ptr,
+                    num: num,
+                    data: self.take(padded_words(num))?,
+                }
+            }
+            0xFFFFFF1B => {
+                let args = self.take(3)?;
+                MemSet {
+                    ptr: args[0],
+                    value: args[1] as u8,
+                    num: args[2],
+                }
+            }
+            0xFFFFFF1C => {
+                let args = self.take(2)?;
+                MemZero {
+                    ptr: args[0],
+                    num: args[1],
+                }
+            }
+            0xFFFFFF1D => {
+                let args = self.take(3)?;
+                MemCpy {
+                    dest: args[0],
+                    src: args[1],
+                    num: args[2],
+                }
+            }
+            0xFFFFFF1E => {
+                let args = self.take(2)?;
+                Append {
+                    ptr: args[0],
+                    num: args[1],
+                }
+            }
+            0xFFFFFF22 => Inflate {
+                ptr: self.take(1)?[0],
+                data: self.take_all(),
+            },
+            0xFFFFFF23 => GetPtr {
+                result: self.take(1)?[0],
+            },
+            0xFFFFFF24 => {
+                let args = self.take(2)?;
+                LoadImage {
+                    ptr: args[0],
+                    options: options::LoadImage::from_raw(args[1]),
+                    data: self.take_streamed_data(args[1]),
+                }
+            }
+            0xFFFFFF26 => LoadIdentity,
+            0xFFFFFF27 => {
+                let args = self.take(2)?;
+                Translate {
+                    tx: args[0] as i32,
+                    ty: args[1] as i32,
+                }
+            }
+            0xFFFFFF28 => {
+                let args = self.take(2)?;
+                Scale {
+                    sx: args[0] as i32,
+                    sy: args[1] as i32,
+                }
+            }
+            0xFFFFFF29 => Rotate {
+                angle: self.take(1)?[0],
+            },
+            0xFFFFFF2A => SetMatrix,
+            0xFFFFFF2D => {
+                let args = self.take(3)?;
+                let (x, y) = split_i16(args[0]);
+                let (radius, opts) = split_u16(args[1]);
+                Dial {
+                    pos: WidgetPos::new(x, y),
+                    radius: radius as i16,
+                    options: opts,
+                    value: args[2],
+                }
+            }
+            0xFFFFFF2E => {
+                let args = self.take(3)?;
+                let (x, y) = split_i16(args[0]);
+                let (font, opts) = split_u16(args[1]);
+                Number {
+                    pos: WidgetPos::new(x, y),
+                    font: options::FontRef::new_raw(font as u8),
+                    options: opts,
+                    value: args[2] as i32,
+                }
+            }
+            0xFFFFFF31 => Logo,
+            0xFFFFFF32 => ColdStart,
+            0xFFFFFF36 => SetRotate {
+                rotation: self.take(1)?[0],
+            },
+            0xFFFFFF3A => {
+                let opts = self.take(1)?[0];
+                PlayVideo {
+                    options: opts,
+                    data: self.take_streamed_data(opts),
+                }
+            }
+            0xFFFFFF3B => {
+                let args = self.take(3)?;
+                SetFont2 {
+                    font: args[0],
+                    ptr: args[1],
+                    first_char: args[2],
+                }
+            }
+            0xFFFFFF42 => WaitVideoScanout,
+            0xFFFFFF43 => {
+                let args = self.take(3)?;
+                let (format, width) = split_u16(args[1]);
+                SetBitmap {
+                    ptr: args[0],
+                    format: format,
+                    width: width,
+                    height: args[2] as u16,
+                }
+            }
+            0xFFFFFF44 => FlashErase,
+            0xFFFFFF45 => {
+                let args = self.take(2)?;
+                let (ptr, num) = (args[0], args[1]);
+                FlashWrite {
+                    ptr: ptr,
+                    num: num,
+                    data: self.take(padded_words(num))?,
+                }
+            }
+            0xFFFFFF46 => {
+                let args = self.take(3)?;
+                FlashRead {
+                    dest: args[0],
+                    src: args[1],
+                    num: args[2],
+                }
+            }
+            0xFFFFFF47 => {
+                let args = self.take(3)?;
+                FlashUpdate {
+                    dest: args[0],
+                    src: args[1],
+                    num: args[2],
+                }
+            }
+            0xFFFFFF48 => FlashDetach,
+            0xFFFFFF49 => FlashAttach,
+            0xFFFFFF4A => FlashFast {
+                result: self.take(1)?[0],
+            },
+            0xFFFFFF4B => FlashSpiDesel,
+            0xFFFFFF4C => {
+                let num = self.take(1)?[0];
+                FlashSpiTx {
+                    num: num,
+                    data: self.take(padded_words(num))?,
+                }
+            }
+            0xFFFFFF4D => {
+                let args = self.take(2)?;
+                FlashSpiRx {
+                    ptr: args[0],
+                    num: args[1],
+                }
+            }
+            0xFFFFFF4E => FlashSource {
+                ptr: self.take(1)?[0],
+            },
+            0xFFFFFF50 => {
+                let args = self.take(2)?;
+                Inflate2 {
+                    ptr: args[0],
+                    options: args[1],
+                    data: self.take_streamed_data(args[1]),
+                }
+            }
+            0xFFFFFF61 => Testcard,
+            0xFFFFFF63 => ApiLevel {
+                level: self.take(1)?[0],
+            },
+            0xFFFFFF65 => Wait {
+                us: self.take(1)?[0],
+            },
+            0xFFFFFF70 => {
+                let args = self.take(3)?;
+                FlashProgram {
+                    dest: args[0],
+                    src: args[1],
+                    num: args[2],
+                }
+            }
+            _ => match OTHER_COMMANDS.iter().find(|c| c.0 == opcode) {
+                Some(&(_, name, count)) => Other {
+                    name: name,
+                    args: self.take(count)?,
+                },
+                // We can't tell where an unknown command's arguments end,
+                // so everything after it is opaque.
+                None => Unknown {
+                    opcode: opcode,
+                    rest: self.take_all(),
+                },
+            },
+        })
+    }
+
+    // Removes the data following a command that can take its data either
+    // from the command stream or from elsewhere, depending on its options.
+    // The command stream doesn't record the length of the data, so this
+    // takes everything remaining in the stream.
+    fn take_streamed_data(&mut self, opts: u32) -> &'a [u32] {
+        if (opts & (options::OPT_MEDIAFIFO | options::OPT_FLASH)) != 0 {
+            &[]
+        } else {
+            self.take_all()
+        }
+    }
+}
+
+// The names and fixed argument counts of the documented commands that the
+// decoder doesn't decode into more specific variants of `DecodedCommand`.
+const OTHER_COMMANDS: &[(u32, &str, usize)] = &[
+    (0xFFFFFF15, "CMD_CALIBRATE", 1),
+    (0xFFFFFF17, "CMD_STOP", 0),
+    (0xFFFFFF1F, "CMD_SNAPSHOT", 1),
+    (0xFFFFFF20, "CMD_TOUCH_TRANSFORM", 13),
+    (0xFFFFFF21, "CMD_BITMAP_TRANSFORM", 13),
+    (0xFFFFFF25, "CMD_GETPROPS", 3),
+    (0xFFFFFF2B, "CMD_SETFONT", 2),
+    (0xFFFFFF2C, "CMD_TRACK", 3),
+    (0xFFFFFF2F, "CMD_SCREENSAVER", 0),
+    (0xFFFFFF30, "CMD_SKETCH", 4),
+    (0xFFFFFF33, "CMD_GETMATRIX", 6),
+    (0xFFFFFF34, "CMD_GRADCOLOR", 1),
+    (0xFFFFFF37, "CMD_SNAPSHOT2", 4),
+    (0xFFFFFF38, "CMD_SETBASE", 1),
+    (0xFFFFFF39, "CMD_MEDIAFIFO", 2),
+    (0xFFFFFF3C, "CMD_SETSCRATCH", 1),
+    (0xFFFFFF3F, "CMD_ROMFONT", 2),
+    (0xFFFFFF40, "CMD_VIDEOSTART", 0),
+    (0xFFFFFF41, "CMD_VIDEOFRAME", 2),
+    (0xFFFFFF4F, "CMD_CLEARCACHE", 0),
+    (0xFFFFFF51, "CMD_ROTATEAROUND", 4),
+    (0xFFFFFF52, "CMD_RESETFONTS", 0),
+    (0xFFFFFF53, "CMD_ANIMSTART", 3),
+    (0xFFFFFF54, "CMD_ANIMSTOP", 1),
+    (0xFFFFFF55, "CMD_ANIMXY", 2),
+    (0xFFFFFF56, "CMD_ANIMDRAW", 1),
+    (0xFFFFFF57, "CMD_GRADIENTA", 4),
+    (0xFFFFFF58, "CMD_FILLWIDTH", 1),
+    (0xFFFFFF59, "CMD_APPENDF", 2),
+    (0xFFFFFF5A, "CMD_ANIMFRAME", 3),
+    (0xFFFFFF5F, "CMD_VIDEOSTARTF", 0),
+    (0xFFFFFF60, "CMD_CALIBRATESUB", 3),
+    (0xFFFFFF62, "CMD_HSF", 1),
+    (0xFFFFFF64, "CMD_GETIMAGE", 5),
+    (0xFFFFFF66, "CMD_RETURN", 0),
+    (0xFFFFFF67, "CMD_CALLLIST", 1),
+    (0xFFFFFF68, "CMD_NEWLIST", 1),
+    (0xFFFFFF69, "CMD_ENDLIST", 0),
+    (0xFFFFFF6A, "CMD_PCLKFREQ", 3),
+    (0xFFFFFF6B, "CMD_FONTCACHE", 3),
+    (0xFFFFFF6C, "CMD_FONTCACHEQUERY", 2),
+    (0xFFFFFF6D, "CMD_ANIMFRAMERAM", 3),
+    (0xFFFFFF6E, "CMD_ANIMSTARTRAM", 3),
+    (0xFFFFFF6F, "CMD_RUNANIM", 2),
+];
+
+impl<'a> Iterator for Commands<'a> {
+    type Item = DecodedCommand<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let before = self.clone();
+        let opcode = self.take(1)?[0];
+        if (opcode & 0xFFFFFF00) != 0xFFFFFF00 {
+            return Some(DecodedCommand::DisplayList(DLCmd::from_raw(opcode)));
+        }
+        match self.decode_coprocessor_command(opcode) {
+            Some(cmd) => Some(cmd),
+            None => {
+                *self = before;
+                Some(DecodedCommand::Truncated(self.take_all()))
+            }
+        }
+    }
+}
+
+/// A coprocessor command decoded from a command stream by
+/// [`decode_commands`].
+///
+/// Memory addresses are given as raw integers, because a command stream
+/// alone doesn't indicate which EVE model it was intended for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodedCommand<'a> {
+    /// A display list command to be appended to the current display list.
+    DisplayList(DLCmd),
+
+    Append {
+        ptr: u32,
+        num: u32,
+    },
+    ApiLevel {
+        level: u32,
+    },
+    BgColor {
+        rgb: u32,
+    },
+    Button {
+        rect: WidgetRect,
+        font: options::FontRef,
+        options: options::Button,
+        message: DecodedMessage<'a>,
+    },
+    Clock {
+        pos: WidgetPos,
+        radius: i16,
+        options: u16,
+        h: u16,
+        m: u16,
+        s: u16,
+        ms: u16,
+    },
+    ColdStart,
+    Dial {
+        pos: WidgetPos,
+        radius: i16,
+        options: u16,
+        value: u32,
+    },
+    DLStart,
+    FgColor {
+        rgb: u32,
+    },
+    FlashAttach,
+    FlashDetach,
+    FlashErase,
+    FlashFast {
+        result: u32,
+    },
+    FlashProgram {
+        dest: u32,
+        src: u32,
+        num: u32,
+    },
+    FlashRead {
+        dest: u32,
+        src: u32,
+        num: u32,
+    },
+    FlashSource {
+        ptr: u32,
+    },
+    FlashSpiDesel,
+    FlashSpiRx {
+        ptr: u32,
+        num: u32,
+    },
+    FlashSpiTx {
+        num: u32,
+        data: &'a [u32],
+    },
+    FlashUpdate {
+        dest: u32,
+        src: u32,
+        num: u32,
+    },
+    FlashWrite {
+        ptr: u32,
+        num: u32,
+        data: &'a [u32],
+    },
+    Gauge {
+        pos: WidgetPos,
+        radius: i16,
+        options: u16,
+        major: u16,
+        minor: u16,
+        value: u16,
+        range: u16,
+    },
+    GetPtr {
+        result: u32,
+    },
+    Gradient {
+        start: WidgetPos,
+        start_rgb: u32,
+        end: WidgetPos,
+        end_rgb: u32,
+    },
+    /// Inflates a deflate stream into main memory.
+    ///
+    /// The command stream doesn't record the length of the compressed data,
+    /// so `data` is everything remaining in the stream after the command.
+    Inflate {
+        ptr: u32,
+        data: &'a [u32],
+    },
+    /// Inflates a deflate stream into main memory, possibly reading it from
+    /// the media FIFO or from flash memory.
+    ///
+    /// If the data is in the command stream then `data` is everything
+    /// remaining in the stream after the command, as for `Inflate`.
+    /// Otherwise, `data` is empty.
+    Inflate2 {
+        ptr: u32,
+        options: u32,
+        data: &'a [u32],
+    },
+    Interrupt {
+        ms: u32,
+    },
+    Keys {
+        rect: WidgetRect,
+        font: options::FontRef,
+        options: u16,
+        message: DecodedMessage<'a>,
+    },
+    LoadIdentity,
+    /// Decodes a JPEG or PNG image into main memory.
+    ///
+    /// The command stream doesn't record the length of the image data,
+    /// so `data` is everything remaining in the stream after the command,
+    /// unless the options select the media FIFO or flash memory as the
+    /// source of the image, in which case `data` is empty.
+    LoadImage {
+        ptr: u32,
+        options: options::LoadImage,
+        data: &'a [u32],
+    },
+    Logo,
+    MemCpy {
+        dest: u32,
+        src: u32,
+        num: u32,
+    },
+    MemCrc {
+        ptr: u32,
+        num: u32,
+        result: u32,
+    },
+    MemSet {
+        ptr: u32,
+        value: u8,
+        num: u32,
+    },
+    MemWrite {
+        ptr: u32,
+        num: u32,
+        data: &'a [u32],
+    },
+    MemZero {
+        ptr: u32,
+        num: u32,
+    },
+    Number {
+        pos: WidgetPos,
+        font: options::FontRef,
+        options: u16,
+        value: i32,
+    },
+    /// Plays back a video, possibly reading it from the media FIFO or from
+    /// flash memory.
+    ///
+    /// As with `LoadImage`, `data` is either everything remaining in the
+    /// stream after the command or empty, depending on the options.
+    PlayVideo {
+        options: u32,
+        data: &'a [u32],
+    },
+    Progress {
+        rect: WidgetRect,
+        options: u16,
+        value: u16,
+        range: u16,
+    },
+    RegRead {
+        ptr: u32,
+        result: u32,
+    },
+    Rotate {
+        angle: u32,
+    },
+    Scale {
+        sx: i32,
+        sy: i32,
+    },
+    Scrollbar {
+        rect: WidgetRect,
+        options: u16,
+        value: u16,
+        size: u16,
+        range: u16,
+    },
+    SetBitmap {
+        ptr: u32,
+        format: u16,
+        width: u16,
+        height: u16,
+    },
+    SetFont2 {
+        font: u32,
+        ptr: u32,
+        first_char: u32,
+    },
+    SetMatrix,
+    Slider {
+        rect: WidgetRect,
+        options: u16,
+        value: u16,
+        range: u16,
+    },
+    Spinner {
+        pos: WidgetPos,
+        style: u16,
+        scale: u16,
+    },
+    Swap,
+    SetRotate {
+        rotation: u32,
+    },
+    Testcard,
+    Text {
+        pos: WidgetPos,
+        font: options::FontRef,
+        options: options::Text,
+        message: DecodedMessage<'a>,
+    },
+    Toggle {
+        pos: WidgetPos,
+        width: i16,
+        font: options::FontRef,
+        options: u16,
+        state: u16,
+        message: DecodedMessage<'a>,
+    },
+    Translate {
+        tx: i32,
+        ty: i32,
+    },
+    Wait {
+        us: u32,
+    },
+    WaitVideoScanout,
+
+    /// A documented coprocessor command that has only a fixed number of
+    /// argument words, which the decoder returns without interpreting them.
+    Other {
+        /// The name of the command, such as `"CMD_SNAPSHOT"`.
+        name: &'static str,
+        args: &'a [u32],
+    },
+
+    /// A coprocessor command whose opcode isn't known to this decoder.
+    ///
+    /// The decoder can't determine how many argument words belong to an
+    /// unknown command, and so `rest` is everything remaining in the stream
+    /// after its opcode, rather than trying to decode those words as more
+    /// commands.
+    Unknown {
+        opcode: u32,
+        rest: &'a [u32],
+    },
+
+    /// The words remaining at the end of a stream that ends partway through
+    /// a command.
+    Truncated(&'a [u32]),
+}
+
+/// A text message embedded in a coprocessor command, along with its
+/// format arguments, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedMessage<'a> {
+    text: &'a [u32],
+    args: Option<&'a [u32]>,
+}
+
+impl<'a> DecodedMessage<'a> {
+    /// Returns an iterator over the bytes of the message text, excluding
+    /// the null terminator and padding.
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + 'a {
+        string_bytes(self.text)
+    }
+
+    /// Returns true if the text of the message is equal to the given bytes,
+    /// which may optionally include a null terminator.
+    pub fn text_eq(&self, want: &[u8]) -> bool {
+        let want = match want.split_last() {
+            Some((0, rest)) => rest,
+            _ => want,
+        };
+        self.bytes().eq(want.iter().copied())
+    }
+
+    /// Returns the raw format arguments, or `None` if the message isn't
+    /// formatted.
+    pub fn args(&self) -> Option<&'a [u32]> {
+        self.args
+    }
+}
+
+impl<'a> core::fmt::Display for DecodedMessage<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use core::fmt::Write;
+        f.write_char('"')?;
+        for c in self.bytes() {
+            for c in core::ascii::escape_default(c) {
+                f.write_char(c as char)?;
+            }
+        }
+        f.write_char('"')?;
+        if let Some(args) = self.args {
+            for arg in args {
+                write!(f, ", {:#x}", arg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> core::fmt::Display for DecodedCommand<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use DecodedCommand::*;
+        match *self {
+            DisplayList(cmd) => write!(f, "{}", cmd),
+            Append { ptr, num } => write!(f, "CMD_APPEND({:#x}, {})", ptr, num),
+            ApiLevel { level } => write!(f, "CMD_APILEVEL({})", level),
+            BgColor { rgb } => write!(f, "CMD_BGCOLOR({:#08x})", rgb),
+            Button {
+                rect,
+                font,
+                options,
+                message,
+            } => write!(
+                f,
+                "CMD_BUTTON({}, {}, {}, {}, {}, {}, {})",
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                font.to_raw(),
+                options.to_raw(),
+                message
+            ),
+            Clock {
+                pos,
+                radius,
+                options,
+                h,
+                m,
+                s,
+                ms,
+            } => write!(
+                f,
+                "CMD_CLOCK({}, {}, {}, {}, {}, {}, {}, {})",
+                pos.x, pos.y, radius, options, h, m, s, ms
+            ),
+            ColdStart => write!(f, "CMD_COLDSTART()"),
+            Dial {
+                pos,
+                radius,
+                options,
+                value,
+            } => write!(
+                f,
+                "CMD_DIAL({}, {}, {}, {}, {})",
+                pos.x, pos.y, radius, options, value
+            ),
+            DLStart => write!(f, "CMD_DLSTART()"),
+            FgColor { rgb } => write!(f, "CMD_FGCOLOR({:#08x})", rgb),
+            FlashAttach => write!(f, "CMD_FLASHATTACH()"),
+            FlashDetach => write!(f, "CMD_FLASHDETACH()"),
+            FlashErase => write!(f, "CMD_FLASHERASE()"),
+            FlashFast { result } => write!(f, "CMD_FLASHFAST({:#x})", result),
+            FlashProgram { dest, src, num } => {
+                write!(f, "CMD_FLASHPROGRAM({:#x}, {:#x}, {})", dest, src, num)
+            }
+            FlashRead { dest, src, num } => {
+                write!(f, "CMD_FLASHREAD({:#x}, {:#x}, {})", dest, src, num)
+            }
+            FlashSource { ptr } => write!(f, "CMD_FLASHSOURCE({:#x})", ptr),
+            FlashSpiDesel => write!(f, "CMD_FLASHSPIDESEL()"),
+            FlashSpiRx { ptr, num } => write!(f, "CMD_FLASHSPIRX({:#x}, {})", ptr, num),
+            FlashSpiTx { num, .. } => write!(f, "CMD_FLASHSPITX({})", num),
+            FlashUpdate { dest, src, num } => {
+                write!(f, "CMD_FLASHUPDATE({:#x}, {:#x}, {})", dest, src, num)
+            }
+            FlashWrite { ptr, num, .. } => write!(f, "CMD_FLASHWRITE({:#x}, {})", ptr, num),
+            Gauge {
+                pos,
+                radius,
+                options,
+                major,
+                minor,
+                value,
+                range,
+            } => write!(
+                f,
+                "CMD_GAUGE({}, {}, {}, {}, {}, {}, {}, {})",
+                pos.x, pos.y, radius, options, major, minor, value, range
+            ),
+            GetPtr { result } => write!(f, "CMD_GETPTR({:#x})", result),
+            Gradient {
+                start,
+                start_rgb,
+                end,
+                end_rgb,
+            } => write!(
+                f,
+                "CMD_GRADIENT({}, {}, {:#08x}, {}, {}, {:#08x})",
+                start.x, start.y, start_rgb, end.x, end.y, end_rgb
+            ),
+            Inflate { ptr, data } => write!(f, "CMD_INFLATE({:#x}) + {} words", ptr, data.len()),
+            Inflate2 { ptr, options, data } => write!(
+                f,
+                "CMD_INFLATE2({:#x}, {}) + {} words",
+                ptr,
+                options,
+                data.len()
+            ),
+            Interrupt { ms } => write!(f, "CMD_INTERRUPT({})", ms),
+            Keys {
+                rect,
+                font,
+                options,
+                message,
+            } => write!(
+                f,
+                "CMD_KEYS({}, {}, {}, {}, {}, {}, {})",
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                font.to_raw(),
+                options,
+                message
+            ),
+            LoadIdentity => write!(f, "CMD_LOADIDENTITY()"),
+            LoadImage { ptr, options, data } => write!(
+                f,
+                "CMD_LOADIMAGE({:#x}, {}) + {} words",
+                ptr,
+                options.to_raw(),
+                data.len()
+            ),
+            Logo => write!(f, "CMD_LOGO()"),
+            MemCpy { dest, src, num } => {
+                write!(f, "CMD_MEMCPY({:#x}, {:#x}, {})", dest, src, num)
+            }
+            MemCrc { ptr, num, result } => {
+                write!(f, "CMD_MEMCRC({:#x}, {}, {:#x})", ptr, num, result)
+            }
+            MemSet { ptr, value, num } => {
+                write!(f, "CMD_MEMSET({:#x}, {}, {})", ptr, value, num)
+            }
+            MemWrite { ptr, num, .. } => write!(f, "CMD_MEMWRITE({:#x}, {})", ptr, num),
+            MemZero { ptr, num } => write!(f, "CMD_MEMZERO({:#x}, {})", ptr, num),
+            Number {
+                pos,
+                font,
+                options,
+                value,
+            } => write!(
+                f,
+                "CMD_NUMBER({}, {}, {}, {}, {})",
+                pos.x,
+                pos.y,
+                font.to_raw(),
+                options,
+                value
+            ),
+            PlayVideo { options, data } => {
+                write!(f, "CMD_PLAYVIDEO({}) + {} words", options, data.len())
+            }
+            Progress {
+                rect,
+                options,
+                value,
+                range,
+            } => write!(
+                f,
+                "CMD_PROGRESS({}, {}, {}, {}, {}, {}, {})",
+                rect.x, rect.y, rect.w, rect.h, options, value, range
+            ),
+            RegRead { ptr, result } => write!(f, "CMD_REGREAD({:#x}, {:#x})", ptr, result),
+            Rotate { angle } => write!(f, "CMD_ROTATE({})", angle),
+            Scale { sx, sy } => write!(f, "CMD_SCALE({}, {})", sx, sy),
+            Scrollbar {
+                rect,
+                options,
+                value,
+                size,
+                range,
+            } => write!(
+                f,
+                "CMD_SCROLLBAR({}, {}, {}, {}, {}, {}, {}, {})",
+                rect.x, rect.y, rect.w, rect.h, options, value, size, range
+            ),
+            SetBitmap {
+                ptr,
+                format,
+                width,
+                height,
+            } => write!(
+                f,
+                "CMD_SETBITMAP({:#x}, {}, {}, {})",
+                ptr, format, width, height
+            ),
+            SetFont2 {
+                font,
+                ptr,
+                first_char,
+            } => write!(f, "CMD_SETFONT2({}, {:#x}, {})", font, ptr, first_char),
+            SetMatrix => write!(f, "CMD_SETMATRIX()"),
+            Slider {
+                rect,
+                options,
+                value,
+                range,
+            } => write!(
+                f,
+                "CMD_SLIDER({}, {}, {}, {}, {}, {}, {})",
+                rect.x, rect.y, rect.w, rect.h, options, value, range
+            ),
+            Spinner { pos, style, scale } => {
+                write!(f, "CMD_SPINNER({}, {}, {}, {})", pos.x, pos.y, style, scale)
+            }
+            Swap => write!(f, "CMD_SWAP()"),
+            SetRotate { rotation } => write!(f, "CMD_SETROTATE({})", rotation),
+            Testcard => write!(f, "CMD_TESTCARD()"),
+            Text {
+                pos,
+                font,
+                options,
+                message,
+            } => write!(
+                f,
+                "CMD_TEXT({}, {}, {}, {}, {})",
+                pos.x,
+                pos.y,
+                font.to_raw(),
+                options.to_raw(),
+                message
+            ),
+            Toggle {
+                pos,
+                width,
+                font,
+                options,
+                state,
+                message,
+            } => write!(
+                f,
+                "CMD_TOGGLE({}, {}, {}, {}, {}, {}, {})",
+                pos.x,
+                pos.y,
+                width,
+                font.to_raw(),
+                options,
+                state,
+                message
+            ),
+            Translate { tx, ty } => write!(f, "CMD_TRANSLATE({}, {})", tx, ty),
+            Wait { us } => write!(f, "CMD_WAIT({})", us),
+            WaitVideoScanout => write!(f, "CMD_WAIT_SCANOUT()"),
+            Other { name, args } => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:#x}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Unknown { opcode, rest } => {
+                write!(f, "UNKNOWN({:#010x}) + {} words", opcode, rest.len())
+            }
+            Truncated(words) => write!(f, "TRUNCATED({} words)", words.len()),
+        }
+    }
+}
+
+fn split_u16(v: u32) -> (u16, u16) {
+    (v as u16, (v >> 16) as u16)
+}
+
+fn split_i16(v: u32) -> (i16, i16) {
+    (v as u16 as i16, (v >> 16) as u16 as i16)
+}
+
+// Returns the number of words needed to hold the given number of bytes of
+// inline data, including the padding after it.
+fn padded_words(num: u32) -> usize {
+    ((num as usize) + 3) / 4
+}
+
+fn split_rect(pos: u32, size: u32) -> WidgetRect {
+    let (x, y) = split_i16(pos);
+    let (w, h) = split_i16(size);
+    WidgetRect::new(x, y, w, h)
+}
+
+// Returns the bytes of the null-terminated string packed into the given
+// words, excluding the terminator.
+fn string_bytes(words: &[u32]) -> impl Iterator<Item = u8> + '_ {
+    words
+        .iter()
+        .flat_map(|w| (0..4).map(move |i| (*w >> (i * 8)) as u8))
+        .take_while(|b| *b != 0)
+}
+
+// Counts the number of argument words that the coprocessor will expect to
+// follow the given format string.
+fn count_format_args<Iter: Iterator<Item = u8>>(mut fmt: Iter) -> usize {
+    let mut count = 0;
+    while let Some(b) = fmt.next() {
+        if b != b'%' {
+            continue;
+        }
+        loop {
+            match fmt.next() {
+                // "%%" is a literal percent sign, without an argument.
+                Some(b'%') => break,
+                // A width or precision of "*" takes its value from an
+                // additional argument.
+                Some(b'*') => count += 1,
+                Some(b'-') | Some(b'+') | Some(b' ') | Some(b'#') | Some(b'.') => {}
+                Some(b'0'..=b'9') | Some(b'l') | Some(b'h') => {}
+                Some(_) => {
+                    count += 1;
+                    break;
+                }
+                None => return count,
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::commands::options::Options;
+    use crate::commands::strfmt::{Argument, Message};
+    use crate::commands::CommandList;
+    use crate::display_list::options::GraphicsPrimitive;
+    use crate::display_list::Builder;
+    use crate::models::testing::Exhaustive;
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_decode_command_list() {
+        let mut buf = [0u32; 64];
+        let mut list = CommandList::<Exhaustive, _>::new(&mut buf[..]);
+        list.start_display_list().unwrap();
+        list.clear_all().unwrap();
+        list.draw_button(
+            (10, 20, 100, 12),
+            Message::new_literal(b"hello world!\0"),
+            options::FontRef::new_raw(31),
+            options::Button::new().style(options::WidgetStyle::Flat),
+        )
+        .unwrap();
+        list.draw_text(
+            (-5, 30),
+            Message::new(b"%d%% of %5.2x\0", &[Argument::Int(-1), Argument::UInt(8)]),
+            options::FontRef::new_raw(18),
+            options::Text::new(),
+        )
+        .unwrap();
+        list.begin(GraphicsPrimitive::Points).unwrap();
+        list.wait_microseconds(1000).unwrap();
+        list.set_rotation(crate::graphics::Rotation::InvertedPortrait)
+            .unwrap();
+        list.display().unwrap();
+        list.display_list_swap().unwrap();
+        let words: Vec<u32> = list.words().collect();
+
+        let mut cmds = decode_commands(&words);
+        assert_eq!(cmds.next(), Some(DecodedCommand::DLStart));
+        assert_eq!(
+            cmds.next(),
+            Some(DecodedCommand::DisplayList(DLCmd::CLEAR_ALL))
+        );
+
+        assert_eq!(cmds.offset(), 2);
+        let button = cmds.next().unwrap();
+        match button {
+            DecodedCommand::Button {
+                rect,
+                font,
+                options,
+                message,
+            } => {
+                assert_eq!(rect, WidgetRect::new(10, 20, 100, 12));
+                assert_eq!(font, options::FontRef::new_raw(31));
+                assert_eq!(
+                    options,
+                    options::Button::new().style(options::WidgetStyle::Flat)
+                );
+                assert!(message.text_eq(b"hello world!\0"));
+                assert_eq!(message.args(), None);
+            }
+            got => panic!("expected a button, but got {:?}", got),
+        }
+        assert_eq!(
+            button.to_string(),
+            "CMD_BUTTON(10, 20, 100, 12, 31, 256, \"hello world!\")"
+        );
+
+        let text = cmds.next().unwrap();
+        match text {
+            DecodedCommand::Text {
+                pos,
+                font,
+                options,
+                message,
+            } => {
+                assert_eq!(pos, WidgetPos::new(-5, 30));
+                assert_eq!(font, options::FontRef::new_raw(18));
+                assert_eq!(options, options::Text::new());
+                assert!(message.text_eq(b"%d%% of %5.2x"));
+                assert_eq!(message.args(), Some(&[0xffffffff, 8][..]));
+            }
+            got => panic!("expected text, but got {:?}", got),
+        }
+        assert_eq!(
+            text.to_string(),
+            "CMD_TEXT(-5, 30, 18, 0, \"%d%% of %5.2x\", 0xffffffff, 0x8)"
+        );
+
+        assert_eq!(
+            cmds.next(),
+            Some(DecodedCommand::DisplayList(DLCmd::begin(
+                GraphicsPrimitive::Points
+            )))
+        );
+        assert_eq!(cmds.next(), Some(DecodedCommand::Wait { us: 1000 }));
+        let rotate = cmds.next().unwrap();
+        assert_eq!(rotate, DecodedCommand::SetRotate { rotation: 3 });
+        assert_eq!(rotate.to_string(), "CMD_SETROTATE(3)");
+        assert_eq!(
+            cmds.next(),
+            Some(DecodedCommand::DisplayList(DLCmd::DISPLAY))
+        );
+        assert_eq!(cmds.next(), Some(DecodedCommand::Swap));
+        assert_eq!(cmds.next(), None);
+        assert_eq!(cmds.offset(), words.len());
+    }
+
+    #[test]
+    fn test_decode_memory_commands() {
+        let words = [
+            0xffffff1a, 0x00001000, 5, 0x6c6c6568, 0x0000006f, // CMD_MEMWRITE
+            0xffffff1d, 0x00002000, 0x00300000, 64, // CMD_MEMCPY
+            0xffffff19, 0x00302000, 0xf0f0f0f0, // CMD_REGREAD
+            0xffffff1b, 0x00003000, 0x000000aa, 16, // CMD_MEMSET
+            0xffffff22, 0x00000000, 0x01020304, 0x05060708, // CMD_INFLATE
+        ];
+        let got: Vec<_> = decode_commands(&words).collect();
+        let want = [
+            DecodedCommand::MemWrite {
+                ptr: 0x1000,
+                num: 5,
+                data: &[0x6c6c6568, 0x0000006f],
+            },
+            DecodedCommand::MemCpy {
+                dest: 0x2000,
+                src: 0x300000,
+                num: 64,
+            },
+            DecodedCommand::RegRead {
+                ptr: 0x302000,
+                result: 0xf0f0f0f0,
+            },
+            DecodedCommand::MemSet {
+                ptr: 0x3000,
+                value: 0xaa,
+                num: 16,
+            },
+            DecodedCommand::Inflate {
+                ptr: 0,
+                data: &[0x01020304, 0x05060708],
+            },
+        ];
+        assert_eq!(&got[..], &want[..]);
+        assert_eq!(got[2].to_string(), "CMD_REGREAD(0x302000, 0xf0f0f0f0)");
+        assert_eq!(got[3].to_string(), "CMD_MEMSET(0x3000, 170, 16)");
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        // A button whose message has no null terminator.
+        let words = [
+            0xffffff00, 0xffffff0d, 0x0014000a, 0x000c0064, 0x0100001f, 0x6c6c6568,
+        ];
+        let mut cmds = decode_commands(&words);
+        assert_eq!(cmds.next(), Some(DecodedCommand::DLStart));
+        assert_eq!(cmds.next(), Some(DecodedCommand::Truncated(&words[1..])));
+        assert_eq!(cmds.next(), None);
+
+        let words = [0xffffff02, 0xffffff99, 0xffffff65, 0x26000007];
+        let got: Vec<_> = decode_commands(&words).collect();
+        assert_eq!(
+            &got[..],
+            &[
+                DecodedCommand::Interrupt { ms: 0xffffff99 },
+                DecodedCommand::Wait { us: 0x26000007 },
+            ][..]
+        );
+
+        // The words after an unknown opcode aren't decoded, because we
+        // can't tell where its arguments end.
+        let words = [0xffffff00, 0xffffff99, 0xffffff65, 0x26000007];
+        let got: Vec<_> = decode_commands(&words).collect();
+        assert_eq!(
+            &got[..],
+            &[
+                DecodedCommand::DLStart,
+                DecodedCommand::Unknown {
+                    opcode: 0xffffff99,
+                    rest: &words[2..],
+                },
+            ][..]
+        );
+        assert_eq!(got[1].to_string(), "UNKNOWN(0xffffff99) + 2 words");
+    }
+
+    #[test]
+    fn test_decode_widgets() {
+        let words = [
+            0xffffff13, 0x00640050, 0x0000003c, 0x00020005, 0x0064001e, // CMD_GAUGE
+            0xffffff0e, 0x00140000, 0x001e00c8, 0x0000001c, 0x00636261, // CMD_KEYS
+            0xffffff0f, 0x00280014, 0x000800c8, 0x00320000, 100, // CMD_PROGRESS
+            0xffffff11, 0x00280014, 0x000800c8, 0x00320000, 0x0064000a, // CMD_SCROLLBAR
+            0xffffff12, 0x0000000a, 0x001b003c, 0xffff0000, 0x00ff6e6f, // CMD_TOGGLE
+            0xffffff2e, 0x00140014, 0x0100001c, 0xfffffffe, // CMD_NUMBER
+            0xffffff0b, 0x00000000, 0x00ff0000, 0x01e00320, 0x000000ff, // CMD_GRADIENT
+            0xffffff0a, 0x00102030, // CMD_FGCOLOR
+            0xffffff26, // CMD_LOADIDENTITY
+            0xffffff27, 0x00010000, 0xffff0000, // CMD_TRANSLATE
+            0xffffff2a, // CMD_SETMATRIX
+            0xffffff43, 0x00001000, 0x00400007, 32, // CMD_SETBITMAP
+            0xffffff1c, 0x00002000, 128, // CMD_MEMZERO
+            0xffffff23, 0, // CMD_GETPTR
+            0xffffff45, 0x00001000, 3, 0x00030201, // CMD_FLASHWRITE
+            0xffffff1f, 0x00003000, // CMD_SNAPSHOT
+            0xffffff24, 0x00004000, 0x40,       // CMD_LOADIMAGE from flash
+            0xffffff01, // CMD_SWAP
+        ];
+        let got: Vec<_> = decode_commands(&words).collect();
+        let got_str: Vec<_> = got.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            &got_str[..],
+            &[
+                "CMD_GAUGE(80, 100, 60, 0, 5, 2, 30, 100)",
+                "CMD_KEYS(0, 20, 200, 30, 28, 0, \"abc\")",
+                "CMD_PROGRESS(20, 40, 200, 8, 0, 50, 100)",
+                "CMD_SCROLLBAR(20, 40, 200, 8, 0, 50, 10, 100)",
+                "CMD_TOGGLE(10, 0, 60, 27, 0, 65535, \"on\\xff\")",
+                "CMD_NUMBER(20, 20, 28, 256, -2)",
+                "CMD_GRADIENT(0, 0, 0xff0000, 800, 480, 0x0000ff)",
+                "CMD_FGCOLOR(0x102030)",
+                "CMD_LOADIDENTITY()",
+                "CMD_TRANSLATE(65536, -65536)",
+                "CMD_SETMATRIX()",
+                "CMD_SETBITMAP(0x1000, 7, 64, 32)",
+                "CMD_MEMZERO(0x2000, 128)",
+                "CMD_GETPTR(0x0)",
+                "CMD_FLASHWRITE(0x1000, 3)",
+                "CMD_SNAPSHOT(0x3000)",
+                "CMD_LOADIMAGE(0x4000, 64) + 0 words",
+                "CMD_SWAP()",
+            ][..]
+        );
+        assert_eq!(
+            got[4],
+            DecodedCommand::Toggle {
+                pos: WidgetPos::new(10, 0),
+                width: 60,
+                font: options::FontRef::new_raw(27),
+                options: 0,
+                state: 0xffff,
+                message: DecodedMessage {
+                    text: &words[24..25],
+                    args: None,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_format_args() {
+        let count = |s: &[u8]| count_format_args(s.iter().copied());
+        assert_eq!(count(b"hello"), 0);
+        assert_eq!(count(b"100%%"), 0);
+        assert_eq!(count(b"%d"), 1);
+        assert_eq!(count(b"%s and %c"), 2);
+        assert_eq!(count(b"%-08.3x"), 1);
+        assert_eq!(count(b"%*d"), 2);
+        assert_eq!(count(b"%ld%"), 1);
+    }
+}
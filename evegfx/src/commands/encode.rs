@@ -0,0 +1,298 @@
+//! Encoding of coprocessor commands as sequences of command words.
+//!
+//! [`Coprocessor`](super::Coprocessor), [`CommandList`](super::CommandList)
+//! and [`AsyncCoprocessor`](super::AsyncCoprocessor) all build their
+//! commands using these functions, so that they produce exactly the same
+//! word stream for the same method call. Each of them is responsible only
+//! for getting the words to the coprocessor, or into storage.
+
+use super::command_word::{command_words_for_bytes_iter, CommandWord};
+use super::options;
+use super::strfmt;
+use crate::graphics::{Rotation, WidgetPos, WidgetRect};
+use crate::memory::{MainMem, MemoryRegion, Slice};
+use crate::models::Model;
+use crate::registers::Register;
+
+// The placeholder we send for words that the coprocessor overwrites with
+// a result.
+const OUTPUT_PLACEHOLDER: u32 = 0xf0f0f0f0;
+
+pub(crate) fn start_display_list() -> [u32; 1] {
+    [0xFFFFFF00]
+}
+
+pub(crate) fn display_list_swap() -> [u32; 1] {
+    [0xFFFFFF01]
+}
+
+pub(crate) fn trigger_cmdflag_interrupt(delay: core::time::Duration) -> [u32; 2] {
+    // The delay saturates at the largest number of milliseconds that fits
+    // in the command's argument.
+    let delay = delay.as_millis();
+    let delay = core::convert::TryFrom::try_from(delay).unwrap_or(u32::MAX);
+    [0xFFFFFF02, delay]
+}
+
+pub(crate) fn cold_start() -> [u32; 1] {
+    [0xFFFFFF32]
+}
+
+pub(crate) fn append_display_list_from_main_mem<R: MainMem>(slice: Slice<R>) -> [u32; 3] {
+    [0xFFFFFF1E, slice.start().to_raw(), slice.len()]
+}
+
+// CMD_MEMCPY, which we use to copy a captured display list fragment from
+// display list memory into main memory.
+pub(crate) fn copy_display_list_fragment<M: Model>(
+    dest: Slice<M::MainMem>,
+    start: u32,
+    len: u32,
+) -> [u32; 4] {
+    let src = M::DisplayListMem::ptr(start);
+    [0xFFFFFF1D, dest.start().to_raw(), src.to_raw(), len]
+}
+
+pub(crate) fn write_register<M: Model>(reg: Register, v: u32) -> [u32; 4] {
+    [0xFFFFFF1A, reg.ptr::<M>().to_raw(), 4, v]
+}
+
+// Sets every output level in REG_GPIOX at once, since the coprocessor has
+// no way to change just one bit of a register.
+pub(crate) fn write_gpio_levels<M: Model>(levels: u16) -> [u32; 4] {
+    write_register::<M>(Register::GPIO_X, levels as u32)
+}
+
+// The fixed part of CMD_MEMWRITE, which the caller must follow with the
+// data itself.
+pub(crate) fn write_memory(to_raw: u32, len: u32) -> [u32; 3] {
+    [0xFFFFFF1A, to_raw, len]
+}
+
+// The fixed part of CMD_INFLATE, which the caller must follow with the
+// compressed data.
+pub(crate) fn write_memory_inflate(to_raw: u32) -> [u32; 2] {
+    [0xFFFFFF22, to_raw]
+}
+
+// The fixed part of CMD_LOADIMAGE, which the caller must follow with the
+// image data.
+pub(crate) fn write_memory_image(to_raw: u32, opts: options::LoadImage) -> [u32; 3] {
+    [0xFFFFFF24, to_raw, opts.to_raw()]
+}
+
+pub(crate) fn show_testcard() -> [u32; 1] {
+    [0xFFFFFF61]
+}
+
+pub(crate) fn show_manufacturer_logo() -> [u32; 1] {
+    [0xFFFFFF31]
+}
+
+pub(crate) fn start_spinner() -> [u32; 5] {
+    // TODO: Make the spinner customizable.
+    [0xFFFFFF16, 1000, 1000, 0, 0]
+}
+
+// The fixed part of CMD_BUTTON, which the caller must follow with the
+// words from `message_format` and `message_arguments`.
+pub(crate) fn draw_button<R: MainMem>(
+    rect: WidgetRect,
+    msg: &strfmt::Message<'_, '_, R>,
+    font: options::FontRef,
+    options: options::Button,
+) -> [u32; 4] {
+    let font_raw = font.to_raw() as u16;
+    let opts_raw = maybe_opt_format(options.to_raw(), msg) as u16;
+    [
+        0xFFFFFF0D,
+        CommandWord::from((rect.x, rect.y)).to_raw(),
+        CommandWord::from((rect.w, rect.h)).to_raw(),
+        CommandWord::from((font_raw, opts_raw)).to_raw(),
+    ]
+}
+
+// The fixed part of CMD_TEXT, which the caller must follow with the
+// words from `message_format` and `message_arguments`.
+pub(crate) fn draw_text<R: MainMem>(
+    pos: WidgetPos,
+    msg: &strfmt::Message<'_, '_, R>,
+    font: options::FontRef,
+    options: options::Text,
+) -> [u32; 3] {
+    let font_raw = font.to_raw() as u16;
+    let opts_raw = maybe_opt_format(options.to_raw(), msg) as u16;
+    [
+        0xFFFFFF0C,
+        CommandWord::from((pos.x, pos.y)).to_raw(),
+        CommandWord::from((font_raw, opts_raw)).to_raw(),
+    ]
+}
+
+pub(crate) fn wait_microseconds(delay: u32) -> [u32; 2] {
+    [0xFFFFFF65, delay]
+}
+
+pub(crate) fn wait_video_scanout() -> [u32; 1] {
+    [0xFFFFFF42]
+}
+
+pub(crate) fn set_rotation(rotation: Rotation) -> [u32; 2] {
+    [0xFFFFFF36, rotation.to_raw() as u32]
+}
+
+// CMD_REGREAD, whose final word the coprocessor overwrites with the
+// register value.
+pub(crate) fn read_register<M: Model>(reg: Register) -> [u32; 3] {
+    [0xFFFFFF19, M::reg_ptr(reg).to_raw(), OUTPUT_PLACEHOLDER]
+}
+
+// CMD_MEMCRC, whose final word the coprocessor overwrites with the
+// checksum.
+pub(crate) fn memory_crc<R: MemoryRegion>(region: Slice<R>) -> [u32; 4] {
+    [
+        0xFFFFFF18,
+        region.start().to_raw(),
+        region.len(),
+        OUTPUT_PLACEHOLDER,
+    ]
+}
+
+pub(crate) fn use_api_level(level: u32) -> [u32; 2] {
+    [0xFFFFFF63, level]
+}
+
+/// The values of `REG_PWM_DUTY` that fade the backlight from one brightness
+/// to another, as produced by [`fade_backlight`].
+pub(crate) struct FadeBacklight {
+    /// The duty cycle to set immediately, before the first step.
+    pub(crate) initial: u32,
+
+    /// The delay before each step, in microseconds.
+    pub(crate) step_delay: u32,
+
+    next: i16,
+    to: i16,
+}
+
+impl Iterator for FadeBacklight {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.next == self.to {
+            return None;
+        }
+        self.next += (self.to - self.next).signum();
+        Some(self.next as u32)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remain = (self.to - self.next).unsigned_abs() as usize;
+        (remain, Some(remain))
+    }
+}
+
+impl ExactSizeIterator for FadeBacklight {}
+
+// Divides a backlight fade into one step per change of the duty cycle,
+// spread evenly over the given duration.
+pub(crate) fn fade_backlight(
+    from: crate::config::Brightness,
+    to: crate::config::Brightness,
+    duration: core::time::Duration,
+) -> FadeBacklight {
+    let from = from.reg_pwm_duty_value() as i16;
+    let to = to.reg_pwm_duty_value() as i16;
+    let steps = (to - from).unsigned_abs() as u128;
+    let step_delay = duration.as_micros().checked_div(steps).unwrap_or(0);
+    FadeBacklight {
+        initial: from as u32,
+        step_delay: core::convert::TryFrom::try_from(step_delay).unwrap_or(u32::MAX),
+        next: from,
+        to: to,
+    }
+}
+
+// Returns the words encoding the format string of the given message,
+// including its null terminator and padding.
+pub(crate) fn message_format<'a, R: MainMem>(
+    msg: &strfmt::Message<'a, '_, R>,
+) -> impl ExactSizeIterator<Item = u32> + 'a {
+    command_words_for_bytes_iter(msg.fmt.iter()).map(|word| word.to_raw())
+}
+
+// Returns the words encoding the arguments of the given message, if any.
+pub(crate) fn message_arguments<'b, R: MainMem>(
+    msg: &strfmt::Message<'_, 'b, R>,
+) -> impl ExactSizeIterator<Item = u32> + 'b {
+    use strfmt::Argument::*;
+    let args: &'b [strfmt::Argument<R>] = match msg.args {
+        Some(args) => args,
+        None => &[],
+    };
+    args.iter().map(|arg| match *arg {
+        Int(v) => v as u32,
+        UInt(v) => v,
+        Char(v) => v as u32,
+        String(ptr) => ptr.to_raw(),
+    })
+}
+
+fn maybe_opt_format<R: MainMem>(given: u32, msg: &strfmt::Message<'_, '_, R>) -> u32 {
+    if msg.needs_format() {
+        given | options::OPT_FORMAT
+    } else {
+        given
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::config::Brightness;
+    use crate::models::fake::MainMem as FakeMainMem;
+    use core::time::Duration;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_fade_backlight() {
+        let fade = fade_backlight(
+            Brightness::Duty(10),
+            Brightness::Duty(7),
+            Duration::from_micros(300),
+        );
+        assert_eq!(fade.initial, 10);
+        assert_eq!(fade.step_delay, 100);
+        assert_eq!(fade.len(), 3);
+        assert_eq!(fade.collect::<Vec<_>>(), [9, 8, 7]);
+
+        let fade = fade_backlight(
+            Brightness::Duty(5),
+            Brightness::Duty(5),
+            Duration::from_micros(300),
+        );
+        assert_eq!(fade.initial, 5);
+        assert_eq!(fade.collect::<Vec<_>>(), []);
+    }
+
+    #[test]
+    fn test_message() {
+        use strfmt::Argument::*;
+        let args: [strfmt::Argument<FakeMainMem>; 3] = [Int(-1), UInt(5), Char('A')];
+        let msg = strfmt::Message::new(b"%d %u %c\0", &args);
+        assert_eq!(
+            message_format(&msg).collect::<Vec<_>>(),
+            [0x25206425, 0x63252075, 0x00000000],
+        );
+        assert_eq!(
+            message_arguments(&msg).collect::<Vec<_>>(),
+            [0xffffffff, 5, 0x41],
+        );
+
+        let msg: strfmt::Message<FakeMainMem> = strfmt::Message::new_literal(b"hi\0");
+        assert_eq!(message_format(&msg).collect::<Vec<_>>(), [0x00006968]);
+        assert_eq!(message_arguments(&msg).len(), 0);
+    }
+}
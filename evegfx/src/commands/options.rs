@@ -35,7 +35,7 @@ impl VideoPlayback {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LoadImage(u32);
 
 impl Options for LoadImage {
@@ -51,6 +51,10 @@ impl core::default::Default for LoadImage {
 }
 
 impl LoadImage {
+    pub(crate) const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
     pub const fn jpeg_color_mode(self, mode: JPEGColorMode) -> Self {
         Self((self.0 & (!0b1)) | mode as u32)
     }
@@ -68,7 +72,7 @@ impl LoadImage {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Button(u32);
 
 impl Options for Button {
@@ -78,6 +82,10 @@ impl Options for Button {
 }
 
 impl Button {
+    pub(crate) const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
     pub const fn style(self, style: WidgetStyle) -> Self {
         const MASK: u32 = !256;
         Self((self.0 & MASK) | style as u32)
@@ -88,7 +96,7 @@ impl Button {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Text(u32);
 
 impl Options for Text {
@@ -98,6 +106,10 @@ impl Options for Text {
 }
 
 impl Text {
+    pub(crate) const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
     pub fn to_raw(self) -> u32 {
         self.0
     }
@@ -167,3 +179,6 @@ const OPT_NODL: u32 = 2;
 const OPT_NOTEAR: u32 = 4;
 const OPT_FULLSCREEN: u32 = 8;
 const OPT_SOUND: u32 = 32;
+pub(crate) const OPT_MEDIAFIFO: u32 = 16;
+pub(crate) const OPT_FLASH: u32 = 64;
+pub(crate) const OPT_FORMAT: u32 = 4096;
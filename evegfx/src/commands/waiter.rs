@@ -7,6 +7,14 @@
 //!
 //! [`PollingWaiter`](PollingWaiter) is a simple built-in implementation of
 //! `Waiter` which busy-polls the coprocessor registers.
+//! [`TimeoutWaiter`](TimeoutWaiter) is similar, but gives up after a given
+//! amount of time as measured by a caller-supplied [`Clock`](Clock), which
+//! avoids hanging forever if the EVE chip stops responding.
+//!
+//! [`AsyncWaiter`](AsyncWaiter) is the equivalent of `Waiter` for use with
+//! [`AsyncCoprocessor`](crate::commands::AsyncCoprocessor), and
+//! [`YieldingWaiter`](YieldingWaiter) is its built-in implementation, which
+//! yields to the executor between each poll so that other tasks can run.
 //!
 //! If you are working with this library on a platform where you are able to
 //! listen for and respond to interrupt signals from the EVE chip then you
@@ -14,7 +22,7 @@
 //! put the host processor to sleep while waiting for a signal that there is
 //! either more buffer space or a coprocessor fault.
 
-use crate::interface::Interface;
+use crate::interface::{AsyncInterface, Interface};
 use crate::low_level::LowLevel;
 use crate::models::Model;
 use crate::registers::Register;
@@ -43,6 +51,9 @@ pub trait Waiter<M: Model, I: Interface> {
 pub enum WaiterError<E: Sized> {
     Comm(E),
     Fault,
+
+    /// The waiter gave up before enough space became available.
+    Timeout,
 }
 
 fn waiter_comm_result<R, E: Sized>(
@@ -79,7 +90,133 @@ impl<M: Model, I: Interface> Waiter<M, I> for PollingWaiter<M, I> {
         need: u16,
     ) -> core::result::Result<u16, WaiterError<Self::Error>> {
         loop {
-            let known_space = waiter_comm_result(ell.rd16(ell.reg_ptr(Register::CMDB_SPACE)))?;
+            if let Some(known_space) = poll_space(ell, need)? {
+                return Ok(known_space);
+            }
+        }
+    }
+}
+
+/// A source of the current time, for use with [`TimeoutWaiter`].
+///
+/// The unit of time is up to the implementation, such as milliseconds or
+/// ticks of a hardware timer, but it must match the unit of the timeout
+/// passed to [`TimeoutWaiter::new`]. The count may wrap around, as long as
+/// a single wait is shorter than a full cycle.
+///
+/// This is implemented for any `FnMut() -> u64`, so a closure that reads
+/// a platform-specific timer is often sufficient.
+pub trait Clock {
+    fn now(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> Clock for F {
+    fn now(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// A [`Waiter`](Waiter) which polls the coprocessor registers in a busy loop
+/// like [`PollingWaiter`](PollingWaiter), but which returns
+/// [`WaiterError::Timeout`] if there still isn't enough space once the
+/// given timeout has elapsed.
+///
+/// A coprocessor object using this waiter returns
+/// [`Error::Timeout`](crate::commands::Error::Timeout) from any method that
+/// needed to wait, after which it's safe to try again.
+pub struct TimeoutWaiter<M: Model, I: Interface, C: Clock> {
+    clock: C,
+    timeout: u64,
+    _ei: core::marker::PhantomData<I>,
+    _m: core::marker::PhantomData<M>,
+}
+
+impl<M: Model, I: Interface, C: Clock> TimeoutWaiter<M, I, C> {
+    /// Creates a waiter which uses the given clock to give up after
+    /// `timeout` units of time.
+    pub fn new(clock: C, timeout: u64) -> Self {
+        Self {
+            clock: clock,
+            timeout: timeout,
+            _ei: core::marker::PhantomData,
+            _m: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<M: Model, I: Interface, C: Clock> Waiter<M, I> for TimeoutWaiter<M, I, C> {
+    type Error = crate::error::Error<I>;
+
+    fn wait_for_space(
+        &mut self,
+        ell: &mut LowLevel<M, I>,
+        need: u16,
+    ) -> core::result::Result<u16, WaiterError<Self::Error>> {
+        let start = self.clock.now();
+        loop {
+            if let Some(known_space) = poll_space(ell, need)? {
+                return Ok(known_space);
+            }
+            if self.clock.now().wrapping_sub(start) >= self.timeout {
+                return Err(WaiterError::Timeout);
+            }
+        }
+    }
+}
+
+/// The equivalent of [`Waiter`](Waiter) for use with
+/// [`AsyncCoprocessor`](crate::commands::AsyncCoprocessor).
+#[allow(async_fn_in_trait)]
+pub trait AsyncWaiter<M: Model, I: AsyncInterface> {
+    type Error;
+
+    async fn wait_for_space(
+        &mut self,
+        ei: &mut I,
+        need: u16,
+    ) -> core::result::Result<u16, WaiterError<Self::Error>>;
+}
+
+/// The default [`AsyncWaiter`](AsyncWaiter) implementation, which polls the
+/// coprocessor registers until there's enough available space, yielding to
+/// the executor between each poll.
+///
+/// Yielding allows other tasks to run while the coprocessor is busy, but
+/// this waiter still keeps the executor awake. An implementation that waits
+/// for the EVE interrupt signal would allow the system to sleep instead.
+pub struct YieldingWaiter<M: Model, I: AsyncInterface> {
+    _ei: core::marker::PhantomData<I>,
+    _m: core::marker::PhantomData<M>,
+}
+
+impl<M: Model, I: AsyncInterface> YieldingWaiter<M, I> {
+    pub fn new() -> Self {
+        Self {
+            _ei: core::marker::PhantomData,
+            _m: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<M: Model, I: AsyncInterface> Default for YieldingWaiter<M, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Model, I: AsyncInterface> AsyncWaiter<M, I> for YieldingWaiter<M, I> {
+    type Error = I::Error;
+
+    async fn wait_for_space(
+        &mut self,
+        ei: &mut I,
+        need: u16,
+    ) -> core::result::Result<u16, WaiterError<Self::Error>> {
+        let ptr = M::reg_ptr(Register::CMDB_SPACE).to_raw();
+        loop {
+            let mut raw = [0u8; 2];
+            waiter_comm_result(ei.read(ptr, &mut raw).await)?;
+            let known_space = u16::from_le_bytes(raw);
             if (known_space % 4) != 0 {
                 // An unaligned amount of space indicates a coprocessor fault.
                 return Err(WaiterError::Fault);
@@ -87,6 +224,95 @@ impl<M: Model, I: Interface> Waiter<M, I> for PollingWaiter<M, I> {
             if known_space >= need {
                 return Ok(known_space);
             }
+            YieldNow(false).await;
         }
     }
 }
+
+// A future which returns `Pending` once, after asking to be polled again, so
+// that the executor can run other tasks in the meantime.
+struct YieldNow(bool);
+
+impl core::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<()> {
+        if self.0 {
+            return core::task::Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        core::task::Poll::Pending
+    }
+}
+
+// Reads the current amount of space in the ring buffer, returning it only
+// if it's at least `need` bytes.
+fn poll_space<M: Model, I: Interface>(
+    ell: &mut LowLevel<M, I>,
+    need: u16,
+) -> core::result::Result<Option<u16>, WaiterError<crate::error::Error<I>>> {
+    let known_space = waiter_comm_result(ell.rd16(ell.reg_ptr(Register::CMDB_SPACE)))?;
+    if (known_space % 4) != 0 {
+        // An unaligned amount of space indicates a coprocessor fault.
+        return Err(WaiterError::Fault);
+    }
+    if known_space >= need {
+        return Ok(Some(known_space));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Error;
+    use crate::interface::fake::Interface as FakeInterface;
+    use crate::models::fake::Model as FakeModel;
+
+    #[test]
+    fn test_timeout_waiter() {
+        let mut mem = [0u8; 1024];
+        let mut dl = [0u8; 8 * 1024];
+        let mut cmd = [0u8; 4 * 1024];
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel)
+            .with_main_ram(&mut mem[..])
+            .with_display_list_ram(&mut dl[..])
+            .with_cmd_ram(&mut cmd[..])
+            .with_register_file(&mut regs[..])
+            .with_coprocessor();
+
+        // Our fake clock advances by one unit each time it's read.
+        let mut ticks = 0;
+        let clock = move || {
+            ticks += 1;
+            ticks
+        };
+        let eve = crate::EVE::new(FakeModel, ei);
+        let mut cp = eve.coprocessor(TimeoutWaiter::new(clock, 10)).unwrap();
+
+        // The coprocessor seems busy for longer than the timeout.
+        cp.with_interface(|ei| {
+            ei.delay_coprocessor_space(100);
+            Ok(())
+        })
+        .unwrap();
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(matches!(err, Error::Timeout), "wrong error {:?}", err);
+        let err = cp.block_read_register(Register::MACRO_0).unwrap_err();
+        assert!(matches!(err, Error::Timeout), "wrong error {:?}", err);
+
+        // Once the coprocessor catches up, waiting succeeds.
+        cp.with_interface(|ei| {
+            ei.clear_injected_faults();
+            Ok(())
+        })
+        .unwrap();
+        cp.block_until_idle().unwrap();
+        assert_eq!(cp.block_read_register(Register::MACRO_0).unwrap(), 0);
+    }
+}
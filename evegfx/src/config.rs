@@ -8,15 +8,18 @@ use crate::interface::Interface;
 use crate::models::Model;
 use crate::EVE;
 
+pub mod presets;
+
 /// Selects whether the EVE chip should use its internal oscillator or if
 /// it should expect external clock signals.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ClockSource {
     Internal,
     External,
 }
 
 /// Represents the timing parameters for video output.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct VideoTimings {
     pub sysclk_freq: ClockFrequency,
     pub pclk_div: u8,
@@ -30,7 +33,7 @@ pub struct VideoTimings {
 ///
 /// For horizontal parameters, the values are in pixel clocks. For vertical
 /// parameters, the values are in lines.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct VideoTimingDimension {
     pub total: u16,
     pub visible: u16,
@@ -54,6 +57,202 @@ impl VideoTimings {
     };
 }
 
+impl VideoTimings {
+    /// Returns the refresh rate these timings produce, in millihertz.
+    ///
+    /// Returns zero if the timings describe an empty raster or have a zero
+    /// pixel clock divisor, neither of which produce any video signal.
+    pub fn refresh_millihz(&self) -> u32 {
+        refresh_millihz(
+            self.sysclk_freq,
+            self.pclk_div,
+            self.horiz.total as u32 * self.vert.total as u32,
+        )
+    }
+}
+
+impl VideoTimings {
+    /// Checks the timings for problems that would prevent the EVE chip from
+    /// producing a valid video signal, returning all of the problems found.
+    ///
+    /// [`EVE::start_video_checked`](crate::EVE::start_video_checked) uses
+    /// this to refuse invalid timings before sending them to the chip.
+    pub fn validate(&self) -> Result<(), TimingProblems> {
+        let mut problems = TimingProblems::new();
+        self.horiz.validate(Axis::Horizontal, &mut problems);
+        self.vert.validate(Axis::Vertical, &mut problems);
+        if self.pclk_div == 0 {
+            problems.push(TimingProblem::PixelClockDivisorZero);
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+/// A problem with a [`VideoTimings`](VideoTimings) value, as reported by
+/// [`VideoTimings::validate`](VideoTimings::validate).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum TimingProblem {
+    /// The value of the given field doesn't fit in the chip's 12-bit timing
+    /// registers.
+    OutOfRange {
+        axis: Axis,
+        field: TimingField,
+        value: u16,
+    },
+
+    /// The sync period starts after it ends.
+    ///
+    /// The EVE chip interprets this as a request for inverted sync
+    /// polarity, which some displays do expect. For example, the
+    /// [`GAMEDUINO_HDMI_720P`](presets::GAMEDUINO_HDMI_720P) preset relies
+    /// on that, and so must be used with the unchecked
+    /// [`EVE::start_video`](crate::EVE::start_video).
+    SyncStartAfterEnd { axis: Axis },
+
+    /// The visible area and the offset before it add up to more than the
+    /// total.
+    VisibleExceedsTotal { axis: Axis },
+
+    /// `pclk_div` is zero, which disables the pixel clock.
+    PixelClockDivisorZero,
+}
+
+/// Identifies a dimension of the video raster, in a
+/// [`TimingProblem`](TimingProblem).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// Identifies a field of [`VideoTimingDimension`](VideoTimingDimension), in
+/// a [`TimingProblem`](TimingProblem).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimingField {
+    Total,
+    Visible,
+    Offset,
+    SyncStart,
+    SyncEnd,
+}
+
+/// The list of problems returned by
+/// [`VideoTimings::validate`](VideoTimings::validate).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimingProblems {
+    problems: [TimingProblem; TimingProblems::CAPACITY],
+    len: u8,
+}
+
+impl TimingProblems {
+    // Enough for every problem that validate can report at once: seven
+    // for each axis, and one for the pixel clock.
+    const CAPACITY: usize = 15;
+
+    fn new() -> Self {
+        Self {
+            problems: [TimingProblem::PixelClockDivisorZero; Self::CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, problem: TimingProblem) {
+        self.problems[self.len as usize] = problem;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[TimingProblem] {
+        &self.problems[..self.len as usize]
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, TimingProblem> {
+        self.as_slice().iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The result of [`choose_pixel_clock`](choose_pixel_clock): a system clock
+/// frequency and pixel clock divisor, along with the refresh rate they
+/// produce.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PixelClock {
+    pub sysclk_freq: ClockFrequency,
+    pub pclk_div: u8,
+
+    /// The refresh rate that results from this pixel clock, in millihertz.
+    pub refresh_millihz: u32,
+
+    /// The difference between the resulting refresh rate and the target
+    /// refresh rate, in millihertz. Positive if the result is faster than
+    /// the target.
+    pub error_millihz: i32,
+}
+
+/// Chooses the combination of system clock frequency and pixel clock divisor
+/// which gives a refresh rate closest to the given target, in millihertz,
+/// for a raster with the given horizontal and vertical timings.
+///
+/// When several combinations are equally close, this prefers the lowest
+/// system clock frequency in order to reduce power usage. Returns `None` if
+/// the timings describe an empty raster.
+///
+/// The result doesn't consider any limits on pixel clock frequency imposed
+/// by the display itself, or whether the selected system clock frequency
+/// is available on a particular model.
+pub fn choose_pixel_clock(
+    target_millihz: u32,
+    horiz: &VideoTimingDimension,
+    vert: &VideoTimingDimension,
+) -> Option<PixelClock> {
+    use ClockFrequency::*;
+
+    let frame_cycles = horiz.total as u32 * vert.total as u32;
+    if frame_cycles == 0 {
+        return None;
+    }
+
+    let mut best: Option<PixelClock> = None;
+    for sysclk_freq in [F24MHz, F36MHz, F48MHz, F60MHz, F72MHz].iter().copied() {
+        for pclk_div in 1..=255 {
+            let refresh = refresh_millihz(sysclk_freq, pclk_div, frame_cycles);
+            let error = refresh as i64 - target_millihz as i64;
+            let better = match best {
+                Some(prev) => error.abs() < (prev.error_millihz as i64).abs(),
+                None => true,
+            };
+            if better {
+                best = Some(PixelClock {
+                    sysclk_freq: sysclk_freq,
+                    pclk_div: pclk_div,
+                    refresh_millihz: refresh,
+                    error_millihz: error as i32,
+                });
+            }
+        }
+    }
+    best
+}
+
+fn refresh_millihz(sysclk_freq: ClockFrequency, pclk_div: u8, frame_cycles: u32) -> u32 {
+    let divisor = pclk_div as u64 * frame_cycles as u64;
+    if divisor == 0 {
+        return 0;
+    }
+    (sysclk_freq.reg_frequency_value() as u64 * 1000 / divisor) as u32
+}
+
 impl VideoTimingDimension {
     /// Calculates a `VideoTimingDimension` from the sizes of the
     /// individual periods in the cycle.
@@ -73,17 +272,49 @@ impl VideoTimingDimension {
     }
 }
 
+impl VideoTimingDimension {
+    fn validate(&self, axis: Axis, problems: &mut TimingProblems) {
+        let fields = [
+            (TimingField::Total, self.total),
+            (TimingField::Visible, self.visible),
+            (TimingField::Offset, self.offset),
+            (TimingField::SyncStart, self.sync_start),
+            (TimingField::SyncEnd, self.sync_end),
+        ];
+        for (field, value) in fields.iter().copied() {
+            if !dimension_is_valid(value) {
+                problems.push(TimingProblem::OutOfRange {
+                    axis: axis,
+                    field: field,
+                    value: value,
+                });
+            }
+        }
+        if self.sync_start > self.sync_end {
+            problems.push(TimingProblem::SyncStartAfterEnd { axis: axis });
+        }
+        if self.visible as u32 + self.offset as u32 > self.total as u32 {
+            problems.push(TimingProblem::VisibleExceedsTotal { axis: axis });
+        }
+    }
+}
+
 /// Represents the electrical characteristics of the EVE RGB interface.
 ///
 /// This behaves as a "builder" type, with methods that modify its parameters.
 /// The default value for each parameter matches the reset values of the EVE
 /// chip itself.
-#[derive(Debug, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct RGBElectricalMode {
     pclk_spread: bool,
     channel_bits: (u8, u8, u8),
     dither: bool,
-    // TODO: REG_SWIZZLE
+    swizzle: Swizzle,
+    pin_drive: [Option<DriveStrength>; PinGroup::COUNT],
+    pin_pd_state: [Option<PinPowerDownState>; PinGroup::COUNT],
+    pclk_2x: Option<bool>,
+    pclk_freq: Option<u16>,
+    verify: bool,
 }
 
 impl RGBElectricalMode {
@@ -91,11 +322,16 @@ impl RGBElectricalMode {
         core::default::Default::default()
     }
 
+    /// Selects whether the RGB outputs should change at staggered times
+    /// around the pixel clock edge, to reduce electromagnetic emissions.
+    /// This sets `REG_CSPREAD`.
     pub fn pclk_spread<'a>(&'a mut self, v: bool) -> &'a mut Self {
         self.pclk_spread = v;
         self
     }
 
+    /// Sets the number of bits used for each of the red, green and blue
+    /// channels, between 1 and 8. The chip treats 8 and 0 as equivalent.
     pub fn channel_bits<'a>(&'a mut self, r: u8, g: u8, b: u8) -> &'a mut Self {
         self.channel_bits = (r, g, b);
         self
@@ -105,8 +341,229 @@ impl RGBElectricalMode {
         self.dither = v;
         self
     }
+
+    /// Selects the order of the color channels on the RGB output pins, as
+    /// set in `REG_SWIZZLE`.
+    pub fn swizzle<'a>(&'a mut self, v: Swizzle) -> &'a mut Self {
+        self.swizzle = v;
+        self
+    }
+
+    /// Sets the drive strength for a group of output pins, using the
+    /// `PINDRIVE` host command. Pin groups that are never given a drive
+    /// strength keep whatever setting they already had.
+    pub fn drive_strength<'a>(&'a mut self, group: PinGroup, v: DriveStrength) -> &'a mut Self {
+        self.pin_drive[group.index()] = Some(v);
+        self
+    }
+
+    /// Sets what a group of pins should do while the chip is powered down,
+    /// using the `PIN_PD_STATE` host command. Pin groups that are never
+    /// given a state keep whatever setting they already had.
+    pub fn power_down_state<'a>(
+        &'a mut self,
+        group: PinGroup,
+        v: PinPowerDownState,
+    ) -> &'a mut Self {
+        self.pin_pd_state[group.index()] = Some(v);
+        self
+    }
+
+    /// Selects whether the graphics engine should output two pixels per
+    /// pixel clock cycle, as set in `REG_PCLK_2X`.
+    ///
+    /// This is only available on models that have the `REG_PCLK_FREQ`
+    /// register. `configure_video_pins` will return
+    /// [`Error::Unsupported`](Error::Unsupported) for other models.
+    pub fn pclk_2x<'a>(&'a mut self, v: bool) -> &'a mut Self {
+        self.pclk_2x = Some(v);
+        self
+    }
+
+    /// Sets a raw value for `REG_PCLK_FREQ`, which configures a separate
+    /// PLL for the pixel clock rather than deriving it from the system clock.
+    /// Refer to the datasheet for the encoding of this register.
+    ///
+    /// This is only available on models that have the `REG_PCLK_FREQ`
+    /// register. `configure_video_pins` will return
+    /// [`Error::Unsupported`](Error::Unsupported) for other models.
+    pub fn pclk_freq<'a>(&'a mut self, v: u16) -> &'a mut Self {
+        self.pclk_freq = Some(v);
+        self
+    }
+
+    /// Selects whether `configure_video_pins` should read back each of the
+    /// registers it writes and return
+    /// [`Error::ReadbackMismatch`](Error::ReadbackMismatch) if any of them
+    /// don't match what was written.
+    ///
+    /// This can help to detect a faulty connection to the chip during
+    /// bring-up of new hardware. The host command settings can't be read
+    /// back, and so aren't verified.
+    pub fn verify<'a>(&'a mut self, v: bool) -> &'a mut Self {
+        self.verify = v;
+        self
+    }
+
+    const fn reg_outbits_value(&self) -> u16 {
+        let (r, g, b) = self.channel_bits;
+        ((r & 0b111) as u16) << 6 | ((g & 0b111) as u16) << 3 | (b & 0b111) as u16
+    }
+}
+
+/// Selects the order of the color channels on the RGB output pins.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Swizzle {
+    /// Red, green and blue on their corresponding pins. This is the default.
+    #[default]
+    Rgb,
+
+    /// Red and blue swapped, for panels that expect BGR order.
+    Bgr,
+
+    /// A raw value for `REG_SWIZZLE`, for the other orderings described
+    /// in the datasheet.
+    Raw(u8),
+}
+
+impl Swizzle {
+    pub const fn reg_swizzle_value(self) -> u8 {
+        match self {
+            Self::Rgb => 0,
+            Self::Bgr => 2,
+            Self::Raw(v) => v & 0b1111,
+        }
+    }
+}
+
+/// Identifies a group of pins for the `PINDRIVE` and `PIN_PD_STATE`
+/// host commands.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+#[allow(non_camel_case_types)]
+pub enum PinGroup {
+    GPIO0 = 0x00,
+    GPIO1 = 0x01,
+    GPIO2 = 0x02,
+    GPIO3 = 0x03,
+    DISP = 0x08,
+    DE = 0x09,
+    VSYNC_HSYNC = 0x0a,
+    PCLK = 0x0b,
+    BACKLIGHT = 0x0c,
+    RGB = 0x0d,
+    AUDIO_L = 0x0e,
+    INT_N = 0x0f,
+    CTP_RST_N = 0x10,
+    CTP_SCL = 0x11,
+    CTP_SDA = 0x12,
+    SPI = 0x13,
+}
+
+impl PinGroup {
+    const COUNT: usize = 0x14;
+
+    const ALL: [Self; 16] = [
+        Self::GPIO0,
+        Self::GPIO1,
+        Self::GPIO2,
+        Self::GPIO3,
+        Self::DISP,
+        Self::DE,
+        Self::VSYNC_HSYNC,
+        Self::PCLK,
+        Self::BACKLIGHT,
+        Self::RGB,
+        Self::AUDIO_L,
+        Self::INT_N,
+        Self::CTP_RST_N,
+        Self::CTP_SCL,
+        Self::CTP_SDA,
+        Self::SPI,
+    ];
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+
+    // Returns the first argument byte for a PINDRIVE or PIN_PD_STATE host
+    // command, which combines the pin group with the setting for it.
+    const fn host_cmd_arg(self, setting: u8) -> u8 {
+        (self as u8) << 2 | (setting & 0b11)
+    }
+}
+
+/// Output drive strength for a [`PinGroup`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DriveStrength {
+    MA5,
+    MA10,
+    MA15,
+    MA20,
+}
+
+impl DriveStrength {
+    pub const fn pindrive_value(self) -> u8 {
+        match self {
+            Self::MA5 => 0,
+            Self::MA10 => 1,
+            Self::MA15 => 2,
+            Self::MA20 => 3,
+        }
+    }
+}
+
+/// The state of a [`PinGroup`] while the chip is powered down.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PinPowerDownState {
+    Float,
+    PullDown,
+    PullUp,
+}
+
+impl PinPowerDownState {
+    pub const fn pin_pd_state_value(self) -> u8 {
+        match self {
+            Self::Float => 0,
+            Self::PullDown => 1,
+            Self::PullUp => 2,
+        }
+    }
+}
+
+/// A backlight brightness, for
+/// [`EVE::set_backlight`](crate::EVE::set_backlight).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Brightness {
+    /// A raw duty cycle for the backlight PWM output, between 0 (off) and
+    /// 128 (fully on). Larger values are treated as 128.
+    Duty(u8),
+
+    /// A percentage of full brightness. Values over 100 are treated as 100.
+    Percent(u8),
+}
+
+impl Brightness {
+    pub const OFF: Self = Self::Duty(0);
+    pub const FULL: Self = Self::Duty(MAX_PWM_DUTY);
+
+    pub const fn reg_pwm_duty_value(self) -> u8 {
+        match self {
+            Self::Duty(v) if v > MAX_PWM_DUTY => MAX_PWM_DUTY,
+            Self::Duty(v) => v,
+            Self::Percent(v) if v > 100 => MAX_PWM_DUTY,
+            Self::Percent(v) => ((v as u16 * MAX_PWM_DUTY as u16 + 50) / 100) as u8,
+        }
+    }
 }
 
+/// The value of `REG_PWM_DUTY` for a fully-on backlight.
+pub const MAX_PWM_DUTY: u8 = 128;
+
+/// The range of frequencies, in hertz, that the backlight PWM output
+/// supports.
+pub const PWM_HZ_RANGE: core::ops::RangeInclusive<u16> = 250..=10000;
+
 /// Selects which clock edge of the pixel clock where video data will be sampled.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ClockPolarity {
@@ -167,6 +624,27 @@ pub const fn dimension_is_valid(v: u16) -> bool {
 
 const DIMENSION_MASK: u16 = 0b0000111111111111;
 
+pub(crate) fn set_backlight<M: Model, I: Interface>(
+    eve: &mut EVE<M, I>,
+    brightness: Brightness,
+) -> Result<(), Error<I>> {
+    use crate::registers::Register::*;
+
+    let ll = &mut eve.ll;
+    ll.wr8(M::reg_ptr(PWM_DUTY), brightness.reg_pwm_duty_value())
+}
+
+pub(crate) fn set_backlight_frequency<M: Model, I: Interface>(
+    eve: &mut EVE<M, I>,
+    hz: u16,
+) -> Result<(), Error<I>> {
+    use crate::registers::Register::*;
+
+    let hz = hz.clamp(*PWM_HZ_RANGE.start(), *PWM_HZ_RANGE.end());
+    let ll = &mut eve.ll;
+    ll.wr16(M::reg_ptr(PWM_HZ), hz)
+}
+
 pub(crate) fn activate_system_clock<M: Model, I: Interface>(
     eve: &mut EVE<M, I>,
     source: ClockSource,
@@ -273,19 +751,222 @@ pub(crate) fn activate_pixel_clock<M: Model, I: Interface>(
 
 pub(crate) fn configure_video_pins<M: Model, I: Interface>(
     eve: &mut EVE<M, I>,
-    _mode: &RGBElectricalMode,
+    mode: &RGBElectricalMode,
 ) -> Result<(), Error<I>> {
-    // TODO: Actually respect the mode settings. For now, just hard-coded.
+    use crate::host_commands::HostCmd;
     use crate::registers::Register::*;
 
+    if (mode.pclk_2x.is_some() || mode.pclk_freq.is_some()) && !M::HAS_PCLK_FREQ {
+        return Err(Error::Unsupported);
+    }
+
     let ll = &mut eve.ll;
 
-    ll.wr8(M::reg_ptr(OUTBITS), 0)?;
-    ll.wr8(M::reg_ptr(DITHER), 0)?;
-    ll.wr8(M::reg_ptr(SWIZZLE), 0)?;
-    ll.wr8(M::reg_ptr(CSPREAD), 0)?;
+    for group in PinGroup::ALL.iter() {
+        if let Some(v) = mode.pin_drive[group.index()] {
+            ll.host_command(HostCmd::PINDRIVE, group.host_cmd_arg(v.pindrive_value()), 0)?;
+        }
+        if let Some(v) = mode.pin_pd_state[group.index()] {
+            ll.host_command(
+                HostCmd::PIN_PD_STATE,
+                group.host_cmd_arg(v.pin_pd_state_value()),
+                0,
+            )?;
+        }
+    }
+
+    let outbits = mode.reg_outbits_value();
+    let dither = mode.dither as u8;
+    let swizzle = mode.swizzle.reg_swizzle_value();
+    let cspread = mode.pclk_spread as u8;
+
+    ll.wr16(M::reg_ptr(OUTBITS), outbits)?;
+    ll.wr8(M::reg_ptr(DITHER), dither)?;
+    ll.wr8(M::reg_ptr(SWIZZLE), swizzle)?;
+    ll.wr8(M::reg_ptr(CSPREAD), cspread)?;
+    if let Some(v) = mode.pclk_freq {
+        ll.wr16(M::reg_ptr(PCLK_FREQ), v)?;
+    }
+    if let Some(v) = mode.pclk_2x {
+        ll.wr8(M::reg_ptr(PCLK_2X), v as u8)?;
+    }
     ll.wr8(M::reg_ptr(ADAPTIVE_FRAMERATE), 0)?;
     ll.wr8(M::reg_ptr(GPIO), 0x83)?;
 
+    if mode.verify {
+        let expect = [
+            (OUTBITS, Some(outbits)),
+            (DITHER, Some(dither as u16)),
+            (SWIZZLE, Some(swizzle as u16)),
+            (CSPREAD, Some(cspread as u16)),
+            (PCLK_FREQ, mode.pclk_freq),
+            (PCLK_2X, mode.pclk_2x.map(|v| v as u16)),
+        ];
+        for (reg, want) in expect.iter() {
+            if let Some(want) = *want {
+                if ll.rd16(M::reg_ptr(*reg))? != want {
+                    return Err(Error::ReadbackMismatch(*reg));
+                }
+            }
+        }
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::fake::Interface as FakeInterface;
+    use crate::models::fake::Model as FakeModel;
+    use crate::registers::Register;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(VideoTimings::MODE_720P.validate(), Ok(()));
+
+        let timings = VideoTimings {
+            sysclk_freq: ClockFrequency::F60MHz,
+            pclk_div: 0,
+            pclk_pol: ClockPolarity::RisingEdge,
+            horiz: VideoTimingDimension {
+                total: 0x1000,
+                visible: 800,
+                offset: 100,
+                sync_start: 50,
+                sync_end: 10,
+            },
+            vert: VideoTimingDimension {
+                total: 500,
+                visible: 480,
+                offset: 30,
+                sync_start: 0,
+                sync_end: 5,
+            },
+        };
+        let problems = timings.validate().unwrap_err();
+        assert_eq!(
+            problems.as_slice(),
+            &[
+                TimingProblem::OutOfRange {
+                    axis: Axis::Horizontal,
+                    field: TimingField::Total,
+                    value: 0x1000,
+                },
+                TimingProblem::SyncStartAfterEnd {
+                    axis: Axis::Horizontal
+                },
+                TimingProblem::VisibleExceedsTotal {
+                    axis: Axis::Vertical
+                },
+                TimingProblem::PixelClockDivisorZero,
+            ][..]
+        );
+    }
+
+    #[test]
+    fn test_start_video_checked() {
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        let invalid = VideoTimings {
+            pclk_div: 0,
+            ..VideoTimings::MODE_720P
+        };
+        let result = eve.start_video_checked(&invalid);
+        match result {
+            Err(Error::InvalidTimings(problems)) => {
+                assert_eq!(problems.as_slice(), &[TimingProblem::PixelClockDivisorZero]);
+            }
+            _ => panic!("wrong result {:?}", result),
+        }
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::HCYCLE)).unwrap(), 0);
+
+        eve.start_video_checked(&VideoTimings::MODE_720P).unwrap();
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::HCYCLE)).unwrap(), 1650);
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::PCLK)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_configure_video_pins() {
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(crate::BT817, ei);
+
+        let mut mode = RGBElectricalMode::new();
+        mode.channel_bits(6, 6, 6)
+            .dither(true)
+            .swizzle(Swizzle::Bgr)
+            .pclk_spread(true)
+            .drive_strength(PinGroup::RGB, DriveStrength::MA10)
+            .power_down_state(PinGroup::DISP, PinPowerDownState::PullDown)
+            .pclk_2x(true)
+            .pclk_freq(0x8a1)
+            .verify(true);
+        eve.configure_video_pins(&mode).unwrap();
+
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::OUTBITS)).unwrap(), 0x1b6);
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::DITHER)).unwrap(), 1);
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::SWIZZLE)).unwrap(), 2);
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::CSPREAD)).unwrap(), 1);
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::PCLK_2X)).unwrap(), 1);
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::PCLK_FREQ)).unwrap(), 0x8a1);
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::GPIO)).unwrap(), 0x83);
+    }
+
+    #[test]
+    fn test_configure_video_pins_readback_mismatch() {
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        let mut mode = RGBElectricalMode::new();
+        mode.verify(true);
+        let outbits = FakeModel::reg_ptr(Register::OUTBITS).to_raw();
+        eve.borrow_interface().corrupt_read_of(outbits, 0, 0x01);
+        let result = eve.configure_video_pins(&mode);
+        assert!(
+            matches!(result, Err(Error::ReadbackMismatch(Register::OUTBITS))),
+            "wrong result {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_configure_video_pins_pclk_freq_unsupported() {
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(crate::BT815, ei);
+
+        let mut mode = RGBElectricalMode::new();
+        mode.pclk_2x(true);
+        let result = eve.configure_video_pins(&mode);
+        assert!(
+            matches!(result, Err(Error::Unsupported)),
+            "wrong result {:?}",
+            result
+        );
+        assert_eq!(eve.borrow_interface().transactions(), 0);
+    }
+
+    #[test]
+    fn test_backlight() {
+        assert_eq!(Brightness::Percent(50).reg_pwm_duty_value(), 64);
+        assert_eq!(Brightness::Percent(101).reg_pwm_duty_value(), 128);
+        assert_eq!(Brightness::Duty(200).reg_pwm_duty_value(), 128);
+
+        let mut regs = [0u32; 1024];
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        eve.set_backlight(Brightness::Percent(25)).unwrap();
+        eve.set_backlight_frequency(20000).unwrap();
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::PWM_DUTY)).unwrap(), 32);
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::PWM_HZ)).unwrap(), 10000);
+    }
+}
@@ -0,0 +1,168 @@
+//! Video timings for some common display panels and video modes.
+//!
+//! These are starting points rather than guarantees: panels from different
+//! vendors that share a resolution often have slightly different timing
+//! requirements, so check your panel's datasheet before relying on one of
+//! these.
+
+use super::{ClockFrequency, ClockPolarity, VideoTimingDimension, VideoTimings};
+
+/// 480x272 at approximately 60Hz, for the 4.3" panels commonly paired with
+/// EVE chips.
+///
+/// This uses a 9.6MHz pixel clock derived from a 48MHz system clock.
+pub const WQVGA_480X272: VideoTimings = VideoTimings {
+    sysclk_freq: ClockFrequency::F48MHz,
+    pclk_div: 5,
+    pclk_pol: ClockPolarity::FallingEdge,
+    horiz: VideoTimingDimension::calculate(480, 25, 41, 2),
+    vert: VideoTimingDimension::calculate(272, 8, 10, 2),
+};
+
+/// 800x480 at approximately 62Hz, for the 5" and 7" panels commonly paired
+/// with EVE chips.
+///
+/// This uses a 30MHz pixel clock derived from a 60MHz system clock.
+pub const WVGA_800X480: VideoTimings = VideoTimings {
+    sysclk_freq: ClockFrequency::F60MHz,
+    pclk_div: 2,
+    pclk_pol: ClockPolarity::FallingEdge,
+    horiz: VideoTimingDimension::calculate(800, 40, 48, 40),
+    vert: VideoTimingDimension::calculate(480, 13, 3, 29),
+};
+
+/// 1024x600 at approximately 56Hz, for 7" panels.
+///
+/// This uses a 48MHz pixel clock, which is within the typical range for
+/// these panels, and so requires a model that supports a pixel clock
+/// divisor of one.
+pub const WSVGA_1024X600: VideoTimings = VideoTimings {
+    sysclk_freq: ClockFrequency::F48MHz,
+    pclk_div: 1,
+    pclk_pol: ClockPolarity::FallingEdge,
+    horiz: VideoTimingDimension::calculate(1024, 160, 70, 90),
+    vert: VideoTimingDimension::calculate(600, 12, 10, 13),
+};
+
+/// 800x480 at approximately 60Hz, for small HDMI monitors that accept the
+/// CVT timings for that resolution.
+///
+/// This uses a 30MHz pixel clock derived from a 60MHz system clock.
+pub const WVGA_HDMI: VideoTimings = VideoTimings {
+    sysclk_freq: ClockFrequency::F60MHz,
+    pclk_div: 2,
+    pclk_pol: ClockPolarity::RisingEdge,
+    horiz: VideoTimingDimension::calculate(800, 24, 72, 96),
+    vert: VideoTimingDimension::calculate(480, 3, 7, 10),
+};
+
+/// An approximation of 720p over HDMI, using the standard blanking intervals
+/// with a 72MHz pixel clock rather than the nominal 74.25MHz, giving a
+/// refresh rate of approximately 58Hz.
+///
+/// This mode is only available on models that can run the system clock at
+/// 72MHz.
+pub const HDMI_720P: VideoTimings = VideoTimings {
+    sysclk_freq: ClockFrequency::F72MHz,
+    pclk_div: 1,
+    pclk_pol: ClockPolarity::RisingEdge,
+    horiz: VideoTimingDimension::calculate(1280, 110, 40, 220),
+    vert: VideoTimingDimension::calculate(720, 5, 5, 20),
+};
+
+/// The 720p timings used by the Gameduino 3X Dazzler's HDMI output.
+///
+/// These timings place the end of each sync period before its start, which
+/// the EVE chip treats as inverted sync polarity. That's what HDMI 720p
+/// expects, but [`VideoTimings::validate`](VideoTimings::validate) reports
+/// it as a problem, so use these with the unchecked
+/// [`EVE::start_video`](crate::EVE::start_video).
+pub const GAMEDUINO_HDMI_720P: VideoTimings = VideoTimings {
+    sysclk_freq: ClockFrequency::F72MHz,
+    pclk_div: 1,
+    pclk_pol: ClockPolarity::RisingEdge,
+    horiz: VideoTimingDimension {
+        total: 1650,
+        offset: 260,
+        visible: 1280,
+        sync_start: 40,
+        sync_end: 0,
+    },
+    vert: VideoTimingDimension {
+        total: 750,
+        offset: 25,
+        visible: 720,
+        sync_start: 5,
+        sync_end: 0,
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::super::{choose_pixel_clock, Axis, PixelClock, TimingProblem};
+    use super::*;
+
+    #[test]
+    fn test_preset_refresh_rates() {
+        assert_eq!(WQVGA_480X272.refresh_millihz(), 59_994);
+        assert_eq!(WVGA_800X480.refresh_millihz(), 61_576);
+        assert_eq!(WSVGA_1024X600.refresh_millihz(), 56_242);
+        assert_eq!(WVGA_HDMI.refresh_millihz(), 60_483);
+        assert_eq!(HDMI_720P.refresh_millihz(), 58_181);
+        assert_eq!(GAMEDUINO_HDMI_720P.refresh_millihz(), 58_181);
+    }
+
+    #[test]
+    fn test_preset_validity() {
+        assert_eq!(WQVGA_480X272.validate(), Ok(()));
+        assert_eq!(WVGA_800X480.validate(), Ok(()));
+        assert_eq!(WSVGA_1024X600.validate(), Ok(()));
+        assert_eq!(WVGA_HDMI.validate(), Ok(()));
+        assert_eq!(HDMI_720P.validate(), Ok(()));
+
+        // This one deliberately inverts the sync polarity.
+        let problems = GAMEDUINO_HDMI_720P.validate().unwrap_err();
+        assert_eq!(
+            problems.as_slice(),
+            &[
+                TimingProblem::SyncStartAfterEnd {
+                    axis: Axis::Horizontal
+                },
+                TimingProblem::SyncStartAfterEnd {
+                    axis: Axis::Vertical
+                },
+            ][..]
+        );
+    }
+
+    #[test]
+    fn test_choose_pixel_clock() {
+        let horiz = VideoTimingDimension::calculate(480, 25, 41, 2);
+        let vert = VideoTimingDimension::calculate(272, 8, 10, 2);
+        assert_eq!(
+            choose_pixel_clock(60_000, &horiz, &vert),
+            Some(PixelClock {
+                sysclk_freq: ClockFrequency::F48MHz,
+                pclk_div: 5,
+                refresh_millihz: 59_994,
+                error_millihz: -6,
+            })
+        );
+
+        let horiz = VideoTimingDimension::calculate(800, 24, 72, 96);
+        let vert = VideoTimingDimension::calculate(480, 3, 7, 10);
+        assert_eq!(
+            choose_pixel_clock(60_000, &horiz, &vert),
+            Some(PixelClock {
+                sysclk_freq: ClockFrequency::F60MHz,
+                pclk_div: 2,
+                refresh_millihz: 60_483,
+                error_millihz: 483,
+            })
+        );
+
+        // An empty raster can't produce any refresh rate at all.
+        let empty = VideoTimingDimension::calculate(0, 0, 0, 0);
+        assert_eq!(choose_pixel_clock(60_000, &empty, &empty), None);
+    }
+}
@@ -3,6 +3,9 @@
 pub mod options;
 pub mod shape_builder;
 
+mod decode;
+pub use decode::DecodedCmd;
+
 use crate::graphics::{Vertex2F, Vertex2II, RGB, RGBA};
 use crate::memory::{MainMem, MemoryRegion, Ptr};
 use core::fmt::Debug;
@@ -0,0 +1,1042 @@
+use super::options;
+use super::{DLCmd, OpCode};
+use crate::graphics::RGB;
+use core::convert::TryFrom;
+use core::fmt::Formatter;
+
+/// A display list command decoded into its opcode and typed fields, as
+/// returned by [`DLCmd::decode`](DLCmd::decode).
+///
+/// Each variant corresponds to one display list opcode, and has fields
+/// representing the same information as the arguments to the corresponding
+/// `DLCmd` constructor function. Some fields have a more limited range than
+/// their types suggest, because they're limited by the number of bits
+/// available in the command encoding.
+///
+/// The `Display` implementation produces the C-like syntax used in the
+/// EVE programming guides, such as `VERTEX2F(160, 320)`, which can be
+/// helpful when debugging display lists.
+///
+/// Converting a `DecodedCmd` back into a `DLCmd` produces the original
+/// encoding only if the original was canonically encoded, with all of its
+/// reserved and unused bits set to zero. Decoding ignores those bits, so
+/// re-encoding any other word produces its canonical form instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodedCmd {
+    AlphaFunc {
+        func: options::TestFunc,
+        ref_val: u8,
+    },
+    Begin(options::GraphicsPrimitive),
+    BitmapExtFormat(options::BitmapExtFormat),
+    BitmapHandle(options::BitmapHandle),
+    /// The low bits of the bitmap layout, with up to ten bits of line stride
+    /// and up to nine bits of height.
+    BitmapLayout {
+        format: options::BitmapFormat,
+        line_stride: u16,
+        height: u16,
+    },
+    /// The high bits of the bitmap layout, as the two most significant bits
+    /// of each of the line stride and height.
+    BitmapLayoutH {
+        line_stride: u8,
+        height: u8,
+    },
+    /// The low bits of the bitmap size, with up to nine bits each of width
+    /// and height.
+    BitmapSize {
+        filter: options::BitmapSizeFilter,
+        wrap_x: options::BitmapWrapMode,
+        wrap_y: options::BitmapWrapMode,
+        width: u16,
+        height: u16,
+    },
+    /// The high bits of the bitmap size, as the two most significant bits
+    /// of each of the width and height.
+    BitmapSizeH {
+        width: u8,
+        height: u8,
+    },
+    BitmapSource(u32),
+    BitmapSwizzle(options::BitmapSwizzle),
+    BitmapTransformA(options::MatrixCoeff),
+    BitmapTransformB(options::MatrixCoeff),
+    BitmapTransformC(options::MatrixCoeff),
+    BitmapTransformD(options::MatrixCoeff),
+    BitmapTransformE(options::MatrixCoeff),
+    BitmapTransformF(options::MatrixCoeff),
+    BlendFunc {
+        src: options::BlendFunc,
+        dst: options::BlendFunc,
+    },
+    Call(u32),
+    Cell(u8),
+    Clear {
+        color: bool,
+        stencil: bool,
+        tag: bool,
+    },
+    ClearColorA(u8),
+    ClearColorRGB(RGB),
+    ClearStencil(u8),
+    ClearTag(u8),
+    ColorA(u8),
+    ColorMask(options::ColorMask),
+    ColorRGB(RGB),
+    Display,
+    End,
+    Jump(u32),
+    LineWidth(u16),
+    Macro(u8),
+    Nop,
+    PaletteSource(u32),
+    PointSize(u16),
+    RestoreContext,
+    Return,
+    SaveContext,
+    ScissorSize {
+        width: u16,
+        height: u16,
+    },
+    ScissorXY {
+        x: u16,
+        y: u16,
+    },
+    StencilFunc {
+        func: options::TestFunc,
+        ref_val: u8,
+        mask: u8,
+    },
+    StencilMask(u8),
+    StencilOp {
+        fail: options::StencilOp,
+        pass: options::StencilOp,
+    },
+    Tag(u8),
+    TagMask(bool),
+    Vertex2F {
+        x: i16,
+        y: i16,
+    },
+    Vertex2II {
+        x: u16,
+        y: u16,
+        handle: u8,
+        cell: u8,
+    },
+    VertexFormat(options::VertexFormat),
+    VertexTranslateX(i16),
+    VertexTranslateY(i16),
+
+    /// A command word that doesn't correspond with any known opcode, or
+    /// which has field values that are invalid for its opcode.
+    Unknown(u32),
+}
+
+impl DLCmd {
+    /// Decodes the command into its opcode and typed fields.
+    ///
+    /// ```rust
+    /// use evegfx::display_list::{DLCmd, DecodedCmd};
+    ///
+    /// let cmd = DLCmd::vertex_2f((160, 320));
+    /// assert_eq!(cmd.decode(), DecodedCmd::Vertex2F { x: 160, y: 320 });
+    /// assert_eq!(format!("{}", cmd.decode()), "VERTEX2F(160, 320)");
+    /// ```
+    pub fn decode(self) -> DecodedCmd {
+        decode(self.0).unwrap_or(DecodedCmd::Unknown(self.0))
+    }
+}
+
+impl From<DecodedCmd> for DLCmd {
+    fn from(cmd: DecodedCmd) -> Self {
+        cmd.encode()
+    }
+}
+
+impl DecodedCmd {
+    /// Returns the raw encoding of the command.
+    pub fn encode(self) -> DLCmd {
+        use DecodedCmd::*;
+        match self {
+            AlphaFunc { func, ref_val } => DLCmd::alpha_test(func, ref_val),
+            Begin(prim) => DLCmd::begin(prim),
+            BitmapExtFormat(format) => DLCmd::bitmap_ext_format(format),
+            BitmapHandle(bmp) => DLCmd::bitmap_handle(bmp),
+            BitmapLayout {
+                format,
+                line_stride,
+                height,
+            } => DLCmd::bitmap_layout_l(format, line_stride, height),
+            BitmapLayoutH {
+                line_stride,
+                height,
+            } => OpCode::BITMAP_LAYOUT_H
+                .build((line_stride as u32 & 0b11) << 2 | (height as u32 & 0b11)),
+            BitmapSize {
+                filter,
+                wrap_x,
+                wrap_y,
+                width,
+                height,
+            } => DLCmd::bitmap_size_l(width, height, filter, wrap_x, wrap_y),
+            BitmapSizeH { width, height } => {
+                OpCode::BITMAP_SIZE_H.build((width as u32 & 0b11) << 2 | (height as u32 & 0b11))
+            }
+            BitmapSource(addr) => OpCode::BITMAP_SOURCE.build(addr & 0xffffff),
+            BitmapSwizzle(swizzle) => DLCmd::bitmap_swizzle(swizzle),
+            BitmapTransformA(coeff) => DLCmd::bitmap_transform_a(coeff),
+            BitmapTransformB(coeff) => DLCmd::bitmap_transform_b(coeff),
+            BitmapTransformC(coeff) => DLCmd::bitmap_transform_c(coeff),
+            BitmapTransformD(coeff) => DLCmd::bitmap_transform_d(coeff),
+            BitmapTransformE(coeff) => DLCmd::bitmap_transform_e(coeff),
+            BitmapTransformF(coeff) => DLCmd::bitmap_transform_f(coeff),
+            BlendFunc { src, dst } => DLCmd::blend_func(src, dst),
+            Call(dest) => OpCode::CALL.build(dest & 0xffffff),
+            Cell(idx) => OpCode::CELL.build(idx as u32 & 0b1111111),
+            Clear {
+                color,
+                stencil,
+                tag,
+            } => DLCmd::clear(color, stencil, tag),
+            ClearColorA(alpha) => DLCmd::clear_color_alpha(alpha),
+            ClearColorRGB(color) => DLCmd::clear_color_rgb(color),
+            ClearStencil(v) => DLCmd::clear_stencil(v),
+            ClearTag(v) => DLCmd::clear_tag(v),
+            ColorA(alpha) => DLCmd::color_alpha(alpha),
+            ColorMask(mask) => DLCmd::color_mask(mask),
+            ColorRGB(color) => DLCmd::color_rgb(color),
+            Display => DLCmd::DISPLAY,
+            End => DLCmd::END,
+            Jump(dest) => OpCode::JUMP.build(dest & 0xffffff),
+            LineWidth(w) => DLCmd::line_width(w),
+            Macro(num) => DLCmd::command_from_macro(num),
+            Nop => DLCmd::NOP,
+            PaletteSource(addr) => OpCode::PALETTE_SOURCE.build(addr & 0xffffff),
+            PointSize(size) => OpCode::POINT_SIZE.build(size as u32 & 0x1fff),
+            RestoreContext => DLCmd::RESTORE_CONTEXT,
+            Return => DLCmd::RETURN,
+            SaveContext => DLCmd::SAVE_CONTEXT,
+            ScissorSize { width, height } => DLCmd::scissor_size((width, height)),
+            ScissorXY { x, y } => OpCode::SCISSOR_XY
+                .build((x as u32 & 0b1111111111) << 10 | (y as u32 & 0b1111111111)),
+            StencilFunc {
+                func,
+                ref_val,
+                mask,
+            } => DLCmd::stencil_test(func, ref_val, mask),
+            StencilMask(mask) => DLCmd::stencil_mask(mask),
+            StencilOp { fail, pass } => DLCmd::stencil_op(fail, pass),
+            Tag(v) => DLCmd::tag(v),
+            TagMask(update) => DLCmd::tag_mask(update),
+            Vertex2F { x, y } => {
+                const MASK: u32 = 0x7fff;
+                OpCode::VERTEX2F.build((x as u32 & MASK) << 15 | (y as u32 & MASK))
+            }
+            Vertex2II { x, y, handle, cell } => OpCode::VERTEX2II.build(
+                (x as u32 & 0x1ff) << 21
+                    | (y as u32 & 0x1ff) << 12
+                    | (handle as u32 & 0x1f) << 7
+                    | (cell as u32 & 0x7f),
+            ),
+            VertexFormat(fmt) => DLCmd::vertex_format(fmt),
+            VertexTranslateX(v) => DLCmd::vertex_translate_x(v),
+            VertexTranslateY(v) => DLCmd::vertex_translate_y(v),
+            Unknown(raw) => DLCmd::from_raw(raw),
+        }
+    }
+}
+
+// Decodes the given raw command word, or returns `None` if it isn't a valid
+// encoding of any known command.
+fn decode(raw: u32) -> Option<DecodedCmd> {
+    use DecodedCmd::*;
+
+    // VERTEX2F and VERTEX2II are special because their opcodes are packed
+    // into only the two most significant bits, to leave more room for
+    // the coordinates.
+    match raw >> 30 {
+        0b01 => {
+            return Some(Vertex2F {
+                x: sign_extend(raw >> 15, 15) as i16,
+                y: sign_extend(raw, 15) as i16,
+            })
+        }
+        0b10 => {
+            return Some(Vertex2II {
+                x: ((raw >> 21) & 0x1ff) as u16,
+                y: ((raw >> 12) & 0x1ff) as u16,
+                handle: ((raw >> 7) & 0x1f) as u8,
+                cell: (raw & 0x7f) as u8,
+            })
+        }
+        0b11 => return None,
+        _ => {}
+    }
+
+    let op = (raw >> 24) as u8;
+    let b0 = raw as u8;
+    let b1 = (raw >> 8) as u8;
+    let b2 = (raw >> 16) as u8;
+    let addr = raw & 0xffffff;
+    let coeff = || options::MatrixCoeff(raw & 0x1ffff);
+
+    Some(match op {
+        x if x == OpCode::ALPHA_FUNC as u8 => AlphaFunc {
+            func: options::TestFunc::try_from(b1 & 0b111).ok()?,
+            ref_val: b0,
+        },
+        x if x == OpCode::BEGIN as u8 => {
+            Begin(options::GraphicsPrimitive::try_from(b0 & 0b1111).ok()?)
+        }
+        x if x == OpCode::BITMAP_EXT_FORMAT as u8 => {
+            BitmapExtFormat(options::BitmapExtFormat::try_from(raw as u16).ok()?)
+        }
+        x if x == OpCode::BITMAP_HANDLE as u8 => BitmapHandle(options::BitmapHandle::force_raw(b0)),
+        x if x == OpCode::BITMAP_LAYOUT as u8 => BitmapLayout {
+            format: options::BitmapFormat::try_from(((raw >> 19) & 0b11111) as u8).ok()?,
+            line_stride: ((raw >> 9) & 0b1111111111) as u16,
+            height: (raw & 0b111111111) as u16,
+        },
+        x if x == OpCode::BITMAP_LAYOUT_H as u8 => BitmapLayoutH {
+            line_stride: (b0 >> 2) & 0b11,
+            height: b0 & 0b11,
+        },
+        x if x == OpCode::BITMAP_SIZE as u8 => BitmapSize {
+            filter: options::BitmapSizeFilter::try_from(((raw >> 20) & 1) as u8).ok()?,
+            wrap_x: options::BitmapWrapMode::try_from(((raw >> 19) & 1) as u8).ok()?,
+            wrap_y: options::BitmapWrapMode::try_from(((raw >> 18) & 1) as u8).ok()?,
+            width: ((raw >> 9) & 0b111111111) as u16,
+            height: (raw & 0b111111111) as u16,
+        },
+        x if x == OpCode::BITMAP_SIZE_H as u8 => BitmapSizeH {
+            width: (b0 >> 2) & 0b11,
+            height: b0 & 0b11,
+        },
+        x if x == OpCode::BITMAP_SOURCE as u8 => BitmapSource(addr),
+        x if x == OpCode::BITMAP_SWIZZLE as u8 => {
+            let source = |shift: u32| {
+                options::BitmapSwizzleSource::try_from(((raw >> shift) & 0b111) as u8).ok()
+            };
+            BitmapSwizzle(options::BitmapSwizzle {
+                r: source(9)?,
+                g: source(6)?,
+                b: source(3)?,
+                a: source(0)?,
+            })
+        }
+        x if x == OpCode::BITMAP_TRANSFORM_A as u8 => BitmapTransformA(coeff()),
+        x if x == OpCode::BITMAP_TRANSFORM_B as u8 => BitmapTransformB(coeff()),
+        x if x == OpCode::BITMAP_TRANSFORM_C as u8 => BitmapTransformC(coeff()),
+        x if x == OpCode::BITMAP_TRANSFORM_D as u8 => BitmapTransformD(coeff()),
+        x if x == OpCode::BITMAP_TRANSFORM_E as u8 => BitmapTransformE(coeff()),
+        x if x == OpCode::BITMAP_TRANSFORM_F as u8 => BitmapTransformF(coeff()),
+        x if x == OpCode::BLEND_FUNC as u8 => BlendFunc {
+            src: options::BlendFunc::try_from((b0 >> 3) & 0b111).ok()?,
+            dst: options::BlendFunc::try_from(b0 & 0b111).ok()?,
+        },
+        x if x == OpCode::CALL as u8 => Call(addr),
+        x if x == OpCode::CELL as u8 => Cell(b0 & 0b1111111),
+        x if x == OpCode::CLEAR as u8 => Clear {
+            color: (b0 & 0b100) != 0,
+            stencil: (b0 & 0b010) != 0,
+            tag: (b0 & 0b001) != 0,
+        },
+        x if x == OpCode::CLEAR_COLOR_A as u8 => ClearColorA(b0),
+        x if x == OpCode::CLEAR_COLOR_RGB as u8 => ClearColorRGB(RGB {
+            r: b2,
+            g: b1,
+            b: b0,
+        }),
+        x if x == OpCode::CLEAR_STENCIL as u8 => ClearStencil(b0),
+        x if x == OpCode::CLEAR_TAG as u8 => ClearTag(b0),
+        x if x == OpCode::COLOR_A as u8 => ColorA(b0),
+        x if x == OpCode::COLOR_MASK as u8 => ColorMask(options::ColorMask::new(
+            (b0 & 0b1000) != 0,
+            (b0 & 0b0100) != 0,
+            (b0 & 0b0010) != 0,
+            (b0 & 0b0001) != 0,
+        )),
+        x if x == OpCode::COLOR_RGB as u8 => ColorRGB(RGB {
+            r: b2,
+            g: b1,
+            b: b0,
+        }),
+        x if x == OpCode::DISPLAY as u8 => Display,
+        x if x == OpCode::END as u8 => End,
+        x if x == OpCode::JUMP as u8 => Jump(addr),
+        x if x == OpCode::LINE_WIDTH as u8 => LineWidth((raw & 0xfff) as u16),
+        x if x == OpCode::MACRO as u8 => Macro(b0 & 0b1),
+        x if x == OpCode::NOP as u8 => Nop,
+        x if x == OpCode::PALETTE_SOURCE as u8 => PaletteSource(addr),
+        x if x == OpCode::POINT_SIZE as u8 => PointSize((raw & 0x1fff) as u16),
+        x if x == OpCode::RESTORE_CONTEXT as u8 => RestoreContext,
+        x if x == OpCode::RETURN as u8 => Return,
+        x if x == OpCode::SAVE_CONTEXT as u8 => SaveContext,
+        x if x == OpCode::SCISSOR_SIZE as u8 => ScissorSize {
+            width: ((raw >> 12) & 0xfff) as u16,
+            height: (raw & 0xfff) as u16,
+        },
+        x if x == OpCode::SCISSOR_XY as u8 => ScissorXY {
+            x: ((raw >> 10) & 0b1111111111) as u16,
+            y: (raw & 0b1111111111) as u16,
+        },
+        x if x == OpCode::STENCIL_FUNC as u8 => StencilFunc {
+            func: options::TestFunc::try_from(b2 & 0b1111).ok()?,
+            ref_val: b1,
+            mask: b0,
+        },
+        x if x == OpCode::STENCIL_MASK as u8 => StencilMask(b0),
+        x if x == OpCode::STENCIL_OP as u8 => StencilOp {
+            fail: options::StencilOp::try_from((b0 >> 3) & 0b111).ok()?,
+            pass: options::StencilOp::try_from(b0 & 0b111).ok()?,
+        },
+        x if x == OpCode::TAG as u8 => Tag(b0),
+        x if x == OpCode::TAG_MASK as u8 => TagMask((b0 & 1) != 0),
+        x if x == OpCode::VERTEX_FORMAT as u8 => {
+            VertexFormat(options::VertexFormat::try_from(b0 & 0b111).ok()?)
+        }
+        x if x == OpCode::VERTEX_TRANSLATE_X as u8 => VertexTranslateX(raw as u16 as i16),
+        x if x == OpCode::VERTEX_TRANSLATE_Y as u8 => VertexTranslateY(raw as u16 as i16),
+        _ => return None,
+    })
+}
+
+// Interprets the lowest `bits` bits of the given value as a two's complement
+// signed integer.
+fn sign_extend(v: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((v << shift) as i32) >> shift
+}
+
+impl core::fmt::Display for DecodedCmd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        use DecodedCmd::*;
+        match *self {
+            AlphaFunc { func, ref_val } => {
+                write!(f, "ALPHA_FUNC({}, {})", test_func_name(func), ref_val)
+            }
+            Begin(prim) => write!(f, "BEGIN({})", primitive_name(prim)),
+            BitmapExtFormat(format) => {
+                write!(f, "BITMAP_EXT_FORMAT({})", ext_format_name(format))
+            }
+            BitmapHandle(bmp) => write!(f, "BITMAP_HANDLE({})", bmp.0),
+            BitmapLayout {
+                format,
+                line_stride,
+                height,
+            } => write!(
+                f,
+                "BITMAP_LAYOUT({}, {}, {})",
+                format_name(format),
+                line_stride,
+                height
+            ),
+            BitmapLayoutH {
+                line_stride,
+                height,
+            } => write!(f, "BITMAP_LAYOUT_H({}, {})", line_stride, height),
+            BitmapSize {
+                filter,
+                wrap_x,
+                wrap_y,
+                width,
+                height,
+            } => write!(
+                f,
+                "BITMAP_SIZE({}, {}, {}, {}, {})",
+                filter_name(filter),
+                wrap_name(wrap_x),
+                wrap_name(wrap_y),
+                width,
+                height
+            ),
+            BitmapSizeH { width, height } => write!(f, "BITMAP_SIZE_H({}, {})", width, height),
+            BitmapSource(addr) => write!(f, "BITMAP_SOURCE({:#x})", addr),
+            BitmapSwizzle(swizzle) => write!(
+                f,
+                "BITMAP_SWIZZLE({}, {}, {}, {})",
+                swizzle_name(swizzle.r),
+                swizzle_name(swizzle.g),
+                swizzle_name(swizzle.b),
+                swizzle_name(swizzle.a)
+            ),
+            BitmapTransformA(coeff) => write_transform(f, 'A', coeff),
+            BitmapTransformB(coeff) => write_transform(f, 'B', coeff),
+            BitmapTransformC(coeff) => write_transform(f, 'C', coeff),
+            BitmapTransformD(coeff) => write_transform(f, 'D', coeff),
+            BitmapTransformE(coeff) => write_transform(f, 'E', coeff),
+            BitmapTransformF(coeff) => write_transform(f, 'F', coeff),
+            BlendFunc { src, dst } => write!(
+                f,
+                "BLEND_FUNC({}, {})",
+                blend_func_name(src),
+                blend_func_name(dst)
+            ),
+            Call(dest) => write!(f, "CALL({})", dest),
+            Cell(idx) => write!(f, "CELL({})", idx),
+            Clear {
+                color,
+                stencil,
+                tag,
+            } => write!(
+                f,
+                "CLEAR({}, {}, {})",
+                color as u8, stencil as u8, tag as u8
+            ),
+            ClearColorA(alpha) => write!(f, "CLEAR_COLOR_A({})", alpha),
+            ClearColorRGB(c) => write!(f, "CLEAR_COLOR_RGB({}, {}, {})", c.r, c.g, c.b),
+            ClearStencil(v) => write!(f, "CLEAR_STENCIL({})", v),
+            ClearTag(v) => write!(f, "CLEAR_TAG({})", v),
+            ColorA(alpha) => write!(f, "COLOR_A({})", alpha),
+            ColorMask(mask) => {
+                let raw = mask.to_raw();
+                write!(
+                    f,
+                    "COLOR_MASK({}, {}, {}, {})",
+                    (raw >> 3) & 1,
+                    (raw >> 2) & 1,
+                    (raw >> 1) & 1,
+                    raw & 1
+                )
+            }
+            ColorRGB(c) => write!(f, "COLOR_RGB({}, {}, {})", c.r, c.g, c.b),
+            Display => write!(f, "DISPLAY()"),
+            End => write!(f, "END()"),
+            Jump(dest) => write!(f, "JUMP({})", dest),
+            LineWidth(w) => write!(f, "LINE_WIDTH({})", w),
+            Macro(num) => write!(f, "MACRO({})", num),
+            Nop => write!(f, "NOP()"),
+            PaletteSource(addr) => write!(f, "PALETTE_SOURCE({:#x})", addr),
+            PointSize(size) => write!(f, "POINT_SIZE({})", size),
+            RestoreContext => write!(f, "RESTORE_CONTEXT()"),
+            Return => write!(f, "RETURN()"),
+            SaveContext => write!(f, "SAVE_CONTEXT()"),
+            ScissorSize { width, height } => write!(f, "SCISSOR_SIZE({}, {})", width, height),
+            ScissorXY { x, y } => write!(f, "SCISSOR_XY({}, {})", x, y),
+            StencilFunc {
+                func,
+                ref_val,
+                mask,
+            } => write!(
+                f,
+                "STENCIL_FUNC({}, {}, {})",
+                test_func_name(func),
+                ref_val,
+                mask
+            ),
+            StencilMask(mask) => write!(f, "STENCIL_MASK({})", mask),
+            StencilOp { fail, pass } => write!(
+                f,
+                "STENCIL_OP({}, {})",
+                stencil_op_name(fail),
+                stencil_op_name(pass)
+            ),
+            Tag(v) => write!(f, "TAG({})", v),
+            TagMask(update) => write!(f, "TAG_MASK({})", update as u8),
+            Vertex2F { x, y } => write!(f, "VERTEX2F({}, {})", x, y),
+            Vertex2II { x, y, handle, cell } => {
+                write!(f, "VERTEX2II({}, {}, {}, {})", x, y, handle, cell)
+            }
+            VertexFormat(fmt) => write!(f, "VERTEX_FORMAT({})", fmt.to_raw()),
+            VertexTranslateX(v) => write!(f, "VERTEX_TRANSLATE_X({})", v),
+            VertexTranslateY(v) => write!(f, "VERTEX_TRANSLATE_Y({})", v),
+            Unknown(raw) => write!(f, "UNKNOWN({:#010x})", raw),
+        }
+    }
+}
+
+impl core::fmt::Display for DLCmd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.decode(), f)
+    }
+}
+
+fn write_transform(
+    f: &mut Formatter<'_>,
+    which: char,
+    coeff: options::MatrixCoeff,
+) -> core::fmt::Result {
+    write!(
+        f,
+        "BITMAP_TRANSFORM_{}({}, {})",
+        which,
+        coeff.is_1_15() as u8,
+        coeff.to_raw_value()
+    )
+}
+
+fn test_func_name(v: options::TestFunc) -> &'static str {
+    use options::TestFunc::*;
+    match v {
+        Never => "NEVER",
+        Less => "LESS",
+        LEqual => "LEQUAL",
+        Greater => "GREATER",
+        GEqual => "GEQUAL",
+        Equal => "EQUAL",
+        NotEqual => "NOTEQUAL",
+        Always => "ALWAYS",
+    }
+}
+
+fn primitive_name(v: options::GraphicsPrimitive) -> &'static str {
+    use options::GraphicsPrimitive::*;
+    match v {
+        Bitmaps => "BITMAPS",
+        Points => "POINTS",
+        Lines => "LINES",
+        LineStrip => "LINE_STRIP",
+        EdgeStripR => "EDGE_STRIP_R",
+        EdgeStripL => "EDGE_STRIP_L",
+        EdgeStripA => "EDGE_STRIP_A",
+        EdgeStripB => "EDGE_STRIP_B",
+        Rects => "RECTS",
+    }
+}
+
+fn format_name(v: options::BitmapFormat) -> &'static str {
+    use options::BitmapFormat::*;
+    match v {
+        ARGB1555 => "ARGB1555",
+        L1 => "L1",
+        L4 => "L4",
+        L8 => "L8",
+        RGB332 => "RGB332",
+        ARGB2 => "ARGB2",
+        ARGB4 => "ARGB4",
+        RGB565 => "RGB565",
+        Text8x8 => "TEXT8X8",
+        TextVGA => "TEXTVGA",
+        Bargraph => "BARGRAPH",
+        Paletted565 => "PALETTED565",
+        Paletted4444 => "PALETTED4444",
+        Paletted8 => "PALETTED8",
+        L2 => "L2",
+        GLFormat => "GLFORMAT",
+    }
+}
+
+fn ext_format_name(v: options::BitmapExtFormat) -> &'static str {
+    use options::BitmapExtFormat::*;
+    match v {
+        ARGB1555 => "ARGB1555",
+        L1 => "L1",
+        L4 => "L4",
+        L8 => "L8",
+        RGB332 => "RGB332",
+        ARGB2 => "ARGB2",
+        ARGB4 => "ARGB4",
+        RGB565 => "RGB565",
+        Text8x8 => "TEXT8X8",
+        TextVGA => "TEXTVGA",
+        Bargraph => "BARGRAPH",
+        Paletted565 => "PALETTED565",
+        Paletted4444 => "PALETTED4444",
+        Paletted8 => "PALETTED8",
+        L2 => "L2",
+        CompressedRGBAASTC4x4KHR => "COMPRESSED_RGBA_ASTC_4x4_KHR",
+        CompressedRGBAASTC5x4KHR => "COMPRESSED_RGBA_ASTC_5x4_KHR",
+        CompressedRGBAASTC5x5KHR => "COMPRESSED_RGBA_ASTC_5x5_KHR",
+        CompressedRGBAASTC6x5KHR => "COMPRESSED_RGBA_ASTC_6x5_KHR",
+        CompressedRGBAASTC6x6KHR => "COMPRESSED_RGBA_ASTC_6x6_KHR",
+        CompressedRGBAASTC8x5KHR => "COMPRESSED_RGBA_ASTC_8x5_KHR",
+        CompressedRGBAASTC8x6KHR => "COMPRESSED_RGBA_ASTC_8x6_KHR",
+        CompressedRGBAASTC8x8KHR => "COMPRESSED_RGBA_ASTC_8x8_KHR",
+        CompressedRGBAASTC10x5KHR => "COMPRESSED_RGBA_ASTC_10x5_KHR",
+        CompressedRGBAASTC10x6KHR => "COMPRESSED_RGBA_ASTC_10x6_KHR",
+        CompressedRGBAASTC10x8KHR => "COMPRESSED_RGBA_ASTC_10x8_KHR",
+        CompressedRGBAASTC10x10KHR => "COMPRESSED_RGBA_ASTC_10x10_KHR",
+        CompressedRGBAASTC12x10KHR => "COMPRESSED_RGBA_ASTC_12x10_KHR",
+        CompressedRGBAASTC12x12KHR => "COMPRESSED_RGBA_ASTC_12x12_KHR",
+    }
+}
+
+fn filter_name(v: options::BitmapSizeFilter) -> &'static str {
+    match v {
+        options::BitmapSizeFilter::Nearest => "NEAREST",
+        options::BitmapSizeFilter::Bilinear => "BILINEAR",
+    }
+}
+
+fn wrap_name(v: options::BitmapWrapMode) -> &'static str {
+    match v {
+        options::BitmapWrapMode::Border => "BORDER",
+        options::BitmapWrapMode::Repeat => "REPEAT",
+    }
+}
+
+fn swizzle_name(v: options::BitmapSwizzleSource) -> &'static str {
+    use options::BitmapSwizzleSource::*;
+    match v {
+        Zero => "ZERO",
+        One => "ONE",
+        Red => "RED",
+        Green => "GREEN",
+        Blue => "BLUE",
+        Alpha => "ALPHA",
+    }
+}
+
+fn blend_func_name(v: options::BlendFunc) -> &'static str {
+    use options::BlendFunc::*;
+    match v {
+        Zero => "ZERO",
+        One => "ONE",
+        SrcAlpha => "SRC_ALPHA",
+        DstAlpha => "DST_ALPHA",
+        OneMinusSrcAlpha => "ONE_MINUS_SRC_ALPHA",
+        OneMinusDstAlpha => "ONE_MINUS_DST_ALPHA",
+    }
+}
+
+fn stencil_op_name(v: options::StencilOp) -> &'static str {
+    use options::StencilOp::*;
+    match v {
+        Zero => "ZERO",
+        Keep => "KEEP",
+        Replace => "REPLACE",
+        Incr => "INCR",
+        Decr => "DECR",
+        Invert => "INVERT",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::memory::MemoryRegion;
+    use crate::models::testing::DisplayListMem as TestDisplayListMem;
+    use crate::models::testing::MainMem as TestMainMem;
+    use std::string::ToString;
+
+    // Checks that the given command decodes to the expected value and
+    // prints as the expected string, and then that re-encoding the decoded
+    // value produces the original command.
+    fn assert_round_trip(cmd: DLCmd, want: DecodedCmd, want_str: &str) {
+        let got = cmd.decode();
+        assert_eq!(got, want, "wrong decoding of {:#010x}", cmd.as_raw());
+        assert_eq!(got.to_string(), want_str);
+        assert_eq!(cmd.to_string(), want_str);
+        assert_eq!(
+            DLCmd::from(got),
+            cmd,
+            "{} doesn't re-encode as {:#010x}",
+            want_str,
+            cmd.as_raw()
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        use DecodedCmd::*;
+
+        assert_round_trip(
+            DLCmd::alpha_test(options::TestFunc::Greater, 254),
+            AlphaFunc {
+                func: options::TestFunc::Greater,
+                ref_val: 254,
+            },
+            "ALPHA_FUNC(GREATER, 254)",
+        );
+        assert_round_trip(
+            DLCmd::begin(options::GraphicsPrimitive::Bitmaps),
+            Begin(options::GraphicsPrimitive::Bitmaps),
+            "BEGIN(BITMAPS)",
+        );
+        assert_round_trip(
+            DLCmd::begin(options::GraphicsPrimitive::EdgeStripR),
+            Begin(options::GraphicsPrimitive::EdgeStripR),
+            "BEGIN(EDGE_STRIP_R)",
+        );
+        assert_round_trip(DLCmd::bitmap_cell(2), Cell(2), "CELL(2)");
+        assert_round_trip(
+            DLCmd::bitmap_ext_format(options::BitmapExtFormat::CompressedRGBAASTC4x4KHR),
+            BitmapExtFormat(options::BitmapExtFormat::CompressedRGBAASTC4x4KHR),
+            "BITMAP_EXT_FORMAT(COMPRESSED_RGBA_ASTC_4x4_KHR)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_handle(options::BitmapHandle::force_raw(15)),
+            BitmapHandle(options::BitmapHandle::force_raw(15)),
+            "BITMAP_HANDLE(15)",
+        );
+        let (layout_l, layout_h) =
+            DLCmd::bitmap_layout_pair(options::BitmapFormat::ARGB4, 1024, 768);
+        assert_round_trip(
+            layout_l,
+            BitmapLayout {
+                format: options::BitmapFormat::ARGB4,
+                line_stride: 0,
+                height: 256,
+            },
+            "BITMAP_LAYOUT(ARGB4, 0, 256)",
+        );
+        assert_round_trip(
+            layout_h,
+            BitmapLayoutH {
+                line_stride: 1,
+                height: 0,
+            },
+            "BITMAP_LAYOUT_H(1, 0)",
+        );
+        let (size_l, size_h) = DLCmd::bitmap_size_pair(
+            800,
+            480,
+            options::BitmapSizeFilter::Bilinear,
+            options::BitmapWrapMode::Repeat,
+            options::BitmapWrapMode::Border,
+        );
+        assert_round_trip(
+            size_l,
+            BitmapSize {
+                filter: options::BitmapSizeFilter::Bilinear,
+                wrap_x: options::BitmapWrapMode::Repeat,
+                wrap_y: options::BitmapWrapMode::Border,
+                width: 800 & 0x1ff,
+                height: 480,
+            },
+            "BITMAP_SIZE(BILINEAR, REPEAT, BORDER, 288, 480)",
+        );
+        assert_round_trip(
+            size_h,
+            BitmapSizeH {
+                width: 1,
+                height: 0,
+            },
+            "BITMAP_SIZE_H(1, 0)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_source(TestMainMem::ptr(0x2000)),
+            BitmapSource(0x2000),
+            "BITMAP_SOURCE(0x2000)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_swizzle(options::BitmapSwizzle::default()),
+            BitmapSwizzle(options::BitmapSwizzle::default()),
+            "BITMAP_SWIZZLE(RED, GREEN, BLUE, ALPHA)",
+        );
+        let half = options::MatrixCoeff::new_f32_approx_1_15(0.5);
+        assert_round_trip(
+            DLCmd::bitmap_transform_a(1),
+            BitmapTransformA(options::MatrixCoeff::ONE),
+            "BITMAP_TRANSFORM_A(0, 256)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_transform_b(half),
+            BitmapTransformB(half),
+            "BITMAP_TRANSFORM_B(1, 16384)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_transform_c(-2),
+            BitmapTransformC(options::MatrixCoeff::new_int(-2)),
+            "BITMAP_TRANSFORM_C(0, -512)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_transform_d(0),
+            BitmapTransformD(options::MatrixCoeff::ZERO),
+            "BITMAP_TRANSFORM_D(0, 0)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_transform_e(1),
+            BitmapTransformE(options::MatrixCoeff::ONE),
+            "BITMAP_TRANSFORM_E(0, 256)",
+        );
+        assert_round_trip(
+            DLCmd::bitmap_transform_f(0),
+            BitmapTransformF(options::MatrixCoeff::ZERO),
+            "BITMAP_TRANSFORM_F(0, 0)",
+        );
+        assert_round_trip(
+            DLCmd::blend_func(
+                options::BlendFunc::SrcAlpha,
+                options::BlendFunc::OneMinusDstAlpha,
+            ),
+            BlendFunc {
+                src: options::BlendFunc::SrcAlpha,
+                dst: options::BlendFunc::OneMinusDstAlpha,
+            },
+            "BLEND_FUNC(SRC_ALPHA, ONE_MINUS_DST_ALPHA)",
+        );
+        assert_round_trip(DLCmd::call(TestDisplayListMem::ptr(4)), Call(4), "CALL(4)");
+        assert_round_trip(
+            DLCmd::clear(true, false, true),
+            Clear {
+                color: true,
+                stencil: false,
+                tag: true,
+            },
+            "CLEAR(1, 0, 1)",
+        );
+        assert_round_trip(
+            DLCmd::clear_color_alpha(128),
+            ClearColorA(128),
+            "CLEAR_COLOR_A(128)",
+        );
+        assert_round_trip(
+            DLCmd::clear_color_rgb(RGB {
+                r: 255,
+                g: 127,
+                b: 1,
+            }),
+            ClearColorRGB(RGB {
+                r: 255,
+                g: 127,
+                b: 1,
+            }),
+            "CLEAR_COLOR_RGB(255, 127, 1)",
+        );
+        assert_round_trip(DLCmd::clear_stencil(3), ClearStencil(3), "CLEAR_STENCIL(3)");
+        assert_round_trip(DLCmd::clear_tag(4), ClearTag(4), "CLEAR_TAG(4)");
+        assert_round_trip(DLCmd::color_alpha(5), ColorA(5), "COLOR_A(5)");
+        assert_round_trip(
+            DLCmd::color_mask(options::ColorMask::new(true, false, true, false)),
+            ColorMask(options::ColorMask::new(true, false, true, false)),
+            "COLOR_MASK(1, 0, 1, 0)",
+        );
+        assert_round_trip(
+            DLCmd::color_rgb(RGB { r: 1, g: 2, b: 3 }),
+            ColorRGB(RGB { r: 1, g: 2, b: 3 }),
+            "COLOR_RGB(1, 2, 3)",
+        );
+        assert_round_trip(DLCmd::display(), Display, "DISPLAY()");
+        assert_round_trip(DLCmd::end(), End, "END()");
+        assert_round_trip(DLCmd::jump(TestDisplayListMem::ptr(8)), Jump(8), "JUMP(8)");
+        assert_round_trip(DLCmd::command_from_macro(1), Macro(1), "MACRO(1)");
+        assert_round_trip(DLCmd::line_width(16), LineWidth(16), "LINE_WIDTH(16)");
+        assert_round_trip(DLCmd::nop(), Nop, "NOP()");
+        assert_round_trip(
+            DLCmd::palette_source(TestMainMem::ptr(0x100)),
+            PaletteSource(0x100),
+            "PALETTE_SOURCE(0x100)",
+        );
+        assert_round_trip(DLCmd::point_size(160), PointSize(160), "POINT_SIZE(160)");
+        assert_round_trip(
+            DLCmd::restore_context(),
+            RestoreContext,
+            "RESTORE_CONTEXT()",
+        );
+        assert_round_trip(DLCmd::return_from_call(), Return, "RETURN()");
+        assert_round_trip(DLCmd::save_context(), SaveContext, "SAVE_CONTEXT()");
+        assert_round_trip(
+            DLCmd::scissor_size((800, 480)),
+            ScissorSize {
+                width: 800,
+                height: 480,
+            },
+            "SCISSOR_SIZE(800, 480)",
+        );
+        assert_round_trip(
+            DLCmd::scissor_pos((10, 8)),
+            ScissorXY { x: 10, y: 8 },
+            "SCISSOR_XY(10, 8)",
+        );
+        assert_round_trip(
+            DLCmd::stencil_test(options::TestFunc::NotEqual, 1, 255),
+            StencilFunc {
+                func: options::TestFunc::NotEqual,
+                ref_val: 1,
+                mask: 255,
+            },
+            "STENCIL_FUNC(NOTEQUAL, 1, 255)",
+        );
+        assert_round_trip(DLCmd::stencil_mask(15), StencilMask(15), "STENCIL_MASK(15)");
+        assert_round_trip(
+            DLCmd::stencil_op(options::StencilOp::Keep, options::StencilOp::Incr),
+            StencilOp {
+                fail: options::StencilOp::Keep,
+                pass: options::StencilOp::Incr,
+            },
+            "STENCIL_OP(KEEP, INCR)",
+        );
+        assert_round_trip(DLCmd::tag(7), Tag(7), "TAG(7)");
+        assert_round_trip(DLCmd::tag_mask(true), TagMask(true), "TAG_MASK(1)");
+        assert_round_trip(DLCmd::tag_mask(false), TagMask(false), "TAG_MASK(0)");
+        assert_round_trip(
+            DLCmd::vertex_2f((160, 320)),
+            Vertex2F { x: 160, y: 320 },
+            "VERTEX2F(160, 320)",
+        );
+        assert_round_trip(
+            DLCmd::vertex_2ii((511, 3)),
+            Vertex2II {
+                x: 511,
+                y: 3,
+                handle: 0,
+                cell: 0,
+            },
+            "VERTEX2II(511, 3, 0, 0)",
+        );
+        assert_round_trip(
+            DLCmd::vertex_format(options::VertexFormat::Sixteenth),
+            VertexFormat(options::VertexFormat::Sixteenth),
+            "VERTEX_FORMAT(4)",
+        );
+        assert_round_trip(
+            DLCmd::vertex_translate_x(-16),
+            VertexTranslateX(-16),
+            "VERTEX_TRANSLATE_X(-16)",
+        );
+        assert_round_trip(
+            DLCmd::vertex_translate_y(32),
+            VertexTranslateY(32),
+            "VERTEX_TRANSLATE_Y(32)",
+        );
+    }
+
+    #[test]
+    fn test_decode_raw() {
+        use DecodedCmd::*;
+
+        // Some encodings can't be produced by the DLCmd constructors, but
+        // we should still be able to decode them.
+        assert_round_trip(
+            DLCmd::from_raw(0x7fffc001),
+            Vertex2F { x: -1, y: -16383 },
+            "VERTEX2F(-1, -16383)",
+        );
+        assert_round_trip(
+            DLCmd::from_raw(0x80000000 | 1 << 21 | 2 << 12 | 3 << 7 | 4),
+            Vertex2II {
+                x: 1,
+                y: 2,
+                handle: 3,
+                cell: 4,
+            },
+            "VERTEX2II(1, 2, 3, 4)",
+        );
+
+        // Unknown opcodes and invalid field values all decode as Unknown.
+        assert_round_trip(
+            DLCmd::from_raw(0xc0000000),
+            Unknown(0xc0000000),
+            "UNKNOWN(0xc0000000)",
+        );
+        assert_round_trip(
+            DLCmd::from_raw(0x3f000000),
+            Unknown(0x3f000000),
+            "UNKNOWN(0x3f000000)",
+        );
+        assert_round_trip(
+            DLCmd::from_raw(0x1f00000f),
+            Unknown(0x1f00000f),
+            "UNKNOWN(0x1f00000f)",
+        );
+        assert_round_trip(
+            DLCmd::from_raw(0x0b000037),
+            Unknown(0x0b000037),
+            "UNKNOWN(0x0b000037)",
+        );
+    }
+
+    #[test]
+    fn test_decode_reserved_bits() {
+        // The reserved bits of a non-canonical encoding are ignored while
+        // decoding, and so re-encoding produces the canonical form.
+        let cmd = DLCmd::from_raw(0x00000001);
+        assert_eq!(cmd.decode(), DecodedCmd::Display);
+        assert_eq!(DLCmd::from(cmd.decode()), DLCmd::DISPLAY);
+    }
+}
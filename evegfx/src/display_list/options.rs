@@ -6,7 +6,7 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 /// Test function options for both alpha test and stencil test during drawing
 /// operations. This is used by both the `alpha_test` and `stencil_test`
 /// methods.
-#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, PartialEq)]
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum TestFunc {
     Never = 0,
@@ -19,7 +19,7 @@ pub enum TestFunc {
     Always = 7,
 }
 
-#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, PartialEq)]
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum GraphicsPrimitive {
     Bitmaps = 1,
@@ -33,7 +33,7 @@ pub enum GraphicsPrimitive {
     Rects = 9,
 }
 
-#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, PartialEq)]
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq)]
 #[repr(u16)]
 pub enum BitmapExtFormat {
     ARGB1555 = 0,
@@ -67,7 +67,7 @@ pub enum BitmapExtFormat {
     CompressedRGBAASTC12x12KHR = 37821,
 }
 
-#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, PartialEq)]
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum BitmapFormat {
     ARGB1555 = 0,
@@ -187,7 +187,7 @@ impl TryFrom<BitmapFormat> for BitmapExtFormat {
 
 /// `BitmapHandle` is a display list bitmap handle, numbered between zero and
 /// 31.
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct BitmapHandle(pub(crate) u8);
 
 impl BitmapHandle {
@@ -248,7 +248,7 @@ impl From<BitmapHandle> for u32 {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(TryFromPrimitive, Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum BitmapSwizzleSource {
     Zero = 0,
@@ -259,7 +259,7 @@ pub enum BitmapSwizzleSource {
     Alpha = 5,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct BitmapSwizzle {
     pub r: BitmapSwizzleSource,
     pub g: BitmapSwizzleSource,
@@ -284,7 +284,7 @@ impl Default for BitmapSwizzle {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(TryFromPrimitive, Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum BlendFunc {
     Zero = 0,
@@ -364,7 +364,7 @@ impl Default for ColorMask {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(TryFromPrimitive, Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum StencilOp {
     Zero = 0,
@@ -381,7 +381,7 @@ impl StencilOp {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(TryFromPrimitive, Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum VertexFormat {
     Whole = 0,
@@ -398,7 +398,7 @@ impl VertexFormat {
 }
 
 /// A matrix coefficient for use with the bitmap transform matrix.
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct MatrixCoeff(pub(crate) u32);
 
 impl MatrixCoeff {
@@ -587,14 +587,14 @@ where
     }
 }
 
-#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, PartialEq)]
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum BitmapSizeFilter {
     Nearest = 0,
     Bilinear = 1,
 }
 
-#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, PartialEq)]
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum BitmapWrapMode {
     Border = 0,
@@ -12,6 +12,22 @@ pub enum Error<I: crate::interface::Interface> {
     /// some differences are handled only dynamically.
     Unsupported,
 
+    /// Indicates that video timings passed to
+    /// [`EVE::start_video_checked`](crate::EVE::start_video_checked) failed
+    /// validation, with the problems that
+    /// [`VideoTimings::validate`](crate::config::VideoTimings::validate)
+    /// reported.
+    InvalidTimings(crate::config::TimingProblems),
+
+    /// Indicates that the EVE chip didn't finish booting within the
+    /// allowed number of polls, during [`boot`](crate::boot).
+    BootTimeout,
+
+    /// Indicates that a register didn't contain the value just written to it
+    /// when read back, while verifying the settings from
+    /// [`RGBElectricalMode`](crate::config::RGBElectricalMode).
+    ReadbackMismatch(crate::low_level::Register),
+
     /// Errors encountered when sending or recieving data from the EVE chip.
     ///
     /// The wrapped error type for this variant is the error type for whichever
@@ -35,6 +51,20 @@ where
                 let mut debug_trait_builder = f.debug_tuple("Unsupported");
                 debug_trait_builder.finish()
             }
+            (&Error::InvalidTimings(ref __self_0),) => {
+                let mut debug_trait_builder = f.debug_tuple("InvalidTimings");
+                let _ = debug_trait_builder.field(&&(*__self_0));
+                debug_trait_builder.finish()
+            }
+            (&Error::BootTimeout,) => {
+                let mut debug_trait_builder = f.debug_tuple("BootTimeout");
+                debug_trait_builder.finish()
+            }
+            (&Error::ReadbackMismatch(ref __self_0),) => {
+                let mut debug_trait_builder = f.debug_tuple("ReadbackMismatch");
+                let _ = debug_trait_builder.field(&&(*__self_0));
+                debug_trait_builder.finish()
+            }
         }
     }
 }
@@ -85,6 +115,17 @@ where
     /// only that the coprocessor is blocked by being the fault state, not that
     /// the most recent method call put it in that state.
     Fault,
+
+    /// Indicates that the result of an operation was too large to fit in the
+    /// memory the caller provided for it.
+    Overflow,
+
+    /// Indicates that the waiter gave up waiting for the coprocessor, such
+    /// as when using a [`TimeoutWaiter`](crate::commands::waiter::TimeoutWaiter).
+    ///
+    /// The coprocessor may still be busy with earlier commands, so it's safe
+    /// to retry the operation afterwards.
+    Timeout,
 }
 
 impl<M, I, W> CoprocessorError<M, I, W>
@@ -97,6 +138,11 @@ where
         match err {
             Error::Unsupported => CoprocessorError::Unsupported,
             Error::Interface(e) => CoprocessorError::Interface(e),
+            // The remaining errors come only from booting and configuring
+            // the chip, which no coprocessor operation does, so they can't
+            // really arise here. We report them as unsupported rather than
+            // adding variants that coprocessor callers would never see.
+            _ => CoprocessorError::Unsupported,
         }
     }
 
@@ -133,6 +179,14 @@ where
                 let mut debug_trait_builder = f.debug_tuple("Fault");
                 debug_trait_builder.finish()
             }
+            (&CoprocessorError::Overflow,) => {
+                let mut debug_trait_builder = f.debug_tuple("Overflow");
+                debug_trait_builder.finish()
+            }
+            (&CoprocessorError::Timeout,) => {
+                let mut debug_trait_builder = f.debug_tuple("Timeout");
+                debug_trait_builder.finish()
+            }
         }
     }
 }
@@ -147,3 +201,54 @@ where
         Self::from_general_error(err)
     }
 }
+
+/// Error type for operations on an
+/// [`AsyncCoprocessor`](crate::commands::AsyncCoprocessor).
+///
+/// This has the same meaning as [`CoprocessorError`], but for use with
+/// [`AsyncInterface`](crate::interface::AsyncInterface) and
+/// [`AsyncWaiter`](crate::commands::waiter::AsyncWaiter) implementations.
+#[non_exhaustive]
+pub enum AsyncCoprocessorError<M, I, W>
+where
+    M: crate::models::Model,
+    I: crate::interface::AsyncInterface,
+    W: crate::commands::waiter::AsyncWaiter<M, I>,
+{
+    /// Errors encountered when sending or recieving data from the EVE chip.
+    Interface(I::Error),
+
+    /// Errors encountered while waiting for more space in the ring buffer.
+    Waiter(W::Error),
+
+    /// Indicates that the coprocessor itself reported a fault.
+    ///
+    /// See [`CoprocessorError::Fault`] for more information.
+    Fault,
+
+    /// Indicates that the result of an operation was too large to fit in the
+    /// memory the caller provided for it.
+    Overflow,
+
+    /// Indicates that the waiter gave up waiting for the coprocessor.
+    Timeout,
+}
+
+impl<M, I, W> core::fmt::Debug for AsyncCoprocessorError<M, I, W>
+where
+    M: crate::models::Model,
+    I: crate::interface::AsyncInterface,
+    W: crate::commands::waiter::AsyncWaiter<M, I>,
+    I::Error: core::fmt::Debug,
+    W::Error: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        match self {
+            AsyncCoprocessorError::Interface(err) => f.debug_tuple("Interface").field(err).finish(),
+            AsyncCoprocessorError::Waiter(err) => f.debug_tuple("Waiter").field(err).finish(),
+            AsyncCoprocessorError::Fault => f.debug_tuple("Fault").finish(),
+            AsyncCoprocessorError::Overflow => f.debug_tuple("Overflow").finish(),
+            AsyncCoprocessorError::Timeout => f.debug_tuple("Timeout").finish(),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Types for controlling the general-purpose I/O pins of an EVE chip.
+//!
+//! Many boards use these pins for functions such as enabling the display
+//! or an audio amplifier. The methods of [`EVE`](crate::EVE) and
+//! [`Coprocessor`](crate::commands::Coprocessor) which use these types work
+//! with `REG_GPIOX` and `REG_GPIOX_DIR`, reading the current value of the
+//! register and then changing only the bit for the selected pin.
+
+use crate::error::Error;
+use crate::interface::Interface;
+use crate::low_level::LowLevel;
+use crate::models::Model;
+use crate::registers::Register;
+
+/// Identifies one of the EVE general-purpose I/O pins.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GpioPin {
+    GPIO0,
+    GPIO1,
+    GPIO2,
+    GPIO3,
+
+    /// The `DISP` pin, which typically controls the display enable signal
+    /// of the attached panel.
+    DISP,
+}
+
+impl GpioPin {
+    pub const ALL: [Self; 5] = [
+        Self::GPIO0,
+        Self::GPIO1,
+        Self::GPIO2,
+        Self::GPIO3,
+        Self::DISP,
+    ];
+
+    /// Returns the bit which represents this pin in `REG_GPIOX` and
+    /// `REG_GPIOX_DIR`.
+    pub const fn reg_gpiox_mask(self) -> u16 {
+        match self {
+            Self::GPIO0 => 1 << 0,
+            Self::GPIO1 => 1 << 1,
+            Self::GPIO2 => 1 << 2,
+            Self::GPIO3 => 1 << 3,
+            Self::DISP => 1 << 15,
+        }
+    }
+}
+
+/// Selects whether a GPIO pin is an input or an output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GpioDirection {
+    Input,
+    Output,
+}
+
+pub(crate) fn set_direction<M: Model, I: Interface>(
+    ll: &mut LowLevel<M, I>,
+    pin: GpioPin,
+    dir: GpioDirection,
+) -> Result<(), Error<I>> {
+    let output = dir == GpioDirection::Output;
+    modify(ll, Register::GPIOX_DIR, pin.reg_gpiox_mask(), output)
+}
+
+pub(crate) fn direction<M: Model, I: Interface>(
+    ll: &mut LowLevel<M, I>,
+    pin: GpioPin,
+) -> Result<GpioDirection, Error<I>> {
+    let v = ll.rd16(M::reg_ptr(Register::GPIOX_DIR))?;
+    Ok(if (v & pin.reg_gpiox_mask()) != 0 {
+        GpioDirection::Output
+    } else {
+        GpioDirection::Input
+    })
+}
+
+pub(crate) fn write<M: Model, I: Interface>(
+    ll: &mut LowLevel<M, I>,
+    pin: GpioPin,
+    high: bool,
+) -> Result<(), Error<I>> {
+    modify(ll, Register::GPIO_X, pin.reg_gpiox_mask(), high)
+}
+
+pub(crate) fn read<M: Model, I: Interface>(
+    ll: &mut LowLevel<M, I>,
+    pin: GpioPin,
+) -> Result<bool, Error<I>> {
+    let v = ll.rd16(M::reg_ptr(Register::GPIO_X))?;
+    Ok((v & pin.reg_gpiox_mask()) != 0)
+}
+
+// Sets or clears the bits selected by `mask` in the given register, leaving
+// the other bits unchanged.
+fn modify<M: Model, I: Interface>(
+    ll: &mut LowLevel<M, I>,
+    reg: Register,
+    mask: u16,
+    set: bool,
+) -> Result<(), Error<I>> {
+    let ptr = M::reg_ptr(reg);
+    let v = ll.rd16(ptr)?;
+    let v = if set { v | mask } else { v & !mask };
+    ll.wr16(ptr, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::fake::Interface as FakeInterface;
+    use crate::models::fake::Model as FakeModel;
+    use crate::EVE;
+
+    #[test]
+    fn test_gpio() {
+        let mut regs = [0u32; 1024];
+        regs[Register::GPIOX_DIR.index()] = 0x8000;
+        regs[Register::GPIO_X.index()] = 0x3000;
+        let ei = FakeInterface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        eve.gpio_set_direction(GpioPin::GPIO2, GpioDirection::Output)
+            .unwrap();
+        eve.gpio_write(GpioPin::GPIO2, true).unwrap();
+        eve.gpio_write(GpioPin::DISP, true).unwrap();
+        assert_eq!(
+            eve.gpio_direction(GpioPin::GPIO2).unwrap(),
+            GpioDirection::Output
+        );
+        assert_eq!(
+            eve.gpio_direction(GpioPin::GPIO3).unwrap(),
+            GpioDirection::Input
+        );
+        assert!(eve.gpio_read(GpioPin::GPIO2).unwrap());
+        assert!(!eve.gpio_read(GpioPin::GPIO0).unwrap());
+
+        eve.gpio_write(GpioPin::DISP, false).unwrap();
+
+        // The other bits in the registers are left unchanged.
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::GPIOX_DIR)).unwrap(), 0x8004);
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::GPIO_X)).unwrap(), 0x3004);
+    }
+
+    #[test]
+    fn test_coprocessor_gpio() {
+        let mut bufs = crate::interface::fake::TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+
+        // The coprocessor must have finished this earlier write before we
+        // read the register to change just one bit.
+        cp.write_register(Register::GPIO_X, 0x0004).unwrap();
+        cp.block_gpio_write(GpioPin::GPIO0, true).unwrap();
+        cp.block_gpio_set_direction(GpioPin::GPIO0, GpioDirection::Output)
+            .unwrap();
+        assert!(cp.block_gpio_read(GpioPin::GPIO2).unwrap());
+        assert_eq!(
+            cp.block_gpio_direction(GpioPin::GPIO0).unwrap(),
+            GpioDirection::Output
+        );
+
+        assert_eq!(bufs.regs[Register::GPIO_X.index()], 0x0005);
+        assert_eq!(bufs.regs[Register::GPIOX_DIR.index()], 0x0001);
+    }
+}
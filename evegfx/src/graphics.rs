@@ -3,6 +3,7 @@
 mod bitmap;
 mod color;
 mod pos;
+pub(crate) mod rotation;
 
 #[doc(inline)]
 pub use pos::Vertex2D;
@@ -39,3 +40,6 @@ pub use pos::ScissorRect;
 
 #[doc(inline)]
 pub use bitmap::Bitmap;
+
+#[doc(inline)]
+pub use rotation::Rotation;
@@ -216,6 +216,7 @@ pub trait CoordinateSystem {
     fn mask_value(v: Self::Dim) -> Self::Dim;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Scaled {}
 impl CoordinateSystem for Scaled {
     type Dim = i16;
@@ -225,6 +226,7 @@ impl CoordinateSystem for Scaled {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Fixed {}
 impl CoordinateSystem for Fixed {
     type Dim = u16;
@@ -234,6 +236,7 @@ impl CoordinateSystem for Fixed {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ForGlobalTranslate {}
 impl CoordinateSystem for ForGlobalTranslate {
     type Dim = i16;
@@ -243,6 +246,7 @@ impl CoordinateSystem for ForGlobalTranslate {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ForCoprocessorWidgets {}
 impl CoordinateSystem for ForCoprocessorWidgets {
     type Dim = i16;
@@ -252,6 +256,7 @@ impl CoordinateSystem for ForCoprocessorWidgets {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ForScissorClip {}
 impl CoordinateSystem for ForScissorClip {
     type Dim = u16;
@@ -0,0 +1,100 @@
+use crate::error::Error;
+use crate::interface::Interface;
+use crate::low_level::{LowLevel, Register};
+use crate::models::Model;
+
+/// Selects the orientation of the display, as used by
+/// [`Coprocessor::set_rotation`](crate::commands::Coprocessor::set_rotation).
+///
+/// The portrait orientations swap the width and height of the screen, so
+/// layout code should use [`logical_size`](Rotation::logical_size) rather
+/// than the physical size of the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    Landscape,
+    InvertedLandscape,
+    Portrait,
+    InvertedPortrait,
+    MirroredLandscape,
+    MirroredInvertedLandscape,
+    MirroredPortrait,
+    MirroredInvertedPortrait,
+}
+
+impl Rotation {
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            Self::Landscape => 0,
+            Self::InvertedLandscape => 1,
+            Self::Portrait => 2,
+            Self::InvertedPortrait => 3,
+            Self::MirroredLandscape => 4,
+            Self::MirroredInvertedLandscape => 5,
+            Self::MirroredPortrait => 6,
+            Self::MirroredInvertedPortrait => 7,
+        }
+    }
+
+    /// Returns the rotation for the given value of `REG_ROTATE`, ignoring
+    /// the unused upper bits.
+    pub const fn from_raw(raw: u8) -> Self {
+        match raw & 0b111 {
+            0 => Self::Landscape,
+            1 => Self::InvertedLandscape,
+            2 => Self::Portrait,
+            3 => Self::InvertedPortrait,
+            4 => Self::MirroredLandscape,
+            5 => Self::MirroredInvertedLandscape,
+            6 => Self::MirroredPortrait,
+            _ => Self::MirroredInvertedPortrait,
+        }
+    }
+
+    pub const fn is_portrait(self) -> bool {
+        (self.to_raw() & 0b010) != 0
+    }
+
+    /// Returns the width and height of the screen as seen by drawing
+    /// commands, given the physical width and height of the display.
+    pub const fn logical_size(self, physical: (u16, u16)) -> (u16, u16) {
+        if self.is_portrait() {
+            (physical.1, physical.0)
+        } else {
+            physical
+        }
+    }
+}
+
+// Reads the physical size of the display and the current rotation, and
+// returns the logical size that results.
+pub(crate) fn read_screen_size<M: Model, I: Interface>(
+    ll: &mut LowLevel<M, I>,
+) -> Result<(u16, u16), Error<I>> {
+    let w = ll.rd16(M::reg_ptr(Register::HSIZE))?;
+    let h = ll.rd16(M::reg_ptr(Register::VSIZE))?;
+    let rotation = ll.rd8(M::reg_ptr(Register::ROTATE))?;
+    Ok(Rotation::from_raw(rotation).logical_size((w, h)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logical_size() {
+        for raw in 0..8 {
+            assert_eq!(Rotation::from_raw(raw).to_raw(), raw);
+        }
+        assert_eq!(Rotation::Landscape.logical_size((800, 480)), (800, 480));
+        assert_eq!(
+            Rotation::MirroredInvertedLandscape.logical_size((800, 480)),
+            (800, 480)
+        );
+        assert_eq!(Rotation::Portrait.logical_size((800, 480)), (480, 800));
+        assert_eq!(
+            Rotation::MirroredInvertedPortrait.logical_size((800, 480)),
+            (480, 800)
+        );
+    }
+}
@@ -42,9 +42,7 @@ pub trait Interface: Sized {
     /// physical implementations that need to construct a message
     /// buffer to transmit to the real chip, e.g. via SPI.
     fn build_write_header(&self, addr: u32, into: &mut [u8; 3]) {
-        into[0] = (((addr >> 16) & 0b00111111) | 0b10000000) as u8;
-        into[1] = (addr >> 8) as u8;
-        into[2] = (addr >> 0) as u8;
+        build_write_header(addr, into)
     }
 
     /// Write the four bytes needed to form a "read memory" header
@@ -52,30 +50,129 @@ pub trait Interface: Sized {
     /// physical implementations that need to construct a message
     /// buffer to transmit to the real chip, e.g. via SPI.
     fn build_read_header(&self, addr: u32, into: &mut [u8; 4]) {
-        into[0] = ((addr >> 16) & 0b00111111) as u8;
-        into[1] = (addr >> 8) as u8;
-        into[2] = (addr >> 0) as u8;
-        into[3] = 0; // "dummy byte", per the datasheet
+        build_read_header(addr, into)
     }
 
     /// Write the three bytes needed to form a command message
     /// for the command and two arguments given. This is a helper
     /// for physical implementations that need to construct a
     /// message buffer to transmit to the real chip, e.g. via SPI.
-    fn build_host_cmd_msg(&self, mut cmd: u8, a0: u8, a1: u8, into: &mut [u8; 3]) {
-        // Make sure the command conforms to the expected bit pattern so that
-        // it won't be misunderstood as a read or write.
-        // Command zero, ACTIVE, is an exception that's intentionally encoded
-        // to look the same as a read header for address zero.
-        if cmd != 0 {
-            cmd = (cmd & 0b00111111) | 0b01000000;
+    fn build_host_cmd_msg(&self, cmd: u8, a0: u8, a1: u8, into: &mut [u8; 3]) {
+        build_host_cmd_msg(cmd, a0, a1, into)
+    }
+}
+
+/// Implementations of `AsyncInterface` are the `async` equivalent of
+/// [`Interface`], for use with
+/// [`AsyncCoprocessor`](crate::commands::AsyncCoprocessor) on systems with
+/// an async executor.
+///
+/// Unlike `Interface`, this trait deals only in whole transactions, because
+/// async bus drivers typically manage the chip select signal themselves and
+/// so can't leave a transaction open between calls.
+#[allow(async_fn_in_trait)]
+pub trait AsyncInterface: Sized {
+    type Error;
+
+    /// Writes the given bytes to consecutive addresses starting at `addr`,
+    /// in a single transaction.
+    async fn write(&mut self, addr: u32, v: &[u8]) -> Result<(), Self::Error>;
+
+    /// Reads enough bytes to fill `into` from consecutive addresses starting
+    /// at `addr`, in a single transaction.
+    async fn read(&mut self, addr: u32, into: &mut [u8]) -> Result<(), Self::Error>;
+
+    async fn host_cmd(&mut self, cmd: u8, a0: u8, a1: u8) -> Result<(), Self::Error>;
+
+    async fn reset(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The same as [`Interface::build_write_header`].
+    fn build_write_header(&self, addr: u32, into: &mut [u8; 3]) {
+        build_write_header(addr, into)
+    }
+
+    /// The same as [`Interface::build_read_header`].
+    fn build_read_header(&self, addr: u32, into: &mut [u8; 4]) {
+        build_read_header(addr, into)
+    }
+
+    /// The same as [`Interface::build_host_cmd_msg`].
+    fn build_host_cmd_msg(&self, cmd: u8, a0: u8, a1: u8, into: &mut [u8; 3]) {
+        build_host_cmd_msg(cmd, a0, a1, into)
+    }
+}
+
+/// The number of data lines used for SPI transfers between the host and the
+/// EVE chip, as selected by [`EVE::set_spi_width`](crate::EVE::set_spi_width).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpiWidth {
+    Single,
+    Dual,
+    Quad,
+}
+
+impl SpiWidth {
+    /// Returns the value to write to `REG_SPI_WIDTH` to select this width.
+    ///
+    /// The "extra dummy byte" bit is always left unset, so that read
+    /// transactions use the same four-byte header regardless of width.
+    pub(crate) fn reg_value(self) -> u8 {
+        match self {
+            SpiWidth::Single => 0b00,
+            SpiWidth::Dual => 0b01,
+            SpiWidth::Quad => 0b10,
         }
-        into[0] = cmd;
-        into[1] = a0;
-        into[2] = a1;
     }
 }
 
+/// Implementations of `SpiWidthInterface` are interfaces that can switch
+/// the host side of the SPI bus between single, dual and quad data lines,
+/// so that [`EVE::set_spi_width`](crate::EVE::set_spi_width) can switch
+/// the chip and the host together.
+///
+/// In dual and quad modes the whole transaction, including the address
+/// header and the read dummy byte, is transferred over the multiple data
+/// lines. Resetting the chip returns it to single mode, so implementations
+/// should also return to single mode in [`Interface::reset`].
+pub trait SpiWidthInterface: Interface {
+    /// Returns true if the interface is able to use the given width.
+    fn supports_spi_width(&self, width: SpiWidth) -> bool;
+
+    /// Switches the host side of the bus to the given width.
+    ///
+    /// The library calls this only after it has already switched the chip,
+    /// and so the next transaction must use the new width.
+    fn set_spi_width(&mut self, width: SpiWidth) -> Result<(), Self::Error>;
+}
+
+fn build_write_header(addr: u32, into: &mut [u8; 3]) {
+    into[0] = (((addr >> 16) & 0b00111111) | 0b10000000) as u8;
+    into[1] = (addr >> 8) as u8;
+    into[2] = (addr >> 0) as u8;
+}
+
+fn build_read_header(addr: u32, into: &mut [u8; 4]) {
+    into[0] = ((addr >> 16) & 0b00111111) as u8;
+    into[1] = (addr >> 8) as u8;
+    into[2] = (addr >> 0) as u8;
+    into[3] = 0; // "dummy byte", per the datasheet
+}
+
+fn build_host_cmd_msg(mut cmd: u8, a0: u8, a1: u8, into: &mut [u8; 3]) {
+    // Make sure the command conforms to the expected bit pattern so that
+    // it won't be misunderstood as a read or write.
+    // Command zero, ACTIVE, is an exception that's intentionally encoded
+    // to look the same as a read header for address zero.
+    if cmd != 0 {
+        cmd = (cmd & 0b00111111) | 0b01000000;
+    }
+    into[0] = cmd;
+    into[1] = a0;
+    into[2] = a1;
+}
+
 /// Read the raw chip ID data from the given interface. This is a helper
 /// for callers of the lowest-level interface API. Higher layers may
 /// provide a more abstract form of this helper which interpret the raw
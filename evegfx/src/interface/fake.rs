@@ -5,7 +5,17 @@ use crate::commands::Coprocessor;
 use crate::low_level::Register;
 use crate::memory::MemoryRegion;
 use crate::models::fake::Model as FakeModel;
-use crate::models::Model;
+use crate::models::{Model, WithCommandErrMem};
+
+#[cfg(test)]
+extern crate std;
+
+mod coprocessor;
+mod faults;
+mod host;
+pub mod raster;
+
+pub use host::PowerState;
 
 pub type WithFakeModel<'a> = Interface<'a, FakeModel>;
 
@@ -58,13 +68,86 @@ pub fn coprocessor_example<R>(f: impl FnOnce(ExampleCoprocessor) -> R) -> R {
     })
 }
 
+/// Run the given function with an instance of the fake `Interface` that
+/// simulates a chip that hasn't yet been booted.
+///
+/// This is only here to make it easy to write testable code examples in the
+/// crate documentation.
+#[doc(hidden)]
+pub fn boot_example<R>(f: impl FnOnce(ExampleInterface) -> R) -> R {
+    const KB: usize = 1024;
+    let mut mem: [u8; 1024 * KB] = [0; 1024 * KB];
+    let mut regs: [u32; 1 * KB] = [0; 1 * KB];
+    let mut dl: [u8; 8 * KB] = [0; 8 * KB];
+    let mut cmd: [u8; 4 * KB] = [0; 4 * KB];
+
+    let ei = Interface::new(FakeModel)
+        .with_main_ram(&mut mem[..])
+        .with_display_list_ram(&mut dl[..])
+        .with_cmd_ram(&mut cmd[..])
+        .with_register_file(&mut regs[..])
+        .with_coprocessor()
+        .with_host_simulation();
+    f(ei)
+}
+
+/// Owns the memory for a fake interface with coprocessor emulation, so that
+/// tests elsewhere in this crate can borrow interfaces from it.
+#[cfg(test)]
+pub(crate) struct TestBuffers {
+    pub(crate) mem: std::vec::Vec<u8>,
+    pub(crate) dl: std::vec::Vec<u8>,
+    pub(crate) cmd: std::vec::Vec<u8>,
+    pub(crate) regs: std::vec::Vec<u32>,
+}
+
+#[cfg(test)]
+impl TestBuffers {
+    pub(crate) fn new() -> Self {
+        Self {
+            mem: std::vec![0; 64 * 1024],
+            dl: std::vec![0; <FakeModel as Model>::DisplayListMem::LENGTH as usize],
+            cmd: std::vec![0; 4 * 1024],
+            // Large enough to include the registers at the end of the
+            // register space, such as REG_COPRO_PATCH_PTR.
+            regs: std::vec![0; 0x8000 / 4],
+        }
+    }
+
+    /// Returns an interface using the buffers, with the coprocessor
+    /// emulation enabled.
+    pub(crate) fn interface(&mut self) -> ExampleInterface<'_> {
+        Interface::new(FakeModel)
+            .with_main_ram(&mut self.mem[..])
+            .with_display_list_ram(&mut self.dl[..])
+            .with_cmd_ram(&mut self.cmd[..])
+            .with_register_file(&mut self.regs[..])
+            .with_coprocessor()
+    }
+
+    pub(crate) fn coprocessor(&mut self) -> ExampleCoprocessor<'_> {
+        Coprocessor::new_polling(self.interface()).unwrap()
+    }
+}
+
 /// An implementation of [`Interface`](super::Interface) which just reads and
 /// writes a buffer in local RAM.
 ///
 /// This type alone doesn't implement any of the typical functionality that
 /// would be expected from an EVE chip, but it could in principle be used in
 /// conjunction with other code (probably running in a separate thread) to
-/// simulate parts of the EVE functionality for testing purposes.
+/// simulate parts of the EVE functionality for testing purposes. It can
+/// also emulate a subset of the coprocessor; see
+/// [`with_coprocessor`](Interface::with_coprocessor).
+///
+/// Tests can also inject communication errors and coprocessor faults at
+/// specific points, using methods such as
+/// [`fail_transaction`](Interface::fail_transaction), in order to exercise
+/// error handling.
+///
+/// This type also implements [`AsyncInterface`](super::AsyncInterface), for
+/// testing code that uses the async API. The async methods complete
+/// immediately, and count as one transaction each.
 ///
 /// This is mainly here just so there's a simple backend to write tests and
 /// examples against.
@@ -73,6 +156,11 @@ pub struct Interface<'a, M: Model, RF: RegisterFile = NoRegisterFile> {
     display_list_ram: &'a mut [u8],
     registers: RF,
     cmd_ram: &'a mut [u8],
+    coprocessor: Option<coprocessor::Emulator>,
+    host: Option<host::HostState>,
+    faults: faults::Faults,
+    spi_width: super::SpiWidth,
+    max_spi_width: super::SpiWidth,
 
     write_addr: Option<u32>,
     read_addr: Option<u32>,
@@ -87,6 +175,11 @@ impl<'a, M: Model> Interface<'a, M, NoRegisterFile> {
             display_list_ram: &mut [],
             registers: NoRegisterFile,
             cmd_ram: &mut [],
+            coprocessor: None,
+            host: None,
+            faults: faults::Faults::default(),
+            spi_width: super::SpiWidth::Single,
+            max_spi_width: super::SpiWidth::Quad,
 
             write_addr: None,
             read_addr: None,
@@ -103,6 +196,11 @@ impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
             display_list_ram: self.display_list_ram,
             registers: self.registers,
             cmd_ram: self.cmd_ram,
+            coprocessor: self.coprocessor,
+            host: self.host,
+            faults: self.faults,
+            spi_width: self.spi_width,
+            max_spi_width: self.max_spi_width,
             write_addr: self.write_addr,
             read_addr: self.read_addr,
             _model: self._model,
@@ -114,6 +212,11 @@ impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
             display_list_ram: buf,
             registers: self.registers,
             cmd_ram: self.cmd_ram,
+            coprocessor: self.coprocessor,
+            host: self.host,
+            faults: self.faults,
+            spi_width: self.spi_width,
+            max_spi_width: self.max_spi_width,
             write_addr: self.write_addr,
             read_addr: self.read_addr,
             _model: self._model,
@@ -125,6 +228,11 @@ impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
             display_list_ram: self.display_list_ram,
             registers: new,
             cmd_ram: self.cmd_ram,
+            coprocessor: self.coprocessor,
+            host: self.host,
+            faults: self.faults,
+            spi_width: self.spi_width,
+            max_spi_width: self.max_spi_width,
             write_addr: self.write_addr,
             read_addr: self.read_addr,
             _model: self._model,
@@ -136,12 +244,60 @@ impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
             display_list_ram: self.display_list_ram,
             registers: self.registers,
             cmd_ram: buf,
+            coprocessor: self.coprocessor,
+            host: self.host,
+            faults: self.faults,
+            spi_width: self.spi_width,
+            max_spi_width: self.max_spi_width,
             write_addr: self.write_addr,
             read_addr: self.read_addr,
             _model: self._model,
         }
     }
 
+    /// Enables simulation of the chip's power states, host commands and
+    /// system clock.
+    ///
+    /// With the simulation enabled, the fake chip starts in the SLEEP state
+    /// as if it had just been powered on, and then responds to host
+    /// commands in the same way as the real chip: it begins booting when it
+    /// first becomes active or after a `RST_PULSE` command, and once a few
+    /// interface transactions have passed it sets `REG_ID` to `0x7c`,
+    /// releases `REG_CPURESET` and sets `REG_FREQUENCY` to the frequency
+    /// selected with `CLKSEL`. After that, `REG_CLOCK` advances as though
+    /// each transaction took one microsecond.
+    ///
+    /// Host commands that the real chip doesn't accept in its current power
+    /// state cause [`Error::InvalidHostCommand`], and memory accesses while
+    /// the chip isn't active cause [`Error::NotActive`]. The simulation
+    /// requires a register file.
+    pub fn with_host_simulation(self) -> Self {
+        Self {
+            main_ram: self.main_ram,
+            display_list_ram: self.display_list_ram,
+            registers: self.registers,
+            cmd_ram: self.cmd_ram,
+            coprocessor: self.coprocessor,
+            host: Some(host::HostState::new(host::HostState::default_boot_delay())),
+            faults: self.faults,
+            spi_width: self.spi_width,
+            max_spi_width: self.max_spi_width,
+            write_addr: self.write_addr,
+            read_addr: self.read_addr,
+            _model: self._model,
+        }
+    }
+
+    /// Limits the SPI widths that the fake interface claims to support, for
+    /// testing code that must handle interfaces without quad or dual SPI
+    /// support.
+    pub fn with_max_spi_width(self, max: super::SpiWidth) -> Self {
+        Self {
+            max_spi_width: max,
+            ..self
+        }
+    }
+
     pub fn model_main_mem_size() -> u32 {
         M::MainMem::LENGTH
     }
@@ -158,7 +314,80 @@ impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
         M::CommandMem::LENGTH
     }
 
-    fn offset_addr(addr: u32) -> OffsetAddr {
+    /// Renders the display list currently stored in the fake display list
+    /// memory into the given framebuffer, using the
+    /// [software rasterizer](raster).
+    ///
+    /// This allows testing code that generates display lists, either
+    /// directly or via the coprocessor, by comparing the rendered result
+    /// with a known-good image.
+    pub fn render_display_list(&self, target: &mut raster::Framebuffer) {
+        let macros = [
+            self.registers.internal_read(Register::MACRO_0),
+            self.registers.internal_read(Register::MACRO_1),
+        ];
+        raster::render(self.display_list_ram, self.main_ram, macros, target)
+    }
+
+    // Reads memory at the given address without disturbing any read or
+    // write transaction in progress, as the coprocessor would.
+    fn mem_read(&mut self, addr: u32, into: &mut [u8]) -> Result<(), Error<RF::Error>> {
+        use OffsetAddr::*;
+        match self.offset_addr(addr) {
+            Main(offset) => result(self.main_ram.mm_read(offset, into)),
+            DisplayList(offset) => result(self.display_list_ram.mm_read(offset, into)),
+            Registers(offset) => {
+                for (i, chunk) in into.chunks_mut(4).enumerate() {
+                    result(self.registers.mm_read(offset + (i as u32) * 4, chunk))?;
+                }
+                Ok(())
+            }
+            Command(offset) => result(self.cmd_ram.mm_read(offset, into)),
+            CommandErr(offset) => result(self.coprocessor_err_mem().mm_read(offset, into)),
+            Unknown => Err(Error::UnmappedAddr),
+        }
+    }
+
+    // Writes memory at the given address without disturbing any read or
+    // write transaction in progress, as the coprocessor would.
+    fn mem_write(&mut self, addr: u32, data: &[u8]) -> Result<(), Error<RF::Error>> {
+        use OffsetAddr::*;
+        match self.offset_addr(addr) {
+            Main(offset) => result(self.main_ram.mm_write(offset, data)),
+            DisplayList(offset) => result(self.display_list_ram.mm_write(offset, data)),
+            Registers(offset) => {
+                for (i, chunk) in data.chunks(4).enumerate() {
+                    result(self.registers.mm_write(offset + (i as u32) * 4, chunk))?;
+                }
+                Ok(())
+            }
+            Command(offset) => result(self.cmd_ram.mm_write(offset, data)),
+            CommandErr(offset) => result(self.coprocessor_err_mem().mm_write(offset, data)),
+            Unknown => Err(Error::UnmappedAddr),
+        }
+    }
+
+    fn set_register(&mut self, reg: Register, v: u32) -> Result<(), Error<RF::Error>> {
+        self.registers
+            .write(reg, v)
+            .map_err(|err| Error::Registers(RegisterError::Hook(err)))
+    }
+
+    // Makes sure that the host side is using the same SPI width as
+    // REG_SPI_WIDTH selects, since otherwise the real chip would
+    // misinterpret the transaction.
+    fn check_spi_width(&self) -> Result<(), Error<RF::Error>> {
+        let chip = self.registers.internal_read(Register::SPI_WIDTH) & 0b111;
+        if chip != self.spi_width.reg_value() as u32 {
+            return Err(Error::SpiWidthMismatch {
+                host: self.spi_width,
+                chip: chip as u8,
+            });
+        }
+        Ok(())
+    }
+
+    fn offset_addr(&self, addr: u32) -> OffsetAddr {
         if M::MainMem::contains_addr(addr) {
             return OffsetAddr::Main(addr - M::MainMem::BASE_ADDR);
         }
@@ -171,10 +400,69 @@ impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
         if M::CommandMem::contains_addr(addr) {
             return OffsetAddr::Command(addr - M::CommandMem::BASE_ADDR);
         }
+        if let Some(offset) = self.coprocessor_err_offset(addr) {
+            return OffsetAddr::CommandErr(offset);
+        }
+        // A few registers, such as REG_COPRO_PATCH_PTR, are outside of the
+        // main register memory.
+        if let Some(offset) = addr.checked_sub(M::RegisterMem::BASE_ADDR) {
+            use core::convert::TryFrom;
+            if offset <= 0xffff && Register::try_from(offset as u16).is_ok() {
+                return OffsetAddr::Registers(offset);
+            }
+        }
         OffsetAddr::Unknown
     }
 }
 
+impl<'a, M: Model + WithCommandErrMem, RF: RegisterFile> Interface<'a, M, RF> {
+    /// Enables emulation of a subset of the EVE coprocessor.
+    ///
+    /// With the emulator enabled, words written to `REG_CMDB_WRITE` (or to
+    /// the command memory, followed by an update to `REG_CMD_WRITE`) are
+    /// placed in the fake command memory and then executed immediately,
+    /// updating `REG_CMD_READ`, `REG_CMD_WRITE` and `REG_CMDB_SPACE` in the
+    /// same way as the real coprocessor. The emulator requires a register
+    /// file and command memory, and also uses the display list memory and
+    /// main memory for commands that access them.
+    ///
+    /// The emulator supports display list commands and `CMD_DLSTART`,
+    /// `CMD_SWAP`, `CMD_APPEND`, `CMD_MEMCPY`, `CMD_MEMWRITE`, `CMD_MEMSET`,
+    /// `CMD_MEMCRC`, `CMD_REGREAD` and `CMD_INTERRUPT`. It ignores commands
+    /// that only wait or select settings, such as `CMD_WAIT` and
+    /// `CMD_APILEVEL`, because it executes everything instantly and has no
+    /// other state.
+    ///
+    /// Any other command, an unrecognized opcode, a display list overflow
+    /// or an access to unmapped memory causes a coprocessor fault, setting
+    /// `REG_CMD_READ` to `0xfff` and writing a message into the coprocessor
+    /// error memory, where
+    /// [`Coprocessor::coprocessor_fault_msg`](crate::commands::Coprocessor::coprocessor_fault_msg)
+    /// can find it. As with the real chip, a fault persists until the
+    /// coprocessor is reset using `REG_CPURESET`.
+    ///
+    /// The coprocessor emulator doesn't render any widgets, but you can
+    /// render the display lists it generates using
+    /// [`render_display_list`](Interface::render_display_list).
+    pub fn with_coprocessor(self) -> Self {
+        let err_base = <M::CommandErrMem as MemoryRegion>::BASE_ADDR;
+        Self {
+            main_ram: self.main_ram,
+            display_list_ram: self.display_list_ram,
+            registers: self.registers,
+            cmd_ram: self.cmd_ram,
+            coprocessor: Some(coprocessor::Emulator::new(err_base)),
+            host: self.host,
+            faults: self.faults,
+            spi_width: self.spi_width,
+            max_spi_width: self.max_spi_width,
+            write_addr: self.write_addr,
+            read_addr: self.read_addr,
+            _model: self._model,
+        }
+    }
+}
+
 impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
     type Error = Error<RF::Error>;
 
@@ -185,6 +473,11 @@ impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
         if let Some(_) = self.read_addr {
             return Err(Error::IncorrectSequence);
         }
+        self.faults_begin_transaction()?;
+        self.check_spi_width()?;
+        if self.host.is_some() {
+            self.host_simulated_transaction()?;
+        }
         self.write_addr = Some(addr);
         Ok(())
     }
@@ -192,7 +485,7 @@ impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
     fn continue_write(&mut self, data: &[u8]) -> core::result::Result<(), Self::Error> {
         if let Some(addr) = self.write_addr {
             use OffsetAddr::*;
-            match Self::offset_addr(addr) {
+            match self.offset_addr(addr) {
                 Main(offset) => {
                     let new_addr =
                         (<M as Model>::MainMem::ptr(offset) + data.len() as u32).to_raw();
@@ -205,13 +498,40 @@ impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
                     self.write_addr = Some(new_addr);
                     result(self.display_list_ram.mm_write(offset, data))
                 }
-                Registers(offset) => result(self.registers.mm_write(offset, data)),
+                Registers(offset) => {
+                    if offset == Register::CMDB_WRITE as u32 {
+                        // The real chip accepts a burst of several words
+                        // written to REG_CMDB_WRITE in a single transaction,
+                        // so we'll treat that as a sequence of separate
+                        // 32-bit writes.
+                        for chunk in data.chunks(4) {
+                            if self.coprocessor.is_some() {
+                                let mut word = [0; 4];
+                                word[..chunk.len()].copy_from_slice(chunk);
+                                self.coprocessor_push(u32::from_le_bytes(word))?;
+                            } else {
+                                result(self.registers.mm_write(offset, chunk))?;
+                            }
+                        }
+                        Ok(())
+                    } else {
+                        result(self.registers.mm_write(offset, data))?;
+                        if self.coprocessor.is_some() {
+                            self.coprocessor_register_written(offset)?;
+                        }
+                        Ok(())
+                    }
+                }
                 Command(offset) => {
                     let new_addr =
                         (<M as Model>::CommandMem::ptr(offset) + data.len() as u32).to_raw();
                     self.write_addr = Some(new_addr);
                     result(self.cmd_ram.mm_write(offset, data))
                 }
+                CommandErr(offset) => {
+                    self.write_addr = Some(addr + data.len() as u32);
+                    result(self.coprocessor_err_mem().mm_write(offset, data))
+                }
                 Unknown => Err(Error::UnmappedAddr),
             }
         } else {
@@ -235,6 +555,12 @@ impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
         if let Some(_) = self.read_addr {
             return Err(Error::IncorrectSequence);
         }
+        self.faults_begin_transaction()?;
+        self.faults_begin_read(addr)?;
+        self.check_spi_width()?;
+        if self.host.is_some() {
+            self.host_simulated_transaction()?;
+        }
         self.read_addr = Some(addr);
         Ok(())
     }
@@ -242,28 +568,36 @@ impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
     fn continue_read(&mut self, into: &mut [u8]) -> core::result::Result<(), Self::Error> {
         if let Some(addr) = self.read_addr {
             use OffsetAddr::*;
-            match Self::offset_addr(addr) {
+            let ret = match self.offset_addr(addr) {
                 Main(offset) => {
                     let new_addr =
                         (<M as Model>::MainMem::ptr(offset) + into.len() as u32).to_raw();
-                    self.write_addr = Some(new_addr);
+                    self.read_addr = Some(new_addr);
                     result(self.main_ram.mm_read(offset, into))
                 }
                 DisplayList(offset) => {
                     let new_addr =
                         (<M as Model>::DisplayListMem::ptr(offset) + into.len() as u32).to_raw();
-                    self.write_addr = Some(new_addr);
+                    self.read_addr = Some(new_addr);
                     result(self.display_list_ram.mm_read(offset, into))
                 }
                 Registers(offset) => result(self.registers.mm_read(offset, into)),
                 Command(offset) => {
                     let new_addr =
                         (<M as Model>::CommandMem::ptr(offset) + into.len() as u32).to_raw();
-                    self.write_addr = Some(new_addr);
+                    self.read_addr = Some(new_addr);
                     result(self.cmd_ram.mm_read(offset, into))
                 }
+                CommandErr(offset) => {
+                    self.read_addr = Some(addr + into.len() as u32);
+                    result(self.coprocessor_err_mem().mm_read(offset, into))
+                }
                 Unknown => Err(Error::UnmappedAddr),
+            };
+            if ret.is_ok() {
+                self.faults_filter_read(addr, into);
             }
+            ret
         } else {
             Err(Error::IncorrectSequence)
         }
@@ -278,10 +612,57 @@ impl<'a, M: Model, RF: RegisterFile> super::Interface for Interface<'a, M, RF> {
         }
     }
 
-    fn host_cmd(&mut self, _cmd: u8, _a0: u8, _a1: u8) -> core::result::Result<(), Self::Error> {
-        // For now the fake interface doesn't do anything with commands.
+    fn host_cmd(&mut self, cmd: u8, a0: u8, a1: u8) -> core::result::Result<(), Self::Error> {
+        self.faults_begin_transaction()?;
+        if self.host.is_some() {
+            return self.host_simulated_cmd(cmd, a0, a1);
+        }
+        // Without host simulation, the fake interface ignores commands.
         Ok(())
     }
+
+    fn reset(&mut self) -> core::result::Result<(), Self::Error> {
+        if self.host.is_some() {
+            self.host_simulated_reset();
+        }
+        // Resetting the chip returns both sides of the bus to single mode.
+        self.spi_width = super::SpiWidth::Single;
+        if !self.registers.is_present() {
+            return Ok(());
+        }
+        self.set_register(Register::SPI_WIDTH, 0)
+    }
+}
+
+impl<'a, M: Model, RF: RegisterFile> super::SpiWidthInterface for Interface<'a, M, RF> {
+    fn supports_spi_width(&self, width: super::SpiWidth) -> bool {
+        width <= self.max_spi_width
+    }
+
+    fn set_spi_width(&mut self, width: super::SpiWidth) -> core::result::Result<(), Self::Error> {
+        self.spi_width = width;
+        Ok(())
+    }
+}
+
+impl<'a, M: Model, RF: RegisterFile> super::AsyncInterface for Interface<'a, M, RF> {
+    type Error = Error<RF::Error>;
+
+    async fn write(&mut self, addr: u32, v: &[u8]) -> core::result::Result<(), Self::Error> {
+        super::Interface::write(self, addr, v)
+    }
+
+    async fn read(&mut self, addr: u32, into: &mut [u8]) -> core::result::Result<(), Self::Error> {
+        super::Interface::read(self, addr, into)
+    }
+
+    async fn host_cmd(&mut self, cmd: u8, a0: u8, a1: u8) -> core::result::Result<(), Self::Error> {
+        super::Interface::host_cmd(self, cmd, a0, a1)
+    }
+
+    async fn reset(&mut self) -> core::result::Result<(), Self::Error> {
+        super::Interface::reset(self)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -291,6 +672,7 @@ enum OffsetAddr {
     DisplayList(u32),
     Registers(u32),
     Command(u32),
+    CommandErr(u32),
 }
 
 #[derive(Debug)]
@@ -300,6 +682,27 @@ pub enum Error<RegError> {
     UnmappedAddr,
     Storage(SliceError),
     Registers(RegisterError<RegError>),
+
+    /// A host command that the chip doesn't accept in its current power
+    /// state, or an unrecognized host command.
+    InvalidHostCommand {
+        cmd: u8,
+        state: PowerState,
+    },
+
+    /// A memory access while the chip isn't in the active power state.
+    NotActive(PowerState),
+
+    /// A failure arranged using
+    /// [`Interface::fail_transaction`](Interface::fail_transaction).
+    Injected,
+
+    /// A transaction while `REG_SPI_WIDTH` selects a different width (or
+    /// the extra dummy byte) than the host side is using.
+    SpiWidthMismatch {
+        host: super::SpiWidth,
+        chip: u8,
+    },
 }
 
 impl<RegError> From<SliceError> for Error<RegError> {
@@ -350,6 +753,15 @@ pub trait RegisterFile {
     fn read(&mut self, reg: Register) -> Result<u32, Self::Error> {
         Ok(self.internal_read(reg))
     }
+
+    /// Returns false if this register file has no registers at all, in which
+    /// case the fake interface skips the register updates it would otherwise
+    /// make as a side-effect of other operations, such as a reset.
+    ///
+    /// The default implementation returns true.
+    fn is_present(&self) -> bool {
+        true
+    }
 }
 
 impl RegisterFile for &mut [u32] {
@@ -416,6 +828,10 @@ impl RegisterFile for NoRegisterFile {
     fn write(&mut self, _reg: Register, _v: u32) -> Result<(), Self::Error> {
         Err(())
     }
+
+    fn is_present(&self) -> bool {
+        false
+    }
 }
 
 trait MemoryMapped {
@@ -459,13 +875,21 @@ impl MemoryMapped for [u8] {
     }
 }
 
+// Returns true if the given register offset is suitably aligned for access.
+// Most registers are at four-byte boundaries, but a few 16-bit registers,
+// such as REG_COPRO_PATCH_PTR, are only two-byte aligned.
+fn register_aligned(offset: u32) -> bool {
+    use core::convert::TryFrom;
+    (offset % 4) == 0 || Register::try_from(offset as u16).is_ok()
+}
+
 impl<RF: RegisterFile> MemoryMapped for RF {
     type Error = RegisterError<RF::Error>;
 
     fn mm_read(&mut self, offset: u32, into: &mut [u8]) -> Result<(), Self::Error> {
         use core::convert::TryFrom;
         let len = into.len();
-        if (offset % 4) != 0 {
+        if !register_aligned(offset) {
             return Err(Self::Error::Unaligned);
         }
         if len > 4 {
@@ -488,7 +912,7 @@ impl<RF: RegisterFile> MemoryMapped for RF {
     fn mm_write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
         use core::convert::TryFrom;
         let len = data.len();
-        if (offset % 4) != 0 {
+        if !register_aligned(offset) {
             return Err(Self::Error::Unaligned);
         }
         if len > 4 {
@@ -536,3 +960,93 @@ pub enum RegisterError<RFErr> {
     Oversize,
     Hook(RFErr),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::SpiWidth;
+    use crate::models::fake::Model as FakeModel;
+    use crate::EVE;
+
+    #[test]
+    fn test_set_spi_width() {
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        eve.set_spi_width(SpiWidth::Quad).unwrap();
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::SPI_WIDTH)).unwrap(), 0b10);
+
+        eve.set_spi_width(SpiWidth::Single).unwrap();
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::SPI_WIDTH)).unwrap(), 0b00);
+    }
+
+    #[test]
+    fn test_set_spi_width_unsupported() {
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel)
+            .with_register_file(&mut regs[..])
+            .with_max_spi_width(SpiWidth::Dual);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        let result = eve.set_spi_width(SpiWidth::Quad);
+        assert!(
+            matches!(result, Err(crate::Error::Unsupported)),
+            "wrong result {:?}",
+            result
+        );
+        // The chip must still be in single mode, or this read would fail.
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::SPI_WIDTH)).unwrap(), 0b00);
+
+        eve.set_spi_width(SpiWidth::Dual).unwrap();
+    }
+
+    #[test]
+    fn test_reset_spi_width() {
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        eve.set_spi_width(SpiWidth::Quad).unwrap();
+        let ll = eve.borrow_low_level();
+        super::super::Interface::reset(ll.borrow_interface()).unwrap();
+
+        // The chip and the host must both be back in single mode, or this
+        // read would fail.
+        assert_eq!(ll.rd8(ll.reg_ptr(Register::SPI_WIDTH)).unwrap(), 0b00);
+    }
+
+    #[test]
+    fn test_reset_without_register_file() {
+        let mut main_ram = [0u8; 16];
+        let mut ei = Interface::new(FakeModel).with_main_ram(&mut main_ram[..]);
+        super::super::Interface::reset(&mut ei).unwrap();
+    }
+
+    #[test]
+    fn test_spi_width_mismatch() {
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel).with_register_file(&mut regs[..]);
+        let mut eve = EVE::new(FakeModel, ei);
+
+        // Switching the chip without also switching the host makes the
+        // next transaction fail.
+        let ll = eve.borrow_low_level();
+        ll.wr8(ll.reg_ptr(Register::SPI_WIDTH), 0b10).unwrap();
+        let result = ll.rd8(ll.reg_ptr(Register::ID));
+        assert!(
+            matches!(
+                result,
+                Err(crate::Error::Interface(Error::SpiWidthMismatch {
+                    host: SpiWidth::Single,
+                    chip: 0b10,
+                }))
+            ),
+            "wrong result {:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,670 @@
+//! A minimal emulation of the EVE coprocessor for the fake interface.
+//!
+//! The emulator executes commands as soon as they are complete in the
+//! command ring buffer, so from the host's perspective the coprocessor
+//! always appears to be idle except while it's waiting for the remainder
+//! of a partially-written command.
+
+use super::{Error, Interface, MemoryMapped, RegisterError, RegisterFile};
+use crate::commands::decode::{decode_commands, DecodedCommand};
+use crate::low_level::Register;
+use crate::memory::MemoryRegion;
+use crate::models::Model;
+
+// The largest ring buffer that the emulator supports, in words. All of the
+// EVE models so far have a 4KiB ring buffer.
+const MAX_RING_WORDS: usize = 1024;
+
+// The value of REG_CMD_READ that signals a coprocessor fault.
+const FAULT_READ_PTR: u32 = 0xfff;
+
+const CMD_MEMWRITE: u32 = 0xFFFFFF1A;
+const INT_CMDFLAG: u32 = 0x40;
+
+pub(super) struct Emulator {
+    err_base: u32,
+    err_mem: [u8; 128],
+
+    // The destination address and the number of bytes still to come for
+    // a CMD_MEMWRITE whose data hasn't all arrived yet. We consume
+    // CMD_MEMWRITE data progressively, as the real coprocessor does, so
+    // that it can write more data than would fit in the ring buffer.
+    mem_write: Option<(u32, u32)>,
+}
+
+impl Emulator {
+    pub(super) fn new(err_base: u32) -> Self {
+        Self {
+            err_base: err_base,
+            err_mem: [0; 128],
+            mem_write: None,
+        }
+    }
+}
+
+// The reasons the emulator might stop executing commands, other than
+// running out of commands to execute.
+enum Stop<RegError> {
+    Fault(Fault),
+    Interface(Error<RegError>),
+}
+
+impl<RegError> From<Fault> for Stop<RegError> {
+    fn from(fault: Fault) -> Self {
+        Stop::Fault(fault)
+    }
+}
+
+impl<RegError> From<Error<RegError>> for Stop<RegError> {
+    fn from(err: Error<RegError>) -> Self {
+        Stop::Interface(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Fault {
+    UnknownCommand(u32),
+    UnsupportedCommand(u32),
+    DisplayListOverflow,
+    InvalidAddress(u32),
+    UnalignedLength(u32),
+}
+
+impl core::fmt::Display for Fault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use Fault::*;
+        match self {
+            UnknownCommand(opcode) => write!(f, "unknown command {:#010x}", opcode),
+            UnsupportedCommand(opcode) => write!(
+                f,
+                "command {:#010x} is not supported by the fake coprocessor",
+                opcode
+            ),
+            DisplayListOverflow => write!(f, "display list overflow"),
+            InvalidAddress(addr) => write!(f, "invalid address {:#x}", addr),
+            UnalignedLength(num) => write!(f, "length {} is not a multiple of four", num),
+        }
+    }
+}
+
+impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
+    // Handles a word written to REG_CMDB_WRITE, by appending it to the ring
+    // buffer and then executing any commands that are now complete.
+    pub(super) fn coprocessor_push(&mut self, word: u32) -> Result<(), Error<RF::Error>> {
+        if self.coprocessor_halted() {
+            // The real coprocessor ignores writes while it's faulted or
+            // held in reset.
+            return Ok(());
+        }
+        let write = self.registers.internal_read(Register::CMD_WRITE);
+        let offset = write & Self::ring_mask();
+        super::result(self.cmd_ram.mm_write(offset, &word.to_le_bytes()))?;
+        self.set_register(Register::CMD_WRITE, (offset + 4) & Self::ring_mask())?;
+        self.coprocessor_run()
+    }
+
+    // Reacts to the host writing directly to one of the registers that
+    // controls the coprocessor.
+    pub(super) fn coprocessor_register_written(
+        &mut self,
+        offset: u32,
+    ) -> Result<(), Error<RF::Error>> {
+        if offset == Register::CPURESET as u32 {
+            if self.coprocessor_in_reset() {
+                return Ok(());
+            }
+            // Leaving reset abandons any partially-executed command. The
+            // host is responsible for resetting the ring buffer pointers.
+            self.coprocessor_mut().mem_write = None;
+            return self.coprocessor_run();
+        }
+        if offset == Register::CMD_WRITE as u32 || offset == Register::CMD_READ as u32 {
+            return self.coprocessor_run();
+        }
+        Ok(())
+    }
+
+    pub(super) fn coprocessor_err_offset(&self, addr: u32) -> Option<u32> {
+        let emu = self.coprocessor.as_ref()?;
+        let len = emu.err_mem.len() as u32;
+        if addr >= emu.err_base && addr < emu.err_base + len {
+            Some(addr - emu.err_base)
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn coprocessor_err_mem(&mut self) -> &mut [u8] {
+        match self.coprocessor {
+            Some(ref mut emu) => &mut emu.err_mem[..],
+            None => &mut [],
+        }
+    }
+
+    fn coprocessor_mut(&mut self) -> &mut Emulator {
+        self.coprocessor.as_mut().unwrap()
+    }
+
+    fn ring_mask() -> u32 {
+        M::CommandMem::LENGTH - 1
+    }
+
+    fn coprocessor_in_reset(&self) -> bool {
+        (self.registers.internal_read(Register::CPURESET) & 0b001) != 0
+    }
+
+    fn coprocessor_halted(&self) -> bool {
+        // A fault sets REG_CMD_READ to an unaligned value, which is also how
+        // the host detects it.
+        let read = self.registers.internal_read(Register::CMD_READ);
+        (read % 4) != 0 || self.coprocessor_in_reset()
+    }
+
+    // Executes commands from the ring buffer until it's either empty or
+    // contains only an incomplete command, and then updates REG_CMDB_SPACE
+    // to match.
+    fn coprocessor_run(&mut self) -> Result<(), Error<RF::Error>> {
+        let mut words = [0u32; MAX_RING_WORDS];
+        while !self.coprocessor_halted() {
+            let read = self.registers.internal_read(Register::CMD_READ);
+            let write = self.registers.internal_read(Register::CMD_WRITE);
+            let count = (write.wrapping_sub(read) & Self::ring_mask()) as usize / 4;
+            if count == 0 {
+                break;
+            }
+            for (i, word) in words[..count].iter_mut().enumerate() {
+                let offset = (read + (i as u32) * 4) & Self::ring_mask();
+                let mut buf = [0; 4];
+                super::result(self.cmd_ram.mm_read(offset, &mut buf))?;
+                *word = u32::from_le_bytes(buf);
+            }
+
+            match self.coprocessor_step(read, &words[..count]) {
+                Ok(Some(consumed)) => {
+                    let read = (read + (consumed as u32) * 4) & Self::ring_mask();
+                    self.set_register(Register::CMD_READ, read)?;
+                }
+                Ok(None) => break,
+                Err(Stop::Fault(fault)) => self.coprocessor_fault(fault)?,
+                Err(Stop::Interface(err)) => return Err(err),
+            }
+        }
+        self.coprocessor_update_space()
+    }
+
+    // Executes the command at the start of the given words, which begin at
+    // offset `read` in the ring buffer, and returns the number of words it
+    // consumed. Returns `None` if the command isn't complete yet.
+    fn coprocessor_step(
+        &mut self,
+        read: u32,
+        words: &[u32],
+    ) -> Result<Option<usize>, Stop<RF::Error>> {
+        if let Some((ptr, remain)) = self.coprocessor_mut().mem_write {
+            let count = core::cmp::min(words.len(), remain.div_ceil(4) as usize);
+            let mut written = 0;
+            for word in &words[..count] {
+                let len = core::cmp::min(remain - written, 4);
+                let bytes = word.to_le_bytes();
+                self.coprocessor_mem_write(ptr + written, &bytes[..len as usize])?;
+                written += len;
+            }
+            self.coprocessor_mut().mem_write = if written < remain {
+                Some((ptr + written, remain - written))
+            } else {
+                None
+            };
+            return Ok(Some(count));
+        }
+
+        if words[0] == CMD_MEMWRITE {
+            if words.len() < 3 {
+                return Ok(None);
+            }
+            let (ptr, num) = (words[1], words[2]);
+            if num > 0 {
+                self.coprocessor_mut().mem_write = Some((ptr, num));
+            }
+            return Ok(Some(3));
+        }
+
+        let mut cmds = decode_commands(words);
+        let cmd = match cmds.next() {
+            Some(cmd) => cmd,
+            None => return Ok(None),
+        };
+        let consumed = cmds.offset();
+        // Commands that produce a result do so by overwriting their final
+        // word in the ring buffer.
+        let result_offset = (read + (consumed as u32 - 1) * 4) & Self::ring_mask();
+
+        use DecodedCommand::*;
+        match cmd {
+            DisplayList(cmd) => self.coprocessor_dl_write(cmd.as_raw())?,
+            DLStart => self.set_register(Register::CMD_DL, 0)?,
+            Append { ptr, num } => {
+                if (num % 4) != 0 {
+                    return Err(Fault::UnalignedLength(num).into());
+                }
+                for i in (0..num).step_by(4) {
+                    let mut buf = [0; 4];
+                    self.coprocessor_mem_read(ptr + i, &mut buf)?;
+                    self.coprocessor_dl_write(u32::from_le_bytes(buf))?;
+                }
+            }
+            Interrupt { .. } => {
+                let flags = self.registers.internal_read(Register::INT_FLAGS);
+                self.set_register(Register::INT_FLAGS, flags | INT_CMDFLAG)?;
+            }
+            MemCpy { dest, src, num } => {
+                for i in (0..num).step_by(4) {
+                    let len = core::cmp::min(num - i, 4) as usize;
+                    let mut buf = [0; 4];
+                    self.coprocessor_mem_read(src + i, &mut buf[..len])?;
+                    self.coprocessor_mem_write(dest + i, &buf[..len])?;
+                }
+            }
+            MemCrc { ptr, num, .. } => {
+                let mut crc = 0xffffffff;
+                for i in (0..num).step_by(4) {
+                    let len = core::cmp::min(num - i, 4) as usize;
+                    let mut buf = [0; 4];
+                    self.coprocessor_mem_read(ptr + i, &mut buf[..len])?;
+                    crc = crc32_update(crc, &buf[..len]);
+                }
+                self.coprocessor_result(result_offset, !crc)?;
+            }
+            MemSet { ptr, value, num } => {
+                let buf = [value; 4];
+                for i in (0..num).step_by(4) {
+                    let len = core::cmp::min(num - i, 4) as usize;
+                    self.coprocessor_mem_write(ptr + i, &buf[..len])?;
+                }
+            }
+            // The real coprocessor also updates the touch transform matrix,
+            // but the emulator doesn't simulate the touch engine.
+            SetRotate { rotation } => self.set_register(Register::ROTATE, rotation & 0b111)?,
+            RegRead { ptr, .. } => {
+                let mut buf = [0; 4];
+                self.coprocessor_mem_read(ptr, &mut buf)?;
+                self.coprocessor_result(result_offset, u32::from_le_bytes(buf))?;
+            }
+            // The emulator executes everything instantly and has no other
+            // state, so these commands have nothing to do.
+            Swap | ColdStart | ApiLevel { .. } | Wait { .. } | WaitVideoScanout => {}
+            Unknown { opcode, .. } => return Err(Fault::UnknownCommand(opcode).into()),
+            Truncated(_) => return Ok(None),
+            _ => return Err(Fault::UnsupportedCommand(words[0]).into()),
+        }
+        Ok(Some(consumed))
+    }
+
+    fn coprocessor_dl_write(&mut self, word: u32) -> Result<(), Stop<RF::Error>> {
+        let offset = self.registers.internal_read(Register::CMD_DL);
+        if offset + 4 > M::DisplayListMem::LENGTH {
+            return Err(Fault::DisplayListOverflow.into());
+        }
+        let addr = M::DisplayListMem::BASE_ADDR + offset;
+        self.coprocessor_mem_write(addr, &word.to_le_bytes())?;
+        self.set_register(Register::CMD_DL, offset + 4)?;
+        Ok(())
+    }
+
+    fn coprocessor_mem_read(&mut self, addr: u32, into: &mut [u8]) -> Result<(), Stop<RF::Error>> {
+        self.mem_read(addr, into)
+            .map_err(|err| Self::coprocessor_mem_err(addr, err))
+    }
+
+    fn coprocessor_mem_write(&mut self, addr: u32, data: &[u8]) -> Result<(), Stop<RF::Error>> {
+        self.mem_write(addr, data)
+            .map_err(|err| Self::coprocessor_mem_err(addr, err))
+    }
+
+    // Errors from the register file's hooks are problems with the test
+    // harness, but any other memory access error is the coprocessor's
+    // problem and so becomes a fault.
+    fn coprocessor_mem_err(addr: u32, err: Error<RF::Error>) -> Stop<RF::Error> {
+        match err {
+            Error::Registers(RegisterError::Hook(_)) => Stop::Interface(err),
+            _ => Stop::Fault(Fault::InvalidAddress(addr)),
+        }
+    }
+
+    fn coprocessor_result(&mut self, offset: u32, v: u32) -> Result<(), Error<RF::Error>> {
+        super::result(self.cmd_ram.mm_write(offset, &v.to_le_bytes()))
+    }
+
+    fn coprocessor_fault(&mut self, fault: Fault) -> Result<(), Error<RF::Error>> {
+        use core::fmt::Write;
+
+        let emu = self.coprocessor_mut();
+        emu.mem_write = None;
+        emu.err_mem = [0; 128];
+        // We always leave at least one null byte at the end of the message.
+        let len = emu.err_mem.len() - 1;
+        let mut w = MessageWriter {
+            buf: &mut emu.err_mem[..len],
+            len: 0,
+        };
+        let _ = write!(w, "{}", fault);
+
+        self.set_register(Register::CMD_READ, FAULT_READ_PTR)
+    }
+
+    fn coprocessor_update_space(&mut self) -> Result<(), Error<RF::Error>> {
+        let read = self.registers.internal_read(Register::CMD_READ);
+        let write = self.registers.internal_read(Register::CMD_WRITE);
+        let space = read.wrapping_sub(write).wrapping_sub(4) & Self::ring_mask();
+        self.set_register(Register::CMDB_SPACE, space)
+    }
+}
+
+// Writes formatted text into a byte buffer, truncating it if it's too long.
+struct MessageWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> core::fmt::Write for MessageWriter<'a> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for b in s.bytes() {
+            if self.len < self.buf.len() {
+                self.buf[self.len] = b;
+                self.len += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The CRC-32 variant used by CMD_MEMCRC is the same one used by zlib.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::super::raster::Framebuffer;
+    use super::super::{Interface, RegisterFile};
+    use crate::commands::{Error, FaultKind};
+    use crate::display_list::{Builder, DLCmd};
+    use crate::graphics::{RGB, RGBA};
+    use crate::interface::fake::TestBuffers;
+    use crate::low_level::Register;
+    use crate::memory::MemoryRegion;
+    use crate::models::fake::{DisplayListMem, MainMem, Model as FakeModel};
+    use std::string::String;
+    use std::vec::Vec;
+
+    type TestInterface<'a> = Interface<'a, FakeModel, &'a mut [u32]>;
+
+    fn dl_words(ei: &TestInterface, count: usize) -> Vec<u32> {
+        ei.display_list_ram[..count * 4]
+            .chunks(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_display_list() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.new_display_list(|cp| {
+            cp.clear_color_rgb(RGB { r: 255, g: 0, b: 0 })?;
+            cp.clear_all()?;
+            cp.display()
+        })
+        .unwrap();
+        cp.block_until_idle().unwrap();
+        let ei = cp.take_interface().unwrap();
+
+        let regs = &ei.registers;
+        assert_eq!(regs.internal_read(Register::CMD_DL), 12);
+        assert_eq!(regs.internal_read(Register::CMD_READ), 20);
+        assert_eq!(regs.internal_read(Register::CMD_WRITE), 20);
+        assert_eq!(regs.internal_read(Register::CMDB_SPACE), 4092);
+        assert_eq!(
+            dl_words(&ei, 3),
+            &[
+                DLCmd::clear_color_rgb(RGB { r: 255, g: 0, b: 0 }).as_raw(),
+                DLCmd::CLEAR_ALL.as_raw(),
+                DLCmd::DISPLAY.as_raw(),
+            ]
+        );
+
+        let black = RGBA {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        };
+        let mut color = [black; 4];
+        let mut stencil = [0u8; 4];
+        let mut fb = Framebuffer::new(2, 2, &mut color, &mut stencil);
+        ei.render_display_list(&mut fb);
+        let got = fb.pixel(1, 1).unwrap();
+        assert_eq!((got.r, got.g, got.b), (255, 0, 0));
+    }
+
+    #[test]
+    fn test_memory_commands() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.write_memory(cp.ram_ptr(0x100), b"123456789").unwrap();
+        for word in &[
+            0xffffff1b, 0x200, 0xaa, 6, // CMD_MEMSET(0x200, 0xaa, 6)
+            0xffffff1d, 0x300, 0x100, 3, // CMD_MEMCPY(0x300, 0x100, 3)
+        ] {
+            cp.append_raw_word(*word).unwrap();
+        }
+        cp.write_register(Register::MACRO_0, 0x25000000).unwrap();
+        let crc = cp
+            .block_for_memory_crc(MainMem::ptr(0x100).slice_length(9))
+            .unwrap();
+        let reg = cp.block_read_register(Register::MACRO_0).unwrap();
+        let ei = cp.take_interface().unwrap();
+
+        assert_eq!(crc, 0xcbf43926);
+        assert_eq!(reg, 0x25000000);
+        assert_eq!(&ei.main_ram[0x200..0x207], b"\xaa\xaa\xaa\xaa\xaa\xaa\x00");
+        assert_eq!(&ei.main_ram[0x300..0x304], b"123\x00");
+    }
+
+    #[test]
+    fn test_memwrite_larger_than_ring() {
+        // The data for this write is larger than the whole ring buffer, so
+        // it only works if the emulator consumes it progressively.
+        let data: Vec<u8> = (0..6001).map(|i| (i % 251) as u8).collect();
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.write_memory(cp.ram_ptr(0x1000), &data[..]).unwrap();
+        cp.block_until_idle().unwrap();
+        let ei = cp.take_interface().unwrap();
+
+        assert_eq!(&ei.main_ram[0x1000..0x1000 + data.len()], &data[..]);
+        assert_eq!(ei.main_ram[0x1000 + data.len()], 0);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        let frag = cp
+            .capture_display_list_fragment(MainMem::ptr(0x400).slice_length(64), |cp| {
+                cp.clear_all()?;
+                cp.display()
+            })
+            .unwrap();
+        cp.start_display_list().unwrap();
+        cp.append_display_list_from_main_mem(frag).unwrap();
+        cp.append_display_list_from_main_mem(frag).unwrap();
+        cp.block_until_idle().unwrap();
+        let ei = cp.take_interface().unwrap();
+
+        assert_eq!(ei.registers.internal_read(Register::CMD_DL), 16);
+        assert_eq!(
+            dl_words(&ei, 4),
+            &[
+                DLCmd::CLEAR_ALL.as_raw(),
+                DLCmd::DISPLAY.as_raw(),
+                DLCmd::CLEAR_ALL.as_raw(),
+                DLCmd::DISPLAY.as_raw(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.trigger_cmdflag_interrupt(core::time::Duration::from_millis(0))
+            .unwrap();
+        cp.block_until_idle().unwrap();
+        let ei = cp.take_interface().unwrap();
+
+        assert_eq!(ei.registers.internal_read(Register::INT_FLAGS), 0x40);
+    }
+
+    #[test]
+    fn test_set_rotation() {
+        use crate::graphics::Rotation;
+
+        let mut bufs = TestBuffers::new();
+        bufs.regs[Register::HSIZE.index()] = 800;
+        bufs.regs[Register::VSIZE.index()] = 480;
+        let mut cp = bufs.coprocessor();
+        assert_eq!(cp.block_screen_size().unwrap(), (800, 480));
+
+        cp.set_rotation(Rotation::MirroredPortrait).unwrap();
+        assert_eq!(cp.block_screen_size().unwrap(), (480, 800));
+
+        let mut eve = cp.into_eve().unwrap();
+        assert_eq!(eve.screen_size().unwrap(), (480, 800));
+        let ei = eve.take_interface();
+        assert_eq!(ei.registers.internal_read(Register::ROTATE), 6);
+    }
+
+    #[test]
+    fn test_fault_unknown_command() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.append_raw_word(0xffffff99).unwrap();
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(matches!(err, Error::Fault), "wrong error {:?}", err);
+        let msg = cp.coprocessor_fault_msg().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(msg.as_bytes()),
+            "unknown command 0xffffff99"
+        );
+
+        // The fault persists until the coprocessor is reset.
+        let err = cp.append_display_list(DLCmd::DISPLAY).unwrap_err();
+        assert!(matches!(err, Error::Fault), "wrong error {:?}", err);
+        let ei = cp.take_interface().unwrap();
+        assert_eq!(ei.registers.internal_read(Register::CMD_READ), 0xfff);
+        assert_eq!(ei.registers.internal_read(Register::CMD_DL), 0);
+    }
+
+    #[test]
+    fn test_recover_from_fault() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.with_interface(|ei| {
+            ei.set_register(Register::COPRO_PATCH_PTR, 0x1234).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        cp.start_display_list().unwrap();
+        cp.append_display_list(DLCmd::NOP).unwrap();
+        cp.append_raw_word(0xffffff99).unwrap();
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(matches!(err, Error::Fault), "wrong error {:?}", err);
+
+        let msg = cp.recover_from_fault().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(msg.as_bytes()),
+            "unknown command 0xffffff99"
+        );
+
+        // The coprocessor is fully usable again, starting from the
+        // beginning of the ring buffer and the display list.
+        cp.start_display_list().unwrap();
+        cp.append_display_list(DLCmd::DISPLAY).unwrap();
+        cp.block_until_idle().unwrap();
+        assert_eq!(
+            cp.block_read_register(Register::COPRO_PATCH_PTR).unwrap(),
+            0x1234
+        );
+        let ei = cp.take_interface().unwrap();
+        assert_eq!(dl_words(&ei, 1), std::vec![DLCmd::DISPLAY.as_raw()]);
+        assert_eq!(ei.registers.internal_read(Register::CMD_DL), 4);
+        assert_eq!(ei.registers.internal_read(Register::CMDB_SPACE), 4092);
+    }
+
+    #[test]
+    fn test_recover_from_fault_clears_whole_registers() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.append_raw_word(0xffffff99).unwrap();
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(matches!(err, Error::Fault), "wrong error {:?}", err);
+
+        // Recovery must zero all 32 bits of each offset register, not just
+        // the low half.
+        cp.with_interface(|ei| {
+            ei.set_register(Register::CMD_DL, 0x10000).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        cp.recover_from_fault().unwrap();
+        assert_eq!(cp.block_read_register(Register::CMD_DL).unwrap(), 0);
+
+        // Commands sent afterwards run to completion.
+        cp.start_display_list().unwrap();
+        cp.append_display_list(DLCmd::clear_color_rgb(RGB { r: 1, g: 2, b: 3 }))
+            .unwrap();
+        cp.append_display_list(DLCmd::DISPLAY).unwrap();
+        cp.display_list_swap().unwrap();
+        cp.block_until_idle().unwrap();
+        let ei = cp.take_interface().unwrap();
+        assert_eq!(ei.registers.internal_read(Register::CMD_DL), 8);
+        assert_eq!(
+            ei.registers.internal_read(Register::CMD_READ),
+            ei.registers.internal_read(Register::CMD_WRITE)
+        );
+        assert_eq!(
+            dl_words(&ei, 2),
+            std::vec![
+                DLCmd::clear_color_rgb(RGB { r: 1, g: 2, b: 3 }).as_raw(),
+                DLCmd::DISPLAY.as_raw()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fault_display_list_overflow() {
+        let mut bufs = TestBuffers::new();
+        let mut cp = bufs.coprocessor();
+        cp.start_display_list().unwrap();
+        for _ in 0..=(DisplayListMem::LENGTH / 4) {
+            cp.append_display_list(DLCmd::NOP).unwrap();
+        }
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(matches!(err, Error::Fault), "wrong error {:?}", err);
+        let msg = cp.coprocessor_fault_msg().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(msg.as_bytes()),
+            "display list overflow"
+        );
+        assert_eq!(msg.kind(), FaultKind::DisplayListOverflow);
+    }
+}
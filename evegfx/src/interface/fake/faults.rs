@@ -0,0 +1,348 @@
+//! Fault injection for the fake interface.
+//!
+//! These methods allow tests to simulate communication glitches and
+//! coprocessor problems at specific points, in order to exercise an
+//! application's error handling and recovery.
+
+use super::{Error, Interface, RegisterFile};
+use crate::low_level::Register;
+use crate::models::Model;
+
+#[derive(Debug, Default)]
+pub(super) struct Faults {
+    // The number of transactions started so far.
+    transactions: u32,
+
+    // The index of the transaction that should fail, if any.
+    fail_at: Option<u32>,
+
+    // The index of the transaction whose read data should be corrupted, and
+    // the mask to XOR with each byte.
+    corrupt_at: Option<(u32, u8)>,
+
+    // Rules selecting a future read from a particular address to fail or
+    // to corrupt, with the mask to use for the latter.
+    fail_read: Option<AddrRule>,
+    corrupt_read: Option<(AddrRule, u8)>,
+
+    // The mask to XOR with the data read in the current transaction, if
+    // corrupt_read selected it.
+    corrupt_current: Option<u8>,
+
+    space_fault: bool,
+    space_delay: u32,
+}
+
+// Selects the `skip`th read from now that starts at address `addr`.
+#[derive(Debug, Clone, Copy)]
+struct AddrRule {
+    addr: u32,
+    skip: u32,
+}
+
+impl AddrRule {
+    // Returns true if the rule applies to a read starting at the given
+    // address, or otherwise counts the read towards the rule if it has the
+    // selected address.
+    fn matches(&mut self, addr: u32) -> bool {
+        if addr != self.addr {
+            return false;
+        }
+        if self.skip > 0 {
+            self.skip -= 1;
+            return false;
+        }
+        true
+    }
+}
+
+impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
+    /// Returns the number of transactions started so far.
+    ///
+    /// Each call to `begin_read`, `begin_write` or `host_cmd` counts as one
+    /// transaction, including those that fail.
+    pub fn transactions(&self) -> u32 {
+        self.faults.transactions
+    }
+
+    /// Arranges for the `n`th transaction from now to fail with
+    /// [`Error::Injected`], where zero means the very next transaction.
+    ///
+    /// A failed transaction has no other effect, as though the chip had
+    /// not seen it at all.
+    pub fn fail_transaction(&mut self, n: u32) {
+        self.faults.fail_at = Some(self.faults.transactions.wrapping_add(n));
+    }
+
+    /// Arranges for the data read during the `n`th transaction from now to
+    /// be corrupted by XORing each byte with the given mask, where zero
+    /// means the very next transaction.
+    pub fn corrupt_read(&mut self, n: u32, mask: u8) {
+        self.faults.corrupt_at = Some((self.faults.transactions.wrapping_add(n), mask));
+    }
+
+    /// Arranges for the `n`th read transaction from now that starts at the
+    /// given address to fail with [`Error::Injected`], where zero means the
+    /// next such read. Transactions involving other addresses don't count.
+    ///
+    /// This is useful for targeting a particular register, such as making
+    /// the coprocessor waiter's poll of `REG_CMDB_SPACE` fail, without
+    /// depending on exactly how many other transactions happen first.
+    pub fn fail_read_of(&mut self, addr: u32, n: u32) {
+        self.faults.fail_read = Some(AddrRule {
+            addr: addr,
+            skip: n,
+        });
+    }
+
+    /// Arranges for the data read during the `n`th read transaction from
+    /// now that starts at the given address to be corrupted by XORing each
+    /// byte with the given mask, where zero means the next such read.
+    pub fn corrupt_read_of(&mut self, addr: u32, n: u32, mask: u8) {
+        self.faults.corrupt_read = Some((
+            AddrRule {
+                addr: addr,
+                skip: n,
+            },
+            mask,
+        ));
+    }
+
+    /// Selects whether reads of `REG_CMDB_SPACE` should return an unaligned
+    /// value, which is how the coprocessor reports a fault.
+    ///
+    /// Unlike a real fault, this doesn't change the state of the
+    /// coprocessor emulator, if enabled, and so it lasts only until it's
+    /// turned off again.
+    pub fn set_coprocessor_space_fault(&mut self, fault: bool) {
+        self.faults.space_fault = fault;
+    }
+
+    /// Arranges for the next `reads` reads of `REG_CMDB_SPACE` to report
+    /// that there's no space available in the command ring buffer, as if the
+    /// coprocessor were busy.
+    pub fn delay_coprocessor_space(&mut self, reads: u32) {
+        self.faults.space_delay = reads;
+    }
+
+    /// Cancels any faults previously arranged using the other fault
+    /// injection methods.
+    pub fn clear_injected_faults(&mut self) {
+        let transactions = self.faults.transactions;
+        self.faults = Faults {
+            transactions: transactions,
+            ..Faults::default()
+        };
+    }
+
+    // Called at the start of each transaction, before taking any other
+    // action.
+    pub(super) fn faults_begin_transaction(&mut self) -> Result<(), Error<RF::Error>> {
+        let idx = self.faults.transactions;
+        self.faults.transactions = idx.wrapping_add(1);
+        self.faults.corrupt_current = None;
+        if self.faults.fail_at == Some(idx) {
+            self.faults.fail_at = None;
+            return Err(Error::Injected);
+        }
+        Ok(())
+    }
+
+    // Called at the start of each read transaction, after
+    // faults_begin_transaction.
+    pub(super) fn faults_begin_read(&mut self, addr: u32) -> Result<(), Error<RF::Error>> {
+        if let Some((rule, mask)) = self.faults.corrupt_read.as_mut() {
+            if rule.matches(addr) {
+                self.faults.corrupt_current = Some(*mask);
+                self.faults.corrupt_read = None;
+            }
+        }
+        if let Some(rule) = self.faults.fail_read.as_mut() {
+            if rule.matches(addr) {
+                self.faults.fail_read = None;
+                return Err(Error::Injected);
+            }
+        }
+        Ok(())
+    }
+
+    // Called after reading data in the current read transaction, to modify
+    // the data as necessary.
+    pub(super) fn faults_filter_read(&mut self, addr: u32, into: &mut [u8]) {
+        let current = self.faults.transactions.wrapping_sub(1);
+        if let Some((idx, mask)) = self.faults.corrupt_at {
+            if idx == current {
+                for b in into.iter_mut() {
+                    *b ^= mask;
+                }
+            }
+        }
+        if let Some(mask) = self.faults.corrupt_current {
+            for b in into.iter_mut() {
+                *b ^= mask;
+            }
+        }
+
+        if addr == M::reg_ptr(Register::CMDB_SPACE).to_raw() && !into.is_empty() {
+            if self.faults.space_delay > 0 {
+                self.faults.space_delay -= 1;
+                for b in into.iter_mut() {
+                    *b = 0;
+                }
+            }
+            if self.faults.space_fault {
+                into[0] |= 0b11;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::super::Interface;
+    use super::*;
+    use crate::commands::waiter::PollingWaiter;
+    use crate::commands::{Coprocessor, Error as CoprocessorError};
+    use crate::display_list::Builder;
+    use crate::models::fake::Model as FakeModel;
+    use crate::EVE;
+
+    type TestInterface<'a> = Interface<'a, FakeModel, &'a mut [u32]>;
+    type TestCoprocessor<'a> =
+        Coprocessor<FakeModel, TestInterface<'a>, PollingWaiter<FakeModel, TestInterface<'a>>>;
+
+    #[test]
+    fn test_fail_transaction() {
+        let mut regs = [0u32; 1024];
+        let mut eve = EVE::new(
+            FakeModel,
+            Interface::new(FakeModel).with_register_file(&mut regs[..]),
+        );
+        let ll = eve.borrow_low_level();
+        ll.wr32(ll.reg_ptr(Register::MACRO_0), 5).unwrap();
+
+        ll.borrow_interface().fail_transaction(1);
+        assert_eq!(ll.rd32(ll.reg_ptr(Register::MACRO_0)).unwrap(), 5);
+        assert!(matches!(
+            ll.rd32(ll.reg_ptr(Register::MACRO_0)),
+            Err(crate::Error::Interface(Error::Injected))
+        ));
+        assert_eq!(ll.rd32(ll.reg_ptr(Register::MACRO_0)).unwrap(), 5);
+        assert_eq!(ll.borrow_interface().transactions(), 4);
+    }
+
+    #[test]
+    fn test_corrupt_read() {
+        let mut regs = [0u32; 1024];
+        let mut eve = EVE::new(
+            FakeModel,
+            Interface::new(FakeModel).with_register_file(&mut regs[..]),
+        );
+        let ll = eve.borrow_low_level();
+        ll.wr32(ll.reg_ptr(Register::MACRO_0), 0x12345678).unwrap();
+
+        ll.borrow_interface().corrupt_read(0, 0xff);
+        assert_eq!(ll.rd32(ll.reg_ptr(Register::MACRO_0)).unwrap(), 0xedcba987);
+        assert_eq!(ll.rd32(ll.reg_ptr(Register::MACRO_0)).unwrap(), 0x12345678);
+    }
+
+    #[test]
+    fn test_read_of() {
+        let mut regs = [0u32; 1024];
+        let mut eve = EVE::new(
+            FakeModel,
+            Interface::new(FakeModel).with_register_file(&mut regs[..]),
+        );
+        let ll = eve.borrow_low_level();
+        let macro_0 = ll.reg_ptr(Register::MACRO_0);
+        let macro_1 = ll.reg_ptr(Register::MACRO_1);
+        ll.wr32(macro_0, 0x12345678).unwrap();
+
+        // Reads of other addresses don't count towards the rules.
+        ll.borrow_interface()
+            .corrupt_read_of(macro_0.to_raw(), 1, 0xff);
+        ll.borrow_interface().fail_read_of(macro_0.to_raw(), 2);
+        assert_eq!(ll.rd32(macro_1).unwrap(), 0);
+        assert_eq!(ll.rd32(macro_0).unwrap(), 0x12345678);
+        assert_eq!(ll.rd32(macro_1).unwrap(), 0);
+        assert_eq!(ll.rd32(macro_0).unwrap(), 0xedcba987);
+        assert!(matches!(
+            ll.rd32(macro_0),
+            Err(crate::Error::Interface(Error::Injected))
+        ));
+        assert_eq!(ll.rd32(macro_0).unwrap(), 0x12345678);
+    }
+
+    #[test]
+    fn test_coprocessor_recovery() {
+        let mut mem = [0u8; 1024];
+        let mut dl = [0u8; 8 * 1024];
+        let mut cmd = [0u8; 4 * 1024];
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel)
+            .with_main_ram(&mut mem[..])
+            .with_display_list_ram(&mut dl[..])
+            .with_cmd_ram(&mut cmd[..])
+            .with_register_file(&mut regs[..])
+            .with_coprocessor();
+        let mut cp: TestCoprocessor = Coprocessor::new_polling(ei).unwrap();
+
+        // A glitch while waiting is reported as a waiter error, but the
+        // coprocessor object remains usable afterwards. The first read of
+        // REG_CMDB_SPACE after the closure returns is with_interface
+        // resynchronizing, and the second is the waiter's poll.
+        cp.write_register(Register::MACRO_0, 7).unwrap();
+        cp.with_interface(|ei| {
+            ei.fail_read_of(FakeModel::reg_ptr(Register::CMDB_SPACE).to_raw(), 1);
+            Ok(())
+        })
+        .unwrap();
+        let err = cp.block_read_register(Register::MACRO_0).unwrap_err();
+        assert!(
+            matches!(err, CoprocessorError::Waiter(_)),
+            "wrong error {:?}",
+            err
+        );
+        assert_eq!(cp.block_read_register(Register::MACRO_0).unwrap(), 7);
+
+        // A reported fault is sticky for as long as the chip reports it.
+        cp.with_interface(|ei| {
+            ei.set_coprocessor_space_fault(true);
+            Ok(())
+        })
+        .unwrap();
+        cp.display().unwrap();
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(
+            matches!(err, CoprocessorError::Fault),
+            "wrong error {:?}",
+            err
+        );
+        let err = cp.block_until_idle().unwrap_err();
+        assert!(
+            matches!(err, CoprocessorError::Fault),
+            "wrong error {:?}",
+            err
+        );
+        cp.with_interface(|ei| {
+            ei.clear_injected_faults();
+            Ok(())
+        })
+        .unwrap();
+        cp.block_until_idle().unwrap();
+
+        // A busy coprocessor just makes the waiter poll for longer.
+        let before = cp
+            .with_interface(|ei| {
+                ei.delay_coprocessor_space(10);
+                Ok(ei.transactions())
+            })
+            .unwrap();
+        cp.display().unwrap();
+        cp.block_until_idle().unwrap();
+        let after = cp.with_interface(|ei| Ok(ei.transactions())).unwrap();
+        assert!(after - before > 10, "only {} transactions", after - before);
+    }
+}
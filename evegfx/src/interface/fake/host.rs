@@ -0,0 +1,248 @@
+//! Simulation of the EVE power states, host commands and system clock for
+//! the fake interface.
+
+use super::{Error, Interface, RegisterFile};
+use crate::low_level::{HostCmd, Register};
+use crate::models::Model;
+
+// The value of REG_ID once the chip has finished booting.
+const CHIP_ID: u32 = 0x7c;
+
+// The system clock frequency in Hz before any CLKSEL command.
+const DEFAULT_FREQUENCY: u32 = 60_000_000;
+
+// The default number of interface transactions the chip spends booting
+// after it first becomes active.
+const DEFAULT_BOOT_DELAY: u32 = 4;
+
+/// The power states of the simulated EVE chip.
+///
+/// See [`Interface::with_host_simulation`](super::Interface::with_host_simulation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    Active,
+    Standby,
+    Sleep,
+    PowerDown,
+}
+
+pub(super) struct HostState {
+    power: PowerState,
+    frequency: u32,
+
+    // The number of transactions remaining until the chip finishes booting,
+    // or `None` if the chip isn't booting.
+    booting: Option<u32>,
+    booted: bool,
+    boot_delay: u32,
+}
+
+impl HostState {
+    pub(super) fn new(boot_delay: u32) -> Self {
+        Self {
+            // The chip is in the SLEEP state after a power-on reset.
+            power: PowerState::Sleep,
+            frequency: DEFAULT_FREQUENCY,
+            booting: None,
+            booted: false,
+            boot_delay: boot_delay,
+        }
+    }
+
+    pub(super) fn default_boot_delay() -> u32 {
+        DEFAULT_BOOT_DELAY
+    }
+}
+
+impl<'a, M: Model, RF: RegisterFile> Interface<'a, M, RF> {
+    /// Returns the current power state of the simulated chip, or `None` if
+    /// host simulation isn't enabled.
+    pub fn power_state(&self) -> Option<PowerState> {
+        self.host.as_ref().map(|host| host.power)
+    }
+
+    pub(super) fn host_simulated_cmd(
+        &mut self,
+        cmd: u8,
+        a0: u8,
+        _a1: u8,
+    ) -> Result<(), Error<RF::Error>> {
+        use HostCmd::*;
+        use PowerState::*;
+
+        let host = self.host.as_mut().unwrap();
+        let state = host.power;
+        let invalid = Err(Error::InvalidHostCommand {
+            cmd: cmd,
+            state: state,
+        });
+        let cmd = match HostCmd::from_raw(cmd) {
+            Some(cmd) => cmd,
+            None => return invalid,
+        };
+        match (cmd, state) {
+            (ACTIVE, _) => {
+                host.power = Active;
+                if !host.booted && host.booting.is_none() {
+                    host.booting = Some(host.boot_delay);
+                }
+            }
+            (STANDBY, Active) | (STANDBY, Standby) => host.power = Standby,
+            (SLEEP, Active) | (SLEEP, Standby) | (SLEEP, Sleep) => host.power = Sleep,
+            (PWRDOWN, _) => {
+                // Powering down loses all of the chip's state.
+                *host = HostState::new(host.boot_delay);
+                host.power = PowerDown;
+            }
+            // The clock can only be reconfigured while the PLL is stopped.
+            (CLKEXT, Sleep) | (CLKINT, Sleep) => {}
+            (CLKSEL, Sleep) => {
+                host.frequency = match a0 & 0b00111111 {
+                    0 => DEFAULT_FREQUENCY,
+                    mult @ 2..=6 => (mult as u32) * 12_000_000,
+                    _ => return invalid,
+                };
+            }
+            (RST_PULSE, Active) => {
+                host.booted = false;
+                host.booting = Some(host.boot_delay);
+            }
+            (PINDRIVE, Sleep) | (PINDRIVE, Active) => {}
+            (PIN_PD_STATE, Sleep) | (PIN_PD_STATE, Active) => {}
+            _ => return invalid,
+        }
+        if self.host.as_ref().unwrap().booting.is_some() {
+            // The chip doesn't respond with its ID, and holds all of its
+            // engines in reset, until it has finished booting.
+            self.set_register(Register::ID, 0)?;
+            self.set_register(Register::CPURESET, 0b111)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn host_simulated_reset(&mut self) {
+        let host = self.host.as_mut().unwrap();
+        *host = HostState::new(host.boot_delay);
+    }
+
+    // Called at the start of each read or write transaction, to check that
+    // the chip can respond and to advance the simulated time.
+    pub(super) fn host_simulated_transaction(&mut self) -> Result<(), Error<RF::Error>> {
+        let host = self.host.as_mut().unwrap();
+        if host.power != PowerState::Active {
+            return Err(Error::NotActive(host.power));
+        }
+
+        if let Some(remain) = host.booting {
+            if remain > 0 {
+                host.booting = Some(remain - 1);
+                return Ok(());
+            }
+            host.booting = None;
+            host.booted = true;
+            let frequency = host.frequency;
+            self.set_register(Register::FREQUENCY, frequency)?;
+            self.set_register(Register::CLOCK, 0)?;
+            self.set_register(Register::CPURESET, 0)?;
+            return self.set_register(Register::ID, CHIP_ID);
+        }
+
+        // We pretend that each transaction takes one microsecond.
+        let cycles = self.registers.internal_read(Register::FREQUENCY) / 1_000_000;
+        let clock = self.registers.internal_read(Register::CLOCK);
+        self.set_register(Register::CLOCK, clock.wrapping_add(cycles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Interface;
+    use super::*;
+    use crate::config::{ClockFrequency, ClockSource, VideoTimings};
+    use crate::models::fake::Model as FakeModel;
+    use crate::EVE;
+
+    #[test]
+    fn test_start_system_clock() {
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel)
+            .with_register_file(&mut regs[..])
+            .with_host_simulation();
+        assert_eq!(ei.power_state(), Some(PowerState::Sleep));
+        let mut eve = EVE::new(FakeModel, ei);
+
+        let timings = VideoTimings {
+            sysclk_freq: ClockFrequency::F48MHz,
+            ..VideoTimings::MODE_720P
+        };
+        eve.start_system_clock(ClockSource::Internal, &timings)
+            .unwrap();
+        assert_eq!(
+            eve.borrow_interface().power_state(),
+            Some(PowerState::Active)
+        );
+        assert!(eve.poll_for_boot(100).unwrap());
+
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd32(ll.reg_ptr(Register::ID)).unwrap(), CHIP_ID);
+        assert_eq!(
+            ll.rd32(ll.reg_ptr(Register::FREQUENCY)).unwrap(),
+            48_000_000
+        );
+        let before = ll.rd32(ll.reg_ptr(Register::CLOCK)).unwrap();
+        let after = ll.rd32(ll.reg_ptr(Register::CLOCK)).unwrap();
+        assert_eq!(after - before, 48);
+    }
+
+    #[test]
+    fn test_boot_delay() {
+        let mut regs = [0u32; 1024];
+        let ei = Interface::new(FakeModel)
+            .with_register_file(&mut regs[..])
+            .with_host_simulation();
+        let mut eve = EVE::new(FakeModel, ei);
+        eve.borrow_low_level()
+            .host_command(HostCmd::ACTIVE, 0, 0)
+            .unwrap();
+
+        // Boot takes longer than one poll.
+        assert!(!eve.poll_for_boot(1).unwrap());
+        assert!(eve.poll_for_boot(100).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_states() {
+        let mut regs = [0u32; 1024];
+        let mut ei = Interface::new(FakeModel)
+            .with_register_file(&mut regs[..])
+            .with_host_simulation();
+        use crate::interface::Interface as _;
+
+        // Memory isn't accessible until the chip is active.
+        assert!(matches!(
+            ei.read(0, &mut [0; 4]),
+            Err(Error::NotActive(PowerState::Sleep))
+        ));
+
+        ei.host_cmd(HostCmd::ACTIVE.to_raw(), 0, 0).unwrap();
+        assert!(matches!(
+            ei.host_cmd(HostCmd::CLKSEL.to_raw(), 4, 0),
+            Err(Error::InvalidHostCommand {
+                cmd: 0x61,
+                state: PowerState::Active
+            })
+        ));
+        ei.host_cmd(HostCmd::PWRDOWN.to_raw(), 0, 0).unwrap();
+        assert!(matches!(
+            ei.host_cmd(HostCmd::RST_PULSE.to_raw(), 0, 0),
+            Err(Error::InvalidHostCommand {
+                cmd: 0x68,
+                state: PowerState::PowerDown
+            })
+        ));
+        assert!(matches!(
+            ei.host_cmd(0x99, 0, 0),
+            Err(Error::InvalidHostCommand { cmd: 0x99, .. })
+        ));
+    }
+}
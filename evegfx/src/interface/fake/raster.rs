@@ -0,0 +1,1343 @@
+//! A software reference rasterizer for display lists.
+//!
+//! This is a pure-Rust approximation of the EVE graphics engine, intended for
+//! testing code that generates display lists without needing real hardware.
+//! It interprets the display list commands in display list memory, fetching
+//! any bitmap data from main memory, and renders the result into a
+//! caller-provided RGBA framebuffer. Tests can then compare the rendered
+//! pixels against known-good "golden" images.
+//!
+//! The rasterizer supports all of the graphics primitives, the base bitmap
+//! formats (including the paletted ones), bitmap transforms, blending,
+//! scissor, stencil and alpha tests, color masking, graphics context
+//! save/restore, and display list calls, jumps and macros.
+//!
+//! It is not pixel-exact with real EVE hardware. In particular, it doesn't
+//! do any anti-aliasing: a pixel is either fully covered by a shape or not
+//! covered at all, depending on whether its center lies strictly inside the
+//! shape. Pixel centers are at whole-pixel coordinates, so for example a
+//! rectangle between `VERTEX2II(1, 1)` and `VERTEX2II(3, 2)` with the
+//! default line width covers exactly the pixels from (1, 1) to (3, 2).
+//! Bilinear filtering is approximated using nearest-neighbor sampling, and
+//! the text, bargraph and ASTC formats render nothing.
+
+use crate::display_list::options;
+use crate::display_list::{DLCmd, DecodedCmd};
+use crate::graphics::RGBA;
+
+/// An RGBA framebuffer that the rasterizer renders into.
+///
+/// The framebuffer borrows caller-provided buffers for its color and
+/// stencil planes, so that it can be used without dynamic allocation.
+pub struct Framebuffer<'a> {
+    width: u16,
+    height: u16,
+    color: &'a mut [RGBA],
+    stencil: &'a mut [u8],
+}
+
+impl<'a> Framebuffer<'a> {
+    /// Creates a framebuffer of the given size, using the given buffers
+    /// to store its color and stencil planes.
+    ///
+    /// Both buffers must have at least `width * height` elements, or this
+    /// function will panic. Pixels are stored in row-major order.
+    pub fn new(width: u16, height: u16, color: &'a mut [RGBA], stencil: &'a mut [u8]) -> Self {
+        let len = width as usize * height as usize;
+        assert!(color.len() >= len, "color buffer is too small");
+        assert!(stencil.len() >= len, "stencil buffer is too small");
+        Self {
+            width: width,
+            height: height,
+            color: &mut color[..len],
+            stencil: &mut stencil[..len],
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns the color of the pixel at the given position, or `None` if
+    /// the position is outside of the framebuffer.
+    pub fn pixel(&self, x: u16, y: u16) -> Option<RGBA> {
+        self.index(x as i32, y as i32).map(|i| self.color[i])
+    }
+
+    /// Returns the stencil value of the pixel at the given position, or
+    /// `None` if the position is outside of the framebuffer.
+    pub fn stencil(&self, x: u16, y: u16) -> Option<u8> {
+        self.index(x as i32, y as i32).map(|i| self.stencil[i])
+    }
+
+    /// Returns all of the pixels in the framebuffer, in row-major order.
+    pub fn pixels(&self) -> &[RGBA] {
+        self.color
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some(y as usize * self.width as usize + x as usize)
+    }
+
+    fn reset(&mut self) {
+        for v in self.color.iter_mut() {
+            *v = BLACK;
+        }
+        for v in self.stencil.iter_mut() {
+            *v = 0;
+        }
+    }
+}
+
+/// Renders the display list at the start of `display_list` into the given
+/// framebuffer, replacing its previous contents.
+///
+/// `main_mem` is the content of main memory (`RAM_G`), starting at address
+/// zero, from which the rasterizer will fetch bitmap and palette data.
+/// `macros` are the values of the `REG_MACRO_0` and `REG_MACRO_1`
+/// registers, which the `MACRO` command refers to.
+///
+/// Rendering stops at the first `DISPLAY` command, at the end of
+/// `display_list`, or after an implementation-defined number of commands
+/// in case the display list contains an infinite loop.
+pub fn render(display_list: &[u8], main_mem: &[u8], macros: [u32; 2], target: &mut Framebuffer) {
+    let mut r = Rasterizer {
+        target: target,
+        main_mem: main_mem,
+        ctx: Context::default(),
+        saved: [Context::default(); CONTEXT_STACK_DEPTH],
+        saved_len: 0,
+        bitmaps: [Bitmap::default(); BITMAP_HANDLES],
+        prim: None,
+        prev_vertex: None,
+    };
+    r.target.reset();
+
+    let mut call_stack = [0usize; CALL_STACK_DEPTH];
+    let mut call_depth = 0;
+    let mut pc = 0usize;
+    for _ in 0..MAX_STEPS {
+        let raw = match read_u32(display_list, pc * 4) {
+            Some(v) => v,
+            None => return,
+        };
+        pc += 1;
+
+        let cmd = match DLCmd::from_raw(raw).decode() {
+            DecodedCmd::Macro(n) => DLCmd::from_raw(macros[n as usize & 1]).decode(),
+            cmd => cmd,
+        };
+        match cmd {
+            DecodedCmd::Display => return,
+            DecodedCmd::Jump(dest) => pc = dest as usize / 4,
+            DecodedCmd::Call(dest) => {
+                if call_depth >= CALL_STACK_DEPTH {
+                    return;
+                }
+                call_stack[call_depth] = pc;
+                call_depth += 1;
+                pc = dest as usize / 4;
+            }
+            DecodedCmd::Return => {
+                if call_depth == 0 {
+                    return;
+                }
+                call_depth -= 1;
+                pc = call_stack[call_depth];
+            }
+            cmd => r.exec(cmd),
+        }
+    }
+}
+
+const BLACK: RGBA = RGBA {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0,
+};
+
+const BITMAP_HANDLES: usize = 32;
+const CONTEXT_STACK_DEPTH: usize = 4;
+const CALL_STACK_DEPTH: usize = 4;
+const MAX_STEPS: usize = 65536;
+
+// Fixed-point positions are in 1/16 pixel units throughout, matching the
+// maximum precision of VERTEX2F.
+const SUBPIXELS: i32 = 16;
+
+/// The subset of the graphics state that SAVE_CONTEXT and RESTORE_CONTEXT
+/// apply to.
+#[derive(Clone, Copy)]
+struct Context {
+    color: RGBA,
+    clear_color: RGBA,
+    clear_stencil: u8,
+    alpha_func: options::TestFunc,
+    alpha_ref: u8,
+    blend_src: options::BlendFunc,
+    blend_dst: options::BlendFunc,
+    color_mask: u8,
+    stencil_func: options::TestFunc,
+    stencil_ref: u8,
+    stencil_test_mask: u8,
+    stencil_write_mask: u8,
+    stencil_fail: options::StencilOp,
+    stencil_pass: options::StencilOp,
+    line_width: i32,
+    point_size: i32,
+    scissor: (i32, i32, i32, i32),
+    bitmap_handle: u8,
+    cell: u8,
+    transform: [i64; 6],
+    palette_source: u32,
+    vertex_format: u8,
+    translate: (i32, i32),
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            color: RGBA {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 255,
+            },
+            clear_color: BLACK,
+            clear_stencil: 0,
+            alpha_func: options::TestFunc::Always,
+            alpha_ref: 0,
+            blend_src: options::BlendFunc::SrcAlpha,
+            blend_dst: options::BlendFunc::OneMinusSrcAlpha,
+            color_mask: 0b1111,
+            stencil_func: options::TestFunc::Always,
+            stencil_ref: 0,
+            stencil_test_mask: 255,
+            stencil_write_mask: 255,
+            stencil_fail: options::StencilOp::Keep,
+            stencil_pass: options::StencilOp::Keep,
+            line_width: 16,
+            point_size: 16,
+            scissor: (0, 0, 2048, 2048),
+            bitmap_handle: 0,
+            cell: 0,
+            transform: [FIXED_ONE, 0, 0, 0, FIXED_ONE, 0],
+            palette_source: 0,
+            vertex_format: 4,
+            translate: (0, 0),
+        }
+    }
+}
+
+/// The per-handle bitmap state, which isn't part of the graphics context.
+#[derive(Clone, Copy)]
+struct Bitmap {
+    source: u32,
+    format: options::BitmapFormat,
+    ext_format: options::BitmapExtFormat,
+    line_stride: u32,
+    layout_height: u32,
+    width: u32,
+    height: u32,
+    wrap_x: options::BitmapWrapMode,
+    wrap_y: options::BitmapWrapMode,
+}
+
+impl Default for Bitmap {
+    fn default() -> Self {
+        Self {
+            source: 0,
+            format: options::BitmapFormat::ARGB1555,
+            ext_format: options::BitmapExtFormat::ARGB1555,
+            line_stride: 0,
+            layout_height: 0,
+            width: 0,
+            height: 0,
+            wrap_x: options::BitmapWrapMode::Border,
+            wrap_y: options::BitmapWrapMode::Border,
+        }
+    }
+}
+
+impl Bitmap {
+    fn effective_format(&self) -> Option<options::BitmapFormat> {
+        use options::BitmapExtFormat as E;
+        use options::BitmapFormat as F;
+        if self.format != F::GLFormat {
+            return Some(self.format);
+        }
+        Some(match self.ext_format {
+            E::ARGB1555 => F::ARGB1555,
+            E::L1 => F::L1,
+            E::L4 => F::L4,
+            E::L8 => F::L8,
+            E::RGB332 => F::RGB332,
+            E::ARGB2 => F::ARGB2,
+            E::ARGB4 => F::ARGB4,
+            E::RGB565 => F::RGB565,
+            E::Text8x8 => F::Text8x8,
+            E::TextVGA => F::TextVGA,
+            E::Bargraph => F::Bargraph,
+            E::Paletted565 => F::Paletted565,
+            E::Paletted4444 => F::Paletted4444,
+            E::Paletted8 => F::Paletted8,
+            E::L2 => F::L2,
+            _ => return None,
+        })
+    }
+}
+
+// Bitmap transform coefficients are stored as signed 16.16 fixed-point.
+const FIXED_ONE: i64 = 1 << 16;
+
+fn coeff_fixed(coeff: options::MatrixCoeff) -> i64 {
+    let v = coeff.to_raw_value() as i64;
+    if coeff.is_1_15() {
+        v << 1
+    } else {
+        v << 8
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Vertex {
+    x: i32,
+    y: i32,
+    handle: u8,
+    cell: u8,
+}
+
+struct Rasterizer<'a, 'b, 'c> {
+    target: &'a mut Framebuffer<'b>,
+    main_mem: &'c [u8],
+    ctx: Context,
+    saved: [Context; CONTEXT_STACK_DEPTH],
+    saved_len: usize,
+    bitmaps: [Bitmap; BITMAP_HANDLES],
+    prim: Option<options::GraphicsPrimitive>,
+    prev_vertex: Option<Vertex>,
+}
+
+impl<'a, 'b, 'c> Rasterizer<'a, 'b, 'c> {
+    fn exec(&mut self, cmd: DecodedCmd) {
+        use DecodedCmd::*;
+        let handle = self.ctx.bitmap_handle as usize % BITMAP_HANDLES;
+        match cmd {
+            AlphaFunc { func, ref_val } => {
+                self.ctx.alpha_func = func;
+                self.ctx.alpha_ref = ref_val;
+            }
+            Begin(prim) => {
+                self.prim = Some(prim);
+                self.prev_vertex = None;
+            }
+            BitmapExtFormat(format) => self.bitmaps[handle].ext_format = format,
+            BitmapHandle(bmp) => self.ctx.bitmap_handle = u8::from(bmp),
+            BitmapLayout {
+                format,
+                line_stride,
+                height,
+            } => {
+                let bmp = &mut self.bitmaps[handle];
+                bmp.format = format;
+                bmp.line_stride = (bmp.line_stride & !0x3ff) | line_stride as u32;
+                bmp.layout_height = (bmp.layout_height & !0x1ff) | height as u32;
+            }
+            BitmapLayoutH {
+                line_stride,
+                height,
+            } => {
+                let bmp = &mut self.bitmaps[handle];
+                bmp.line_stride = (bmp.line_stride & 0x3ff) | (line_stride as u32) << 10;
+                bmp.layout_height = (bmp.layout_height & 0x1ff) | (height as u32) << 9;
+            }
+            BitmapSize {
+                wrap_x,
+                wrap_y,
+                width,
+                height,
+                ..
+            } => {
+                let bmp = &mut self.bitmaps[handle];
+                bmp.wrap_x = wrap_x;
+                bmp.wrap_y = wrap_y;
+                bmp.width = (bmp.width & !0x1ff) | width as u32;
+                bmp.height = (bmp.height & !0x1ff) | height as u32;
+            }
+            BitmapSizeH { width, height } => {
+                let bmp = &mut self.bitmaps[handle];
+                bmp.width = (bmp.width & 0x1ff) | (width as u32) << 9;
+                bmp.height = (bmp.height & 0x1ff) | (height as u32) << 9;
+            }
+            BitmapSource(addr) => self.bitmaps[handle].source = addr,
+            BitmapSwizzle(_) => {}
+            BitmapTransformA(coeff) => self.ctx.transform[0] = coeff_fixed(coeff),
+            BitmapTransformB(coeff) => self.ctx.transform[1] = coeff_fixed(coeff),
+            BitmapTransformC(coeff) => self.ctx.transform[2] = coeff_fixed(coeff),
+            BitmapTransformD(coeff) => self.ctx.transform[3] = coeff_fixed(coeff),
+            BitmapTransformE(coeff) => self.ctx.transform[4] = coeff_fixed(coeff),
+            BitmapTransformF(coeff) => self.ctx.transform[5] = coeff_fixed(coeff),
+            BlendFunc { src, dst } => {
+                self.ctx.blend_src = src;
+                self.ctx.blend_dst = dst;
+            }
+            Cell(idx) => self.ctx.cell = idx,
+            Clear { color, stencil, .. } => self.clear(color, stencil),
+            ClearColorA(alpha) => self.ctx.clear_color.a = alpha,
+            ClearColorRGB(c) => {
+                self.ctx.clear_color.r = c.r;
+                self.ctx.clear_color.g = c.g;
+                self.ctx.clear_color.b = c.b;
+            }
+            ClearStencil(v) => self.ctx.clear_stencil = v,
+            ColorA(alpha) => self.ctx.color.a = alpha,
+            ColorMask(mask) => self.ctx.color_mask = mask.to_raw(),
+            ColorRGB(c) => {
+                self.ctx.color.r = c.r;
+                self.ctx.color.g = c.g;
+                self.ctx.color.b = c.b;
+            }
+            End => {
+                self.prim = None;
+                self.prev_vertex = None;
+            }
+            LineWidth(w) => self.ctx.line_width = w as i32,
+            PaletteSource(addr) => self.ctx.palette_source = addr,
+            PointSize(size) => self.ctx.point_size = size as i32,
+            RestoreContext => {
+                if self.saved_len > 0 {
+                    self.saved_len -= 1;
+                    self.ctx = self.saved[self.saved_len];
+                }
+            }
+            SaveContext => {
+                if self.saved_len < CONTEXT_STACK_DEPTH {
+                    self.saved[self.saved_len] = self.ctx;
+                    self.saved_len += 1;
+                }
+            }
+            ScissorSize { width, height } => {
+                self.ctx.scissor.2 = width as i32;
+                self.ctx.scissor.3 = height as i32;
+            }
+            ScissorXY { x, y } => {
+                self.ctx.scissor.0 = x as i32;
+                self.ctx.scissor.1 = y as i32;
+            }
+            StencilFunc {
+                func,
+                ref_val,
+                mask,
+            } => {
+                self.ctx.stencil_func = func;
+                self.ctx.stencil_ref = ref_val;
+                self.ctx.stencil_test_mask = mask;
+            }
+            StencilMask(mask) => self.ctx.stencil_write_mask = mask,
+            StencilOp { fail, pass } => {
+                self.ctx.stencil_fail = fail;
+                self.ctx.stencil_pass = pass;
+            }
+            Vertex2F { x, y } => {
+                let shift = 4 - self.ctx.vertex_format.min(4) as i32;
+                self.vertex(Vertex {
+                    x: ((x as i32) << shift) + self.ctx.translate.0,
+                    y: ((y as i32) << shift) + self.ctx.translate.1,
+                    handle: self.ctx.bitmap_handle,
+                    cell: self.ctx.cell,
+                });
+            }
+            Vertex2II { x, y, handle, cell } => self.vertex(Vertex {
+                x: x as i32 * SUBPIXELS + self.ctx.translate.0,
+                y: y as i32 * SUBPIXELS + self.ctx.translate.1,
+                handle: handle,
+                cell: cell,
+            }),
+            VertexFormat(fmt) => self.ctx.vertex_format = fmt.to_raw(),
+            VertexTranslateX(v) => self.ctx.translate.0 = v as i32,
+            VertexTranslateY(v) => self.ctx.translate.1 = v as i32,
+
+            // Tags don't affect the rendered image, and the control flow
+            // commands are handled by the caller.
+            ClearTag(_) | Tag(_) | TagMask(_) => {}
+            Call(_) | Display | Jump(_) | Macro(_) | Nop | Return | Unknown(_) => {}
+        }
+    }
+
+    fn vertex(&mut self, v: Vertex) {
+        use options::GraphicsPrimitive::*;
+        let prim = match self.prim {
+            Some(prim) => prim,
+            None => return,
+        };
+        let prev = self.prev_vertex;
+        match prim {
+            Bitmaps => self.draw_bitmap(v),
+            Points => {
+                let r = self.ctx.point_size;
+                self.fill_shape((v.x - r, v.y - r, v.x + r, v.y + r), |px, py| {
+                    dist2_point(px, py, v.x, v.y) < sq(r)
+                });
+            }
+            Lines | Rects => {
+                // These primitives use their vertices in pairs, so the
+                // second vertex of each pair resets for the next one.
+                if let Some(a) = prev {
+                    if prim == Lines {
+                        self.draw_line(a, v);
+                    } else {
+                        self.draw_rect(a, v);
+                    }
+                    self.prev_vertex = None;
+                    return;
+                }
+            }
+            LineStrip => {
+                if let Some(a) = prev {
+                    self.draw_line(a, v);
+                }
+            }
+            EdgeStripR | EdgeStripL | EdgeStripA | EdgeStripB => {
+                if let Some(a) = prev {
+                    self.draw_edge(prim, a, v);
+                }
+            }
+        }
+        self.prev_vertex = Some(v);
+    }
+
+    fn draw_line(&mut self, a: Vertex, b: Vertex) {
+        let r = self.ctx.line_width;
+        let bounds = (
+            a.x.min(b.x) - r,
+            a.y.min(b.y) - r,
+            a.x.max(b.x) + r,
+            a.y.max(b.y) + r,
+        );
+        self.fill_shape(bounds, |px, py| {
+            dist2_segment_lt(px, py, (a.x, a.y), (b.x, b.y), sq(r))
+        });
+    }
+
+    fn draw_rect(&mut self, a: Vertex, b: Vertex) {
+        let r = self.ctx.line_width;
+        let (x0, x1) = (a.x.min(b.x), a.x.max(b.x));
+        let (y0, y1) = (a.y.min(b.y), a.y.max(b.y));
+        self.fill_shape((x0 - r, y0 - r, x1 + r, y1 + r), |px, py| {
+            let dx = (x0 - px).max(0).max(px - x1) as i64;
+            let dy = (y0 - py).max(0).max(py - y1) as i64;
+            dx * dx + dy * dy < sq(r)
+        });
+    }
+
+    fn draw_edge(&mut self, prim: options::GraphicsPrimitive, a: Vertex, b: Vertex) {
+        use options::GraphicsPrimitive::*;
+        let max = 2048 * SUBPIXELS;
+        let (ax, ay, bx, by) = (a.x as i64, a.y as i64, b.x as i64, b.y as i64);
+        match prim {
+            EdgeStripR | EdgeStripL => {
+                let (y0, y1) = (a.y.min(b.y), a.y.max(b.y));
+                let right = prim == EdgeStripR;
+                let bounds = if right {
+                    (a.x.min(b.x), y0, max, y1)
+                } else {
+                    (0, y0, a.x.max(b.x), y1)
+                };
+                self.fill_shape(bounds, |px, py| {
+                    if py < y0 || py >= y1 {
+                        return false;
+                    }
+                    // Compare the pixel center with the point on the edge
+                    // at the same height, scaled by the edge's height to
+                    // avoid division.
+                    let mut lhs = (px as i64 - ax) * (by - ay);
+                    let mut rhs = (py as i64 - ay) * (bx - ax);
+                    if by < ay {
+                        lhs = -lhs;
+                        rhs = -rhs;
+                    }
+                    if right {
+                        lhs >= rhs
+                    } else {
+                        lhs < rhs
+                    }
+                });
+            }
+            _ => {
+                let (x0, x1) = (a.x.min(b.x), a.x.max(b.x));
+                let above = prim == EdgeStripA;
+                let bounds = if above {
+                    (x0, 0, x1, a.y.max(b.y))
+                } else {
+                    (x0, a.y.min(b.y), x1, max)
+                };
+                self.fill_shape(bounds, |px, py| {
+                    if px < x0 || px >= x1 {
+                        return false;
+                    }
+                    let mut lhs = (py as i64 - ay) * (bx - ax);
+                    let mut rhs = (px as i64 - ax) * (by - ay);
+                    if bx < ax {
+                        lhs = -lhs;
+                        rhs = -rhs;
+                    }
+                    if above {
+                        lhs < rhs
+                    } else {
+                        lhs >= rhs
+                    }
+                });
+            }
+        }
+    }
+
+    fn draw_bitmap(&mut self, v: Vertex) {
+        let bmp = self.bitmaps[v.handle as usize % BITMAP_HANDLES];
+        let format = match bmp.effective_format() {
+            Some(f) => f,
+            None => return,
+        };
+        let bpp = match bits_per_pixel(format) {
+            Some(bpp) => bpp,
+            None => return,
+        };
+        let width = if bmp.width == 0 { 2048 } else { bmp.width } as i32;
+        let height = if bmp.height == 0 { 2048 } else { bmp.height } as i32;
+        let tex_width = (bmp.line_stride * 8 / bpp) as i64;
+        let tex_height = bmp.layout_height as i64;
+        if tex_width == 0 || tex_height == 0 {
+            return;
+        }
+        let cell_base = bmp.source + v.cell as u32 * bmp.line_stride * bmp.layout_height;
+        let [a, b, c, d, e, f] = self.ctx.transform;
+        let color = self.ctx.color;
+
+        let (x0, y0) = (v.x, v.y);
+        let (x1, y1) = (x0 + width * SUBPIXELS, y0 + height * SUBPIXELS);
+        let (px0, py0, px1, py1) = self.pixel_bounds((x0, y0, x1, y1));
+        for py in py0..py1 {
+            for px in px0..px1 {
+                let cx = px * SUBPIXELS;
+                let cy = py * SUBPIXELS;
+                if cx < x0 || cx >= x1 || cy < y0 || cy >= y1 {
+                    continue;
+                }
+                let dx = (cx - x0) as i64;
+                let dy = (cy - y0) as i64;
+                // The transform coefficients are 16.16 fixed-point, and
+                // dx and dy are in 1/16 pixel units.
+                let u = (a * dx + b * dy) / SUBPIXELS as i64 + c;
+                let v = (d * dx + e * dy) / SUBPIXELS as i64 + f;
+                let tu = wrap(u >> 16, tex_width, bmp.wrap_x);
+                let tv = wrap(v >> 16, tex_height, bmp.wrap_y);
+                let texel = match (tu, tv) {
+                    (Some(tu), Some(tv)) => {
+                        let row = cell_base + tv as u32 * bmp.line_stride;
+                        self.fetch_texel(format, row, tu as u32)
+                    }
+                    _ => BLACK,
+                };
+                let src = RGBA {
+                    r: mul8(texel.r, color.r),
+                    g: mul8(texel.g, color.g),
+                    b: mul8(texel.b, color.b),
+                    a: mul8(texel.a, color.a),
+                };
+                self.plot(px, py, src);
+            }
+        }
+    }
+
+    fn fetch_texel(&self, format: options::BitmapFormat, row: u32, x: u32) -> RGBA {
+        use options::BitmapFormat::*;
+        let mem = self.main_mem;
+        let byte = |addr: u32| mem.get(addr as usize).copied().unwrap_or(0);
+        let half = |addr: u32| byte(addr) as u16 | (byte(addr + 1) as u16) << 8;
+        let lum = |bits: u32| {
+            let per_byte = 8 / bits;
+            let shift = 8 - bits - (x % per_byte) * bits;
+            let raw = (byte(row + x / per_byte) >> shift) & ((1 << bits) - 1) as u8;
+            RGBA {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: expand(raw as u32, bits),
+            }
+        };
+        match format {
+            ARGB1555 => {
+                let v = half(row + x * 2) as u32;
+                argb(v >> 15, 1, v >> 10, 5, v >> 5, 5, v, 5)
+            }
+            ARGB4 => {
+                let v = half(row + x * 2) as u32;
+                argb(v >> 12, 4, v >> 8, 4, v >> 4, 4, v, 4)
+            }
+            RGB565 => {
+                let v = half(row + x * 2) as u32;
+                argb(1, 1, v >> 11, 5, v >> 5, 6, v, 5)
+            }
+            RGB332 => {
+                let v = byte(row + x) as u32;
+                argb(1, 1, v >> 5, 3, v >> 2, 3, v, 2)
+            }
+            ARGB2 => {
+                let v = byte(row + x) as u32;
+                argb(v >> 6, 2, v >> 4, 2, v >> 2, 2, v, 2)
+            }
+            L1 => lum(1),
+            L2 => lum(2),
+            L4 => lum(4),
+            L8 => lum(8),
+            Paletted8 => {
+                let entry = self.ctx.palette_source + byte(row + x) as u32 * 4;
+                RGBA {
+                    b: byte(entry),
+                    g: byte(entry + 1),
+                    r: byte(entry + 2),
+                    a: byte(entry + 3),
+                }
+            }
+            Paletted565 => {
+                let v = half(self.ctx.palette_source + byte(row + x) as u32 * 2) as u32;
+                argb(1, 1, v >> 11, 5, v >> 5, 6, v, 5)
+            }
+            Paletted4444 => {
+                let v = half(self.ctx.palette_source + byte(row + x) as u32 * 2) as u32;
+                argb(v >> 12, 4, v >> 8, 4, v >> 4, 4, v, 4)
+            }
+            Text8x8 | TextVGA | Bargraph | GLFormat => BLACK,
+        }
+    }
+
+    fn clear(&mut self, color: bool, stencil: bool) {
+        let (x0, y0, x1, y1) = self.scissor_bounds();
+        let cc = self.ctx.clear_color;
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let i = match self.target.index(px, py) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                if color {
+                    self.target.color[i] = self.masked(self.target.color[i], cc);
+                }
+                if stencil {
+                    let old = self.target.stencil[i];
+                    let mask = self.ctx.stencil_write_mask;
+                    self.target.stencil[i] = (old & !mask) | (self.ctx.clear_stencil & mask);
+                }
+            }
+        }
+    }
+
+    // Calls `plot` with the current color for each pixel within the given
+    // bounds (in subpixel units) whose center the given function reports
+    // as inside the shape.
+    fn fill_shape<F>(&mut self, bounds: (i32, i32, i32, i32), inside: F)
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        let (px0, py0, px1, py1) = self.pixel_bounds(bounds);
+        let color = self.ctx.color;
+        for py in py0..py1 {
+            for px in px0..px1 {
+                let cx = px * SUBPIXELS;
+                let cy = py * SUBPIXELS;
+                if inside(cx, cy) {
+                    self.plot(px, py, color);
+                }
+            }
+        }
+    }
+
+    // Converts bounds in subpixel units into a range of pixels, clipped to
+    // the scissor rectangle and the framebuffer.
+    fn pixel_bounds(&self, bounds: (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+        let (sx0, sy0, sx1, sy1) = self.scissor_bounds();
+        let (x0, y0, x1, y1) = bounds;
+        (
+            (x0 / SUBPIXELS - 1).max(sx0),
+            (y0 / SUBPIXELS - 1).max(sy0),
+            (x1 / SUBPIXELS + 1).min(sx1),
+            (y1 / SUBPIXELS + 1).min(sy1),
+        )
+    }
+
+    fn scissor_bounds(&self) -> (i32, i32, i32, i32) {
+        let (x, y, w, h) = self.ctx.scissor;
+        (
+            x.max(0),
+            y.max(0),
+            (x + w).min(self.target.width as i32),
+            (y + h).min(self.target.height as i32),
+        )
+    }
+
+    // Runs a fragment of the given color through the alpha test, stencil
+    // test and blending, and writes the result to the framebuffer.
+    fn plot(&mut self, x: i32, y: i32, src: RGBA) {
+        let i = match self.target.index(x, y) {
+            Some(i) => i,
+            None => return,
+        };
+        let ctx = &self.ctx;
+        if !test(ctx.alpha_func, src.a, ctx.alpha_ref) {
+            return;
+        }
+
+        let old_stencil = self.target.stencil[i];
+        let stencil_mask = ctx.stencil_test_mask;
+        let pass = test(
+            ctx.stencil_func,
+            ctx.stencil_ref & stencil_mask,
+            old_stencil & stencil_mask,
+        );
+        let op = if pass {
+            ctx.stencil_pass
+        } else {
+            ctx.stencil_fail
+        };
+        let new_stencil = stencil_op(op, old_stencil, ctx.stencil_ref);
+        let write_mask = ctx.stencil_write_mask;
+        self.target.stencil[i] = (old_stencil & !write_mask) | (new_stencil & write_mask);
+        if !pass {
+            return;
+        }
+
+        let dst = self.target.color[i];
+        let sf = blend_factor(ctx.blend_src, src, dst);
+        let df = blend_factor(ctx.blend_dst, src, dst);
+        let blend = |s: u8, d: u8| {
+            let v = (s as u32 * sf + d as u32 * df + 127) / 255;
+            v.min(255) as u8
+        };
+        let result = RGBA {
+            r: blend(src.r, dst.r),
+            g: blend(src.g, dst.g),
+            b: blend(src.b, dst.b),
+            a: blend(src.a, dst.a),
+        };
+        self.target.color[i] = self.masked(dst, result);
+    }
+
+    // Returns `new`, except for any channels disabled by the color mask,
+    // which are taken from `old`.
+    fn masked(&self, old: RGBA, new: RGBA) -> RGBA {
+        let mask = self.ctx.color_mask;
+        let pick = |bit: u8, old: u8, new: u8| if (mask & bit) != 0 { new } else { old };
+        RGBA {
+            r: pick(0b1000, old.r, new.r),
+            g: pick(0b0100, old.g, new.g),
+            b: pick(0b0010, old.b, new.b),
+            a: pick(0b0001, old.a, new.a),
+        }
+    }
+}
+
+fn test(func: options::TestFunc, a: u8, b: u8) -> bool {
+    use options::TestFunc::*;
+    match func {
+        Never => false,
+        Less => a < b,
+        LEqual => a <= b,
+        Greater => a > b,
+        GEqual => a >= b,
+        Equal => a == b,
+        NotEqual => a != b,
+        Always => true,
+    }
+}
+
+fn stencil_op(op: options::StencilOp, old: u8, ref_val: u8) -> u8 {
+    use options::StencilOp::*;
+    match op {
+        Zero => 0,
+        Keep => old,
+        Replace => ref_val,
+        Incr => old.saturating_add(1),
+        Decr => old.saturating_sub(1),
+        Invert => !old,
+    }
+}
+
+fn blend_factor(func: options::BlendFunc, src: RGBA, dst: RGBA) -> u32 {
+    use options::BlendFunc::*;
+    match func {
+        Zero => 0,
+        One => 255,
+        SrcAlpha => src.a as u32,
+        DstAlpha => dst.a as u32,
+        OneMinusSrcAlpha => 255 - src.a as u32,
+        OneMinusDstAlpha => 255 - dst.a as u32,
+    }
+}
+
+fn bits_per_pixel(format: options::BitmapFormat) -> Option<u32> {
+    use options::BitmapFormat::*;
+    match format {
+        L1 => Some(1),
+        L2 => Some(2),
+        L4 => Some(4),
+        L8 | RGB332 | ARGB2 | Paletted565 | Paletted4444 | Paletted8 => Some(8),
+        ARGB1555 | ARGB4 | RGB565 => Some(16),
+        Text8x8 | TextVGA | Bargraph | GLFormat => None,
+    }
+}
+
+fn wrap(v: i64, size: i64, mode: options::BitmapWrapMode) -> Option<i64> {
+    match mode {
+        options::BitmapWrapMode::Repeat => Some(v.rem_euclid(size)),
+        options::BitmapWrapMode::Border => {
+            if v >= 0 && v < size {
+                Some(v)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Builds a color from channels of the given bit widths, each given in the
+// low bits of a value that might also have other bits set.
+#[allow(clippy::too_many_arguments)]
+fn argb(
+    a: u32,
+    a_bits: u32,
+    r: u32,
+    r_bits: u32,
+    g: u32,
+    g_bits: u32,
+    b: u32,
+    b_bits: u32,
+) -> RGBA {
+    let chan = |v: u32, bits: u32| expand(v & ((1 << bits) - 1), bits);
+    RGBA {
+        r: chan(r, r_bits),
+        g: chan(g, g_bits),
+        b: chan(b, b_bits),
+        a: chan(a, a_bits),
+    }
+}
+
+// Scales a value with the given number of bits to the full range of a u8.
+fn expand(v: u32, bits: u32) -> u8 {
+    (v * 255 / ((1 << bits) - 1)) as u8
+}
+
+fn mul8(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+fn sq(v: i32) -> i64 {
+    v as i64 * v as i64
+}
+
+fn dist2_point(px: i32, py: i32, x: i32, y: i32) -> i64 {
+    sq(px - x) + sq(py - y)
+}
+
+// Returns true if the squared distance from the given point to the line
+// segment between `a` and `b` is less than `limit`, using only integer
+// arithmetic.
+fn dist2_segment_lt(px: i32, py: i32, a: (i32, i32), b: (i32, i32), limit: i64) -> bool {
+    let (dx, dy) = ((b.0 - a.0) as i128, (b.1 - a.1) as i128);
+    let (qx, qy) = ((px - a.0) as i128, (py - a.1) as i128);
+    let len2 = dx * dx + dy * dy;
+    let dot = qx * dx + qy * dy;
+    if len2 == 0 || dot <= 0 {
+        return dist2_point(px, py, a.0, a.1) < limit;
+    }
+    if dot >= len2 {
+        return dist2_point(px, py, b.0, b.1) < limit;
+    }
+    // The squared distance to the closest point on the segment is
+    // |q|^2 - dot^2 / len2, so we multiply through by len2.
+    (qx * qx + qy * qy) * len2 - dot * dot < limit as i128 * len2
+}
+
+fn read_u32(mem: &[u8], offset: usize) -> Option<u32> {
+    let bytes = mem.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_list::options::{BitmapFormat, BitmapSizeFilter, BitmapWrapMode};
+    use crate::display_list::options::{GraphicsPrimitive, StencilOp, TestFunc};
+    use crate::graphics::RGB;
+
+    const W: u16 = 8;
+    const H: u16 = 8;
+
+    const RED: RGB = RGB { r: 255, g: 0, b: 0 };
+    const BLUE: RGB = RGB { r: 0, g: 0, b: 255 };
+
+    // Renders the given commands into an 8x8 framebuffer and then returns
+    // the result as a string with one character per pixel, using the
+    // given function to choose the character for each color.
+    fn render_ascii(cmds: &[DLCmd], main_mem: &[u8], f: impl Fn(RGBA) -> char) -> [[char; 8]; 8] {
+        let mut dl = [0u8; 1024];
+        for (i, cmd) in cmds.iter().enumerate() {
+            dl[i * 4..i * 4 + 4].copy_from_slice(&cmd.as_raw().to_le_bytes());
+        }
+        let mut color = [BLACK; (W as usize) * (H as usize)];
+        let mut stencil = [0u8; (W as usize) * (H as usize)];
+        let mut fb = Framebuffer::new(W, H, &mut color, &mut stencil);
+        render(&dl, main_mem, [0, 0], &mut fb);
+
+        let mut ret = [['?'; 8]; 8];
+        for y in 0..H {
+            for x in 0..W {
+                ret[y as usize][x as usize] = f(fb.pixel(x, y).unwrap());
+            }
+        }
+        ret
+    }
+
+    fn by_color(c: RGBA) -> char {
+        match (c.r, c.g, c.b) {
+            (0, 0, 0) => '.',
+            (255, 0, 0) => 'R',
+            (0, 0, 255) => 'B',
+            (255, 255, 255) => 'W',
+            _ => '?',
+        }
+    }
+
+    fn rows(s: [&str; 8]) -> [[char; 8]; 8] {
+        let mut ret = [['?'; 8]; 8];
+        for (y, row) in s.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                ret[y][x] = c;
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn test_clear_and_scissor() {
+        let got = render_ascii(
+            &[
+                DLCmd::clear_color_rgb(RED),
+                DLCmd::CLEAR_ALL,
+                DLCmd::scissor_pos((2, 1)),
+                DLCmd::scissor_size((3, 2)),
+                DLCmd::clear_color_rgb(BLUE),
+                DLCmd::CLEAR_ALL,
+                DLCmd::DISPLAY,
+            ],
+            &[],
+            by_color,
+        );
+        let want = rows([
+            "RRRRRRRR", //
+            "RRBBBRRR", //
+            "RRBBBRRR", //
+            "RRRRRRRR", //
+            "RRRRRRRR", //
+            "RRRRRRRR", //
+            "RRRRRRRR", //
+            "RRRRRRRR", //
+        ]);
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_rects_and_points() {
+        let got = render_ascii(
+            &[
+                DLCmd::CLEAR_ALL,
+                DLCmd::color_rgb(RED),
+                DLCmd::begin(GraphicsPrimitive::Rects),
+                DLCmd::vertex_2ii((1, 1)),
+                DLCmd::vertex_2ii((3, 2)),
+                DLCmd::color_rgb(BLUE),
+                DLCmd::begin(GraphicsPrimitive::Points),
+                DLCmd::point_size(24),
+                DLCmd::vertex_2f((96, 96)),
+                DLCmd::END,
+                DLCmd::DISPLAY,
+            ],
+            &[],
+            by_color,
+        );
+        let want = rows([
+            "........", //
+            ".RRR....", //
+            ".RRR....", //
+            "........", //
+            "........", //
+            ".....BBB", //
+            ".....BBB", //
+            ".....BBB", //
+        ]);
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_lines_and_edge_strips() {
+        let got = render_ascii(
+            &[
+                DLCmd::CLEAR_ALL,
+                DLCmd::color_rgb(BLUE),
+                DLCmd::begin(GraphicsPrimitive::EdgeStripR),
+                DLCmd::vertex_2ii((6, 0)),
+                DLCmd::vertex_2ii((6, 8)),
+                DLCmd::color_rgb(RED),
+                DLCmd::line_width(8),
+                DLCmd::begin(GraphicsPrimitive::Lines),
+                DLCmd::vertex_2f((0, 32)),
+                DLCmd::vertex_2f((128, 32)),
+                DLCmd::END,
+                DLCmd::DISPLAY,
+            ],
+            &[],
+            by_color,
+        );
+        let want = rows([
+            "......BB", //
+            "......BB", //
+            "RRRRRRRR", //
+            "......BB", //
+            "......BB", //
+            "......BB", //
+            "......BB", //
+            "......BB", //
+        ]);
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_bitmap_l1() {
+        // A 4x4 checkerboard in L1 format, with one byte per row, drawn
+        // at its natural size and then repeated at double scale.
+        let mem = [0b1010_0000, 0b0101_0000, 0b1010_0000, 0b0101_0000];
+        let got = render_ascii(
+            &[
+                DLCmd::CLEAR_ALL,
+                DLCmd::bitmap_layout_l(BitmapFormat::L1, 1, 4),
+                DLCmd::bitmap_size_l(
+                    4,
+                    4,
+                    BitmapSizeFilter::Nearest,
+                    BitmapWrapMode::Border,
+                    BitmapWrapMode::Border,
+                ),
+                DLCmd::begin(GraphicsPrimitive::Bitmaps),
+                DLCmd::vertex_2ii((0, 0)),
+                DLCmd::bitmap_size_l(
+                    4,
+                    8,
+                    BitmapSizeFilter::Nearest,
+                    BitmapWrapMode::Repeat,
+                    BitmapWrapMode::Repeat,
+                ),
+                DLCmd::bitmap_transform_a(options::MatrixCoeff::new_8_8(0, 128)),
+                DLCmd::bitmap_transform_e(options::MatrixCoeff::new_8_8(0, 128)),
+                DLCmd::vertex_2ii((4, 0)),
+                DLCmd::END,
+                DLCmd::DISPLAY,
+            ],
+            &mem,
+            by_color,
+        );
+        let want = rows([
+            "W.W.WW..", //
+            ".W.WWW..", //
+            "W.W...WW", //
+            ".W.W..WW", //
+            "....WW..", //
+            "....WW..", //
+            "......WW", //
+            "......WW", //
+        ]);
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_bitmap_rgb565_with_color() {
+        // A 2x1 bitmap of white and green, drawn modulated by red so that
+        // the white texel becomes red and the green one becomes opaque
+        // black.
+        let mem = [0xff, 0xff, 0xe0, 0x07];
+        let got = render_ascii(
+            &[
+                DLCmd::CLEAR_ALL,
+                DLCmd::bitmap_layout_l(BitmapFormat::RGB565, 4, 1),
+                DLCmd::bitmap_size_l(
+                    2,
+                    1,
+                    BitmapSizeFilter::Nearest,
+                    BitmapWrapMode::Border,
+                    BitmapWrapMode::Border,
+                ),
+                DLCmd::color_rgb(RED),
+                DLCmd::begin(GraphicsPrimitive::Bitmaps),
+                DLCmd::vertex_2ii((1, 1)),
+                DLCmd::END,
+                DLCmd::DISPLAY,
+            ],
+            &mem,
+            |c| match by_color(c) {
+                '.' if c.a == 255 => 'k',
+                other => other,
+            },
+        );
+        assert_eq!(&got[1][..4], &['.', 'R', 'k', '.']);
+    }
+
+    #[test]
+    fn test_blending() {
+        let mut dl = [0u8; 64];
+        let cmds = [
+            DLCmd::clear_color_rgb(RED),
+            DLCmd::CLEAR_ALL,
+            DLCmd::color_rgb(BLUE),
+            DLCmd::color_alpha(128),
+            DLCmd::begin(GraphicsPrimitive::Rects),
+            DLCmd::vertex_2ii((0, 0)),
+            DLCmd::vertex_2ii((1, 1)),
+            DLCmd::DISPLAY,
+        ];
+        for (i, cmd) in cmds.iter().enumerate() {
+            dl[i * 4..i * 4 + 4].copy_from_slice(&cmd.as_raw().to_le_bytes());
+        }
+        let mut color = [BLACK; 4];
+        let mut stencil = [0u8; 4];
+        let mut fb = Framebuffer::new(2, 2, &mut color, &mut stencil);
+        render(&dl, &[], [0, 0], &mut fb);
+        assert_eq!(
+            fb.pixel(1, 1),
+            Some(RGBA {
+                r: 127,
+                g: 0,
+                b: 128,
+                a: 64,
+            })
+        );
+    }
+
+    #[test]
+    fn test_stencil_and_alpha_test() {
+        let got = render_ascii(
+            &[
+                DLCmd::CLEAR_ALL,
+                // Mark the left half of the screen in the stencil buffer,
+                // without drawing anything visible.
+                DLCmd::color_mask(options::ColorMask::new(false, false, false, false)),
+                DLCmd::stencil_op(StencilOp::Keep, StencilOp::Replace),
+                DLCmd::stencil_test(TestFunc::Always, 1, 255),
+                DLCmd::begin(GraphicsPrimitive::EdgeStripL),
+                DLCmd::vertex_2ii((4, 0)),
+                DLCmd::vertex_2ii((4, 8)),
+                // Now fill the whole screen, but only where the stencil
+                // is set.
+                DLCmd::color_mask(options::ColorMask::default()),
+                DLCmd::stencil_op(StencilOp::Keep, StencilOp::Keep),
+                DLCmd::stencil_test(TestFunc::Equal, 1, 255),
+                DLCmd::color_rgb(RED),
+                DLCmd::begin(GraphicsPrimitive::Rects),
+                DLCmd::vertex_2ii((0, 0)),
+                DLCmd::vertex_2ii((7, 7)),
+                // A rectangle that fails the alpha test draws nothing.
+                DLCmd::stencil_test(TestFunc::Always, 0, 255),
+                DLCmd::alpha_test(TestFunc::Greater, 200),
+                DLCmd::color_alpha(100),
+                DLCmd::vertex_2ii((0, 0)),
+                DLCmd::vertex_2ii((7, 7)),
+                DLCmd::END,
+                DLCmd::DISPLAY,
+            ],
+            &[],
+            by_color,
+        );
+        let want = rows([
+            "RRRR....", //
+            "RRRR....", //
+            "RRRR....", //
+            "RRRR....", //
+            "RRRR....", //
+            "RRRR....", //
+            "RRRR....", //
+            "RRRR....", //
+        ]);
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_call_and_context() {
+        // The commands at word 8 onwards are a subroutine that draws a
+        // blue point in a saved context, so its color change doesn't
+        // leak out into the caller's red point.
+        let mut cmds = [DLCmd::NOP; 13];
+        cmds[..7].copy_from_slice(&[
+            DLCmd::CLEAR_ALL,
+            DLCmd::color_rgb(RED),
+            DLCmd::begin(GraphicsPrimitive::Points),
+            DLCmd::point_size(8),
+            DecodedCmd::Call(8 * 4).into(),
+            DLCmd::vertex_2ii((1, 0)),
+            DLCmd::DISPLAY,
+        ]);
+        cmds[8..].copy_from_slice(&[
+            DLCmd::SAVE_CONTEXT,
+            DLCmd::color_rgb(BLUE),
+            DLCmd::vertex_2ii((0, 0)),
+            DLCmd::RESTORE_CONTEXT,
+            DLCmd::RETURN,
+        ]);
+        let got = render_ascii(&cmds, &[], by_color);
+        assert_eq!(&got[0][..3], &['B', 'R', '.']);
+        assert_eq!(&got[1][..3], &['.', '.', '.']);
+    }
+
+    #[test]
+    fn test_fake_interface_render() {
+        use crate::interface::fake::Interface as FakeInterface;
+        use crate::interface::Interface as _;
+        use crate::memory::MemoryRegion;
+        use crate::models::fake::Model as FakeModel;
+        use crate::models::Model;
+        use crate::registers::Register;
+
+        let mut mem = [0u8; 16];
+        let mut dl = [0u8; 64];
+        let mut regs = [0u32; 128];
+        regs[Register::MACRO_0.index()] = DLCmd::color_rgb(BLUE).as_raw();
+        let mut ei = FakeInterface::new(FakeModel)
+            .with_main_ram(&mut mem[..])
+            .with_display_list_ram(&mut dl[..])
+            .with_register_file(&mut regs[..]);
+
+        let cmds = [
+            DLCmd::command_from_macro(0),
+            DLCmd::begin(GraphicsPrimitive::Rects),
+            DLCmd::vertex_2ii((0, 0)),
+            DLCmd::vertex_2ii((1, 0)),
+            DLCmd::DISPLAY,
+        ];
+        ei.begin_write(<FakeModel as Model>::DisplayListMem::ptr(0).to_raw())
+            .unwrap();
+        for cmd in cmds.iter() {
+            ei.continue_write(&cmd.as_raw().to_le_bytes()).unwrap();
+        }
+        ei.end_write().unwrap();
+
+        let mut color = [BLACK; 4];
+        let mut stencil = [0u8; 4];
+        let mut fb = Framebuffer::new(2, 2, &mut color, &mut stencil);
+        ei.render_display_list(&mut fb);
+        let blue = BLUE.as_rgba();
+        assert_eq!(fb.pixels(), &[blue, blue, BLACK, BLACK]);
+    }
+}
@@ -1,13 +1,22 @@
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod boot;
 pub mod commands;
 pub mod config;
 pub mod display_list;
+pub mod gpio;
 pub mod graphics;
 pub mod interface;
 pub mod low_level;
 pub mod memory;
 pub mod models;
+pub mod power;
+
+#[cfg(feature = "testing")]
+pub mod testing;
 
 mod error;
 pub use error::CoprocessorError;
@@ -39,13 +48,21 @@ pub(crate) use low_level::{host_commands, registers};
 #[doc(inline)]
 pub use models::bt815::BT815;
 
-use interface::Interface;
+use interface::{Interface, SpiWidth, SpiWidthInterface};
 
 /// An alias for [`BT815`](BT815), because both models belong to the same
 /// generation and thus share a common API.
 #[doc(inline)]
 pub type BT816 = BT815;
 
+/// Model type representing the BT817 and BT818 chips.
+#[doc(inline)]
+pub use models::bt817::BT817;
+
+/// An alias for [`BT817`](BT817), because both models belong to the same
+/// generation and thus share a common API.
+pub type BT818 = BT817;
+
 use models::Model;
 
 /// The main type for this crate, providing a high-level API to an EVE chip
@@ -114,6 +131,31 @@ impl<M: Model, I: Interface> EVE<M, I> {
         &mut self.ll
     }
 
+    /// Begins the full boot sequence for the chip, returning an object
+    /// that performs each of the steps in order. See [`boot`](boot) for
+    /// more information.
+    ///
+    /// This is an alternative to calling `start_system_clock`,
+    /// `poll_for_boot`, `configure_video_pins`, `start_video` and
+    /// `coprocessor` individually.
+    pub fn boot(self, config: boot::BootConfig) -> boot::Boot<M, I, boot::Uninitialized> {
+        boot::Boot::new(self, config)
+    }
+
+    /// Puts the chip into one of the low-power states, returning an object
+    /// that can only move to a deeper state or wake the chip again. See
+    /// [`power`](power) for more information.
+    ///
+    /// If you are using the coprocessor, use
+    /// [`Coprocessor::into_eve`](commands::Coprocessor::into_eve) first to
+    /// make sure it has finished its work.
+    pub fn enter_power_state<S: power::PowerState>(
+        self,
+        state: S,
+    ) -> Result<power::Suspended<M, I, S>, power::PowerError<M, I>> {
+        power::Suspended::enter(self, state)
+    }
+
     /// Sends commands to the device to configure and then activate the system
     /// clock.
     ///
@@ -140,7 +182,7 @@ impl<M: Model, I: Interface> EVE<M, I> {
     ///
     /// If the connected device isn't an EVE, or if the chip isn't connected
     /// correctly, or if it's failing boot in some other way then this
-    /// function will poll forever.
+    /// function gives up and returns `false` after `poll_limit` polls.
     pub fn poll_for_boot(&mut self, poll_limit: u32) -> Result<bool, Error<I>> {
         config::poll_for_boot(self, poll_limit)
     }
@@ -152,6 +194,56 @@ impl<M: Model, I: Interface> EVE<M, I> {
         config::configure_video_pins(self, mode)
     }
 
+    /// Sets the brightness of the display backlight, by changing the duty
+    /// cycle of the backlight PWM output.
+    ///
+    /// To fade the backlight gradually without the host's involvement, use
+    /// [`Coprocessor::fade_backlight`](commands::Coprocessor::fade_backlight)
+    /// instead.
+    pub fn set_backlight(&mut self, brightness: config::Brightness) -> Result<(), Error<I>> {
+        config::set_backlight(self, brightness)
+    }
+
+    /// Sets the frequency of the backlight PWM output, in hertz. Values
+    /// outside of [`PWM_HZ_RANGE`](config::PWM_HZ_RANGE) are clamped to
+    /// the nearest supported frequency.
+    pub fn set_backlight_frequency(&mut self, hz: u16) -> Result<(), Error<I>> {
+        config::set_backlight_frequency(self, hz)
+    }
+
+    /// Returns the logical width and height of the screen, taking into
+    /// account the rotation selected by
+    /// [`Coprocessor::set_rotation`](commands::Coprocessor::set_rotation).
+    pub fn screen_size(&mut self) -> Result<(u16, u16), Error<I>> {
+        graphics::rotation::read_screen_size(&mut self.ll)
+    }
+
+    /// Selects whether the given GPIO pin is an input or an output, leaving
+    /// the other pins unchanged.
+    pub fn gpio_set_direction(
+        &mut self,
+        pin: gpio::GpioPin,
+        dir: gpio::GpioDirection,
+    ) -> Result<(), Error<I>> {
+        gpio::set_direction(&mut self.ll, pin, dir)
+    }
+
+    /// Returns whether the given GPIO pin is currently an input or an output.
+    pub fn gpio_direction(&mut self, pin: gpio::GpioPin) -> Result<gpio::GpioDirection, Error<I>> {
+        gpio::direction(&mut self.ll, pin)
+    }
+
+    /// Sets the output level of the given GPIO pin, leaving the other pins
+    /// unchanged. This has no visible effect unless the pin is an output.
+    pub fn gpio_write(&mut self, pin: gpio::GpioPin, high: bool) -> Result<(), Error<I>> {
+        gpio::write(&mut self.ll, pin, high)
+    }
+
+    /// Returns true if the given GPIO pin is currently at a high level.
+    pub fn gpio_read(&mut self, pin: gpio::GpioPin) -> Result<bool, Error<I>> {
+        gpio::read(&mut self.ll, pin)
+    }
+
     /// Configures registers to achieve a particular graphics mode wLowLevel
     /// given timings.
     ///
@@ -170,6 +262,15 @@ impl<M: Model, I: Interface> EVE<M, I> {
         config::activate_pixel_clock(self, c)
     }
 
+    /// Like [`start_video`](Self::start_video), but first checks the
+    /// timings using [`VideoTimings::validate`](config::VideoTimings::validate),
+    /// returning [`Error::InvalidTimings`](Error::InvalidTimings) without
+    /// changing anything if they have problems.
+    pub fn start_video_checked(&mut self, c: &config::VideoTimings) -> Result<(), Error<I>> {
+        c.validate().map_err(Error::InvalidTimings)?;
+        self.start_video(c)
+    }
+
     pub fn new_display_list<
         F: FnOnce(
             &mut display_list::JustBuilder<low_level::LowLevel<M, I>>,
@@ -226,3 +327,25 @@ impl<M: Model, I: Interface> EVE<M, I> {
         commands::Coprocessor::new_polling(ei)
     }
 }
+
+impl<M: Model, I: SpiWidthInterface> EVE<M, I> {
+    /// Switches both the EVE chip and the host interface to use the given
+    /// number of SPI data lines.
+    ///
+    /// Returns [`Error::Unsupported`](Error::Unsupported) without changing
+    /// anything if the interface can't use the requested width.
+    ///
+    /// Resetting the chip returns it to single-line mode, so you'll need to
+    /// call this again after any reset.
+    pub fn set_spi_width(&mut self, width: SpiWidth) -> Result<(), Error<I>> {
+        if !self.ll.borrow_interface().supports_spi_width(width) {
+            return Err(Error::Unsupported);
+        }
+        self.ll.wr8(
+            M::reg_ptr(registers::Register::SPI_WIDTH),
+            width.reg_value(),
+        )?;
+        let ei = self.ll.borrow_interface();
+        low_level::LowLevel::<M, I>::result(ei.set_spi_width(width))
+    }
+}
@@ -40,6 +40,8 @@ pub enum Register {
     MEDIAFIFO_WRITE = 0x7018,
     OUTBITS = 0x5c,
     PCLK = 0x70,
+    PCLK_2X = 0x618,
+    PCLK_FREQ = 0x614,
     PCLK_POL = 0x6c,
     PLAY = 0x8c,
     PLAY_CONTROL = 0x714e,
@@ -60,6 +62,8 @@ pub enum Register {
     TAG = 0x7c,
     TAG_X = 0x74,
     TAG_Y = 0x78,
+    TOUCH_MODE = 0x104,
+    TOUCH_RZTHRESH = 0x118,
     TRACKER = 0x7000,
     TRACKER_1 = 0x7004,
     TRACKER_2 = 0x7008,
@@ -76,7 +80,13 @@ pub enum Register {
 impl Register {
     pub fn ptr<M: crate::models::Model>(self) -> crate::memory::Ptr<M::RegisterMem> {
         use crate::memory::MemoryRegion;
-        M::RegisterMem::ptr(self as u32)
+        // We can't use M::RegisterMem::ptr here, because a few registers
+        // such as REG_COPRO_PATCH_PTR are beyond the end of the main
+        // register memory, and so the offset would wrap around.
+        crate::memory::Ptr {
+            addr: M::RegisterMem::BASE_ADDR + self as u32,
+            _region: core::marker::PhantomData,
+        }
     }
 
     /// Returns the offset of the register address within the register memory.
@@ -100,5 +110,9 @@ mod tests {
     fn test_ptr() {
         assert_eq!(Register::VSYNC1.ptr::<Exhaustive>().to_raw(), 0x302050);
         assert_eq!(Register::VSYNC1.ptr::<Exhaustive>().to_raw(), 0x302050);
+        assert_eq!(
+            Register::COPRO_PATCH_PTR.ptr::<Exhaustive>().to_raw(),
+            0x309162
+        );
     }
 }
@@ -2,6 +2,7 @@
 //! the different generations.
 
 pub mod bt815;
+pub mod bt817;
 pub mod fake;
 
 use crate::memory;
@@ -25,6 +26,10 @@ pub trait Model: Sized + core::fmt::Debug {
     type RegisterMem: memory::RegisterMem;
     type CommandMem: memory::CommandMem;
 
+    /// True for models that have the `REG_PCLK_FREQ` and `REG_PCLK_2X`
+    /// registers, which were introduced in the BT817 and BT818.
+    const HAS_PCLK_FREQ: bool = false;
+
     fn new_low_level<I: crate::Interface>(ei: I) -> crate::low_level::LowLevel<Self, I> {
         crate::low_level::LowLevel::new(ei)
     }
@@ -76,6 +81,7 @@ pub(crate) mod testing {
         type DisplayListMem = DisplayListMem;
         type RegisterMem = RegisterMem;
         type CommandMem = CommandMem;
+        const HAS_PCLK_FREQ: bool = true;
     }
 
     impl WithExtFlashMem for Exhaustive {
@@ -11,6 +11,7 @@ impl super::Model for Model {
     type DisplayListMem = DisplayListMem;
     type RegisterMem = RegisterMem;
     type CommandMem = CommandMem;
+    const HAS_PCLK_FREQ: bool = true;
 }
 
 impl super::WithExtFlashMem for Model {
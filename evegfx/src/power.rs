@@ -0,0 +1,323 @@
+//! Entering and leaving the EVE chip's low-power states.
+//!
+//! [`EVE::enter_power_state`](crate::EVE::enter_power_state) consumes the
+//! `EVE` object and returns a [`Suspended`](Suspended) object, which only
+//! allows the operations that are valid in the selected state: moving to a
+//! deeper power state, or waking the chip again.
+//!
+//! The coprocessor must be idle before the chip stops, so if you have a
+//! [`Coprocessor`](crate::commands::Coprocessor) object then first use its
+//! `into_eve` method to wait for it and stop its command stream:
+//!
+//! ```rust
+//! use evegfx::boot::BootConfig;
+//! use evegfx::config::presets;
+//! use evegfx::interface::Interface;
+//! use evegfx::power;
+//! use evegfx::EVE;
+//!
+//! fn nap<I>(ei: I) -> Result<(), evegfx::Error<I>>
+//! where
+//!     I: Interface,
+//!     I::Error: core::fmt::Debug,
+//! {
+//!     let eve = EVE::new(evegfx::BT815, ei);
+//!     let cp = eve
+//!         .boot(BootConfig::new(presets::WVGA_800X480))
+//!         .start_clock()?
+//!         .wait_for_boot()?
+//!         .start_video()?
+//!         .coprocessor_polling()
+//!         .unwrap();
+//!
+//!     let eve = cp.into_eve().unwrap();
+//!     let asleep = eve.enter_power_state(power::Sleep)?;
+//!     let eve = asleep.wake(|_ms| { /* delay here */ })?;
+//!     Ok(())
+//! }
+//! # evegfx::interface::fake::boot_example(|ei| nap(ei).unwrap());
+//! ```
+
+use crate::boot::{BootConfig, BootError};
+use crate::error::Error;
+use crate::host_commands::HostCmd;
+use crate::interface::Interface;
+use crate::models::Model;
+use crate::EVE;
+
+/// Implemented by the types representing the EVE low-power states.
+///
+/// This trait is intended only for implementation inside this crate.
+pub trait PowerState: Sized + Copy + core::fmt::Debug {
+    #[doc(hidden)]
+    const HOST_CMD: HostCmd;
+}
+
+/// Implemented by power states that can be entered directly from state `S`.
+pub trait DeeperThan<S: PowerState>: PowerState {}
+
+/// The standby state, where the clock oscillator and PLL keep running but
+/// the clock to the rest of the chip is stopped. The chip can wake quickly
+/// from this state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Standby;
+
+/// The sleep state, where the clock oscillator and PLL are also stopped.
+/// Memory and register contents are retained, but waking takes longer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sleep;
+
+/// The power-down state, where the core of the chip is switched off
+/// altogether. All memory and register contents are lost, and so waking
+/// requires booting the chip again.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PowerDown;
+
+impl PowerState for Standby {
+    const HOST_CMD: HostCmd = HostCmd::STANDBY;
+}
+
+impl PowerState for Sleep {
+    const HOST_CMD: HostCmd = HostCmd::SLEEP;
+}
+
+impl PowerState for PowerDown {
+    const HOST_CMD: HostCmd = HostCmd::PWRDOWN;
+}
+
+impl DeeperThan<Standby> for Sleep {}
+impl DeeperThan<Standby> for PowerDown {}
+impl DeeperThan<Sleep> for PowerDown {}
+
+/// The number of milliseconds to wait after waking from [`Sleep`](Sleep),
+/// while the clock oscillator and PLL restart.
+pub const SLEEP_WAKE_DELAY_MS: u32 = 20;
+
+/// An EVE chip in one of the low-power states, represented by `S`.
+///
+/// If any of the methods of this type fail, the chip is in an unknown
+/// state. The returned [`PowerError`](PowerError) allows recovering the `EVE`
+/// object in order to boot the chip again.
+pub struct Suspended<M: Model, I: Interface, S: PowerState> {
+    eve: EVE<M, I>,
+    state: S,
+}
+
+impl<M: Model, I: Interface, S: PowerState> Suspended<M, I, S> {
+    pub(crate) fn enter(mut eve: EVE<M, I>, state: S) -> Result<Self, PowerError<M, I>> {
+        match eve.ll.host_command(S::HOST_CMD, 0, 0) {
+            Ok(()) => Ok(Self {
+                eve: eve,
+                state: state,
+            }),
+            Err(err) => Err(PowerError {
+                error: err,
+                eve: eve,
+            }),
+        }
+    }
+
+    /// Returns the power state the chip is in.
+    pub fn state(&self) -> S {
+        self.state
+    }
+
+    /// Moves the chip into a deeper power state, without waking it first.
+    pub fn enter_power_state<S2: DeeperThan<S>>(
+        self,
+        state: S2,
+    ) -> Result<Suspended<M, I, S2>, PowerError<M, I>> {
+        Suspended::enter(self.eve, state)
+    }
+
+    /// Returns the `EVE` object without waking the chip, such as for
+    /// booting it again from the beginning.
+    pub fn into_eve(self) -> EVE<M, I> {
+        self.eve
+    }
+
+    // Sends the ACTIVE host command and then calls the given function to
+    // wait, if needed, returning the `EVE` object if successful.
+    fn activate<F: FnOnce()>(mut self, wait: F) -> Result<EVE<M, I>, PowerError<M, I>> {
+        match self.eve.ll.host_command(HostCmd::ACTIVE, 0, 0) {
+            Ok(()) => {
+                wait();
+                Ok(self.eve)
+            }
+            Err(err) => Err(PowerError {
+                error: err,
+                eve: self.eve,
+            }),
+        }
+    }
+}
+
+impl<M: Model, I: Interface> Suspended<M, I, Standby> {
+    /// Returns the chip to the active state. The clock is already running,
+    /// so the chip is ready again immediately.
+    pub fn wake(self) -> Result<EVE<M, I>, PowerError<M, I>> {
+        self.activate(|| ())
+    }
+}
+
+impl<M: Model, I: Interface> Suspended<M, I, Sleep> {
+    /// Returns the chip to the active state, and then calls `delay_ms`
+    /// with the number of milliseconds to wait before the clock is stable
+    /// enough for the chip to be used again.
+    ///
+    /// The display list and register settings are retained in this state,
+    /// so the display resumes as it was.
+    pub fn wake<D: FnOnce(u32)>(self, delay_ms: D) -> Result<EVE<M, I>, PowerError<M, I>> {
+        self.activate(|| delay_ms(SLEEP_WAKE_DELAY_MS))
+    }
+}
+
+impl<M: Model, I: Interface> Suspended<M, I, PowerDown> {
+    /// Returns the chip to the active state by running the full
+    /// [`boot`](crate::boot) sequence with the given configuration, which
+    /// restarts the system clock and video output and loads the initial
+    /// display list.
+    ///
+    /// Everything else stored on the chip was lost while it was powered down,
+    /// so the application must reload any assets and its own display list.
+    pub fn wake(self, config: BootConfig) -> Result<EVE<M, I>, PowerError<M, I>> {
+        let booted = self
+            .eve
+            .boot(config)
+            .start_clock()?
+            .wait_for_boot()?
+            .start_video()?;
+        Ok(booted.into_eve())
+    }
+}
+
+/// The error type for the power state transitions, which returns the `EVE`
+/// object along with the error so that the caller can boot the chip again.
+///
+/// This converts to [`Error`](Error) using `?`, discarding the `EVE` object.
+pub struct PowerError<M: Model, I: Interface> {
+    pub error: Error<I>,
+    pub eve: EVE<M, I>,
+}
+
+impl<M: Model, I: Interface> PowerError<M, I> {
+    /// Returns the `EVE` object, so that the chip can be booted again.
+    pub fn into_eve(self) -> EVE<M, I> {
+        self.eve
+    }
+}
+
+impl<M: Model, I: Interface> From<PowerError<M, I>> for Error<I> {
+    fn from(err: PowerError<M, I>) -> Self {
+        err.error
+    }
+}
+
+// Waking from power-down runs the boot steps, whose errors also carry the
+// `EVE` object.
+impl<M: Model, I: Interface> From<BootError<M, I>> for PowerError<M, I> {
+    fn from(err: BootError<M, I>) -> Self {
+        PowerError {
+            error: err.error,
+            eve: err.eve,
+        }
+    }
+}
+
+impl<M: Model, I: Interface> core::fmt::Debug for PowerError<M, I>
+where
+    I::Error: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PowerError")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<M: Model, I: Interface, S: PowerState> core::fmt::Debug for Suspended<M, I, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Suspended")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::presets;
+    use crate::interface::fake::{
+        Interface as FakeInterface, PowerState as FakeState, TestBuffers,
+    };
+    use crate::models::fake::Model as FakeModel;
+    use crate::registers::Register;
+
+    // Returns an EVE object for a chip that has already booted.
+    fn eve(bufs: &mut TestBuffers) -> EVE<FakeModel, FakeInterface<'_, FakeModel, &mut [u32]>> {
+        let eve = EVE::new(FakeModel, bufs.interface().with_host_simulation());
+        let config = BootConfig::new(presets::WVGA_800X480);
+        eve.boot(config)
+            .start_clock()
+            .unwrap()
+            .wait_for_boot()
+            .unwrap()
+            .start_video()
+            .unwrap()
+            .into_eve()
+    }
+
+    #[test]
+    fn test_standby_and_sleep() {
+        let mut bufs = TestBuffers::new();
+        let eve = eve(&mut bufs);
+
+        let standby = eve.enter_power_state(Standby).unwrap();
+        let mut eve = standby.wake().unwrap();
+        assert_eq!(
+            eve.borrow_interface().power_state(),
+            Some(FakeState::Active)
+        );
+
+        let standby = eve.enter_power_state(Standby).unwrap();
+        let asleep = standby.enter_power_state(Sleep).unwrap();
+        assert_eq!(
+            asleep.into_eve().borrow_interface().power_state(),
+            Some(FakeState::Sleep)
+        );
+    }
+
+    #[test]
+    fn test_sleep_wake_delay() {
+        let mut bufs = TestBuffers::new();
+        let asleep = eve(&mut bufs).enter_power_state(Sleep).unwrap();
+        let mut waited = 0;
+        let mut eve = asleep.wake(|ms| waited += ms).unwrap();
+        assert_eq!(waited, SLEEP_WAKE_DELAY_MS);
+
+        // The chip kept its settings while asleep.
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::HSIZE)).unwrap(), 800);
+    }
+
+    #[test]
+    fn test_power_down() {
+        let mut bufs = TestBuffers::new();
+        let cp = eve(&mut bufs).coprocessor_polling().unwrap();
+        let eve = cp.into_eve().unwrap();
+
+        let down = eve.enter_power_state(PowerDown).unwrap();
+        let config = BootConfig::new(presets::WQVGA_480X272);
+        let mut eve = down.wake(config).unwrap();
+        assert_eq!(
+            eve.borrow_interface().power_state(),
+            Some(FakeState::Active)
+        );
+        let ll = eve.borrow_low_level();
+        assert_eq!(ll.rd16(ll.reg_ptr(Register::HSIZE)).unwrap(), 480);
+
+        // The coprocessor can start again after waking.
+        let mut cp = eve.coprocessor_polling().unwrap();
+        cp.block_until_idle().unwrap();
+    }
+}
@@ -0,0 +1,354 @@
+//! Helpers for testing code that generates display lists.
+//!
+//! The main entry point is the [`assert_dl_snapshot!`](crate::assert_dl_snapshot)
+//! macro, which compares a sequence of display list or coprocessor command
+//! words against a "golden" text file checked in alongside the tests. The
+//! text file contains one decoded command per line, in the same C-like
+//! syntax used by the EVE programming guides, so that changes to the
+//! generated commands show up as readable diffs in code review.
+//!
+//! To create or update the snapshot files, run the tests with the
+//! environment variable `EVEGFX_UPDATE_SNAPSHOTS` set to any non-empty
+//! value. The macro then writes the actual result to the file instead of
+//! comparing with it.
+//!
+//! [`Recorder`] is an in-memory [`Builder`] which is a convenient way to
+//! capture the display list commands generated by code that is generic
+//! over `Builder`.
+//!
+//! This module is available only when the `testing` feature is enabled.
+
+use crate::commands::decode_commands;
+use crate::display_list::Builder;
+use crate::models::Model;
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+
+/// The environment variable that causes
+/// [`assert_dl_snapshot!`](crate::assert_dl_snapshot) to write new snapshot
+/// files rather than comparing with the existing ones.
+pub const UPDATE_ENV_VAR: &str = "EVEGFX_UPDATE_SNAPSHOTS";
+
+/// Asserts that the given display list or coprocessor command words match
+/// the snapshot in the given text file.
+///
+/// The first argument is the path to the snapshot file, relative to the
+/// root directory of the calling crate. The second is anything that can
+/// iterate over `u32` command words, such as a reference to a
+/// [`Recorder`](crate::testing::Recorder) or the result of
+/// [`CommandList::words`](crate::commands::CommandList::words).
+///
+/// If the commands don't match, the macro panics with a line-by-line diff
+/// between the snapshot and the actual commands. If the environment
+/// variable `EVEGFX_UPDATE_SNAPSHOTS` is set then the macro instead
+/// overwrites the snapshot file with the actual commands.
+///
+/// ```rust,no_run
+/// use evegfx::display_list::Builder;
+/// use evegfx::testing::Recorder;
+///
+/// let mut rec = Recorder::new(evegfx::BT815);
+/// rec.clear_all().unwrap();
+/// rec.display().unwrap();
+/// evegfx::assert_dl_snapshot!("tests/snapshots/blank.txt", &rec);
+/// ```
+#[macro_export]
+macro_rules! assert_dl_snapshot {
+    ($path:expr, $commands:expr $(,)?) => {
+        if let ::core::result::Result::Err(msg) = $crate::testing::check_snapshot(
+            ::std::path::Path::new(::core::env!("CARGO_MANIFEST_DIR")).join($path),
+            &$crate::testing::format_commands($commands),
+        ) {
+            ::core::panic!("{}", msg);
+        }
+    };
+}
+
+/// An implementation of [`Builder`] which just records the display list
+/// commands in memory.
+///
+/// ```rust
+/// use evegfx::display_list::Builder;
+/// use evegfx::testing::Recorder;
+///
+/// let mut rec = Recorder::new(evegfx::BT815);
+/// rec.clear_all().unwrap();
+/// rec.display().unwrap();
+/// assert_eq!(rec.to_string(), "CLEAR(1, 1, 1)\nDISPLAY()\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recorder<M: Model> {
+    words: Vec<u32>,
+    _model: core::marker::PhantomData<M>,
+}
+
+impl<M: Model> Recorder<M> {
+    pub fn new(_model: M) -> Self {
+        Self {
+            words: Vec::new(),
+            _model: core::marker::PhantomData,
+        }
+    }
+
+    /// Returns the raw display list words recorded so far.
+    pub fn words(&self) -> &[u32] {
+        &self.words[..]
+    }
+
+    /// Discards all of the recorded commands.
+    pub fn clear(&mut self) {
+        self.words.clear()
+    }
+}
+
+impl<M: Model> Builder for Recorder<M> {
+    type Model = M;
+    type Error = core::convert::Infallible;
+
+    fn append_raw_command(&mut self, raw: u32) -> Result<(), Self::Error> {
+        self.words.push(raw);
+        Ok(())
+    }
+}
+
+impl<'a, M: Model> IntoIterator for &'a Recorder<M> {
+    type Item = u32;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'a, u32>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.words.iter().copied()
+    }
+}
+
+impl<M: Model> core::fmt::Display for Recorder<M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&format_commands(self))
+    }
+}
+
+/// Decodes the given display list or coprocessor command words and returns
+/// them as text, with one command per line.
+///
+/// This is the format used for snapshot files.
+pub fn format_commands<W: IntoIterator<Item = u32>>(words: W) -> String {
+    use core::fmt::Write;
+
+    let words: Vec<u32> = words.into_iter().collect();
+    let mut ret = String::new();
+    for cmd in decode_commands(&words) {
+        writeln!(ret, "{}", cmd).unwrap();
+    }
+    ret
+}
+
+/// Compares the given text with the content of the snapshot file at the
+/// given path, returning a description of the differences if they don't
+/// match.
+///
+/// If the environment variable named in [`UPDATE_ENV_VAR`] is set to a
+/// non-empty value then this instead writes the given text to the file,
+/// creating any missing parent directories.
+///
+/// This is the implementation of
+/// [`assert_dl_snapshot!`](crate::assert_dl_snapshot), which is usually
+/// more convenient to use.
+pub fn check_snapshot<P: AsRef<Path>>(path: P, actual: &str) -> Result<(), String> {
+    let update = match std::env::var_os(UPDATE_ENV_VAR) {
+        Some(v) => !v.is_empty(),
+        None => false,
+    };
+    check_snapshot_inner(path.as_ref(), actual, update)
+}
+
+fn check_snapshot_inner(path: &Path, actual: &str, update: bool) -> Result<(), String> {
+    if update {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|err| std::format!("failed to create {}: {}", dir.display(), err))?;
+        }
+        return std::fs::write(path, actual)
+            .map_err(|err| std::format!("failed to write {}: {}", path.display(), err));
+    }
+
+    let want = match std::fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(err) => {
+            return Err(std::format!(
+                "failed to read snapshot {}: {}\n\nRun the tests with {}=1 to create it.",
+                path.display(),
+                err,
+                UPDATE_ENV_VAR,
+            ))
+        }
+    };
+    // Snapshots might have Windows-style line endings if they were checked
+    // out on Windows with line ending conversion enabled.
+    let want = want.replace("\r\n", "\n");
+    if want == actual {
+        return Ok(());
+    }
+    Err(std::format!(
+        "display list doesn't match snapshot {}\n\n{}\nRun the tests with {}=1 to update the snapshot.",
+        path.display(),
+        diff_lines(&want, actual),
+        UPDATE_ENV_VAR,
+    ))
+}
+
+// The number of unchanged lines to show before and after each change in
+// a diff.
+const DIFF_CONTEXT: usize = 2;
+
+// Returns a line-oriented diff between the two strings, with lines only in
+// `want` prefixed by "-" and lines only in `got` prefixed by "+".
+fn diff_lines(want: &str, got: &str) -> String {
+    use core::fmt::Write;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Op {
+        Same,
+        Remove,
+        Add,
+    }
+
+    let a: Vec<&str> = want.lines().collect();
+    let b: Vec<&str> = got.lines().collect();
+
+    // We find the longest common subsequence using the classic dynamic
+    // programming approach. Snapshots are small enough that the quadratic
+    // cost doesn't matter.
+    let mut lcs = std::vec![std::vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                core::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops: Vec<(Op, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((Op::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Remove, a[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Add, b[j]));
+            j += 1;
+        }
+    }
+
+    let mut ret = String::new();
+    let mut skipped = false;
+    for (idx, (op, line)) in ops.iter().enumerate() {
+        let start = idx.saturating_sub(DIFF_CONTEXT);
+        let end = core::cmp::min(idx + DIFF_CONTEXT + 1, ops.len());
+        let near_change = ops[start..end].iter().any(|(op, _)| *op != Op::Same);
+        if !near_change {
+            if !skipped {
+                ret.push_str("  ...\n");
+                skipped = true;
+            }
+            continue;
+        }
+        skipped = false;
+        let prefix = match op {
+            Op::Same => ' ',
+            Op::Remove => '-',
+            Op::Add => '+',
+        };
+        writeln!(ret, "{} {}", prefix, line).unwrap();
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_list::options::GraphicsPrimitive;
+    use crate::graphics::RGB;
+    use crate::models::testing::Exhaustive;
+
+    fn recorder() -> Recorder<Exhaustive> {
+        Recorder {
+            words: Vec::new(),
+            _model: core::marker::PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_format_commands() {
+        let mut rec = recorder();
+        rec.clear_color_rgb(RGB { r: 255, g: 0, b: 0 }).unwrap();
+        rec.clear_all().unwrap();
+        rec.begin(GraphicsPrimitive::Points).unwrap();
+        rec.vertex_2f((160, 320)).unwrap();
+        rec.end().unwrap();
+        rec.display().unwrap();
+        assert_eq!(
+            format_commands(&rec),
+            "CLEAR_COLOR_RGB(255, 0, 0)\n\
+             CLEAR(1, 1, 1)\n\
+             BEGIN(POINTS)\n\
+             VERTEX2F(160, 320)\n\
+             END()\n\
+             DISPLAY()\n",
+        );
+
+        // Coprocessor commands are decoded too.
+        let got = format_commands([0xffffff00, 0x00000000, 0xffffff01].iter().copied());
+        assert_eq!(got, "CMD_DLSTART()\nDISPLAY()\nCMD_SWAP()\n");
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let want = "A\nB\nC\nD\nE\nF\nG\nH\n";
+        let got = "A\nB\nC\nD\nX\nF\nG\nH\nI\n";
+        assert_eq!(
+            diff_lines(want, got),
+            "  ...\n  \
+             C\n  \
+             D\n\
+             - E\n\
+             + X\n  \
+             F\n  \
+             G\n  \
+             H\n\
+             + I\n",
+        );
+    }
+
+    #[test]
+    fn test_check_snapshot() {
+        let dir =
+            std::env::temp_dir().join(std::format!("evegfx-snapshot-test-{}", std::process::id()));
+        let path = dir.join("sub").join("snapshot.txt");
+
+        let err = check_snapshot_inner(&path, "DISPLAY()\n", false).unwrap_err();
+        assert!(
+            err.contains("failed to read snapshot"),
+            "wrong error: {}",
+            err
+        );
+
+        check_snapshot_inner(&path, "DISPLAY()\n", true).unwrap();
+        check_snapshot_inner(&path, "DISPLAY()\n", false).unwrap();
+
+        std::fs::write(&path, "CLEAR(1, 1, 1)\r\nDISPLAY()\r\n").unwrap();
+        check_snapshot_inner(&path, "CLEAR(1, 1, 1)\nDISPLAY()\n", false).unwrap();
+        let err = check_snapshot_inner(&path, "DISPLAY()\n", false).unwrap_err();
+        assert!(
+            err.contains("- CLEAR(1, 1, 1)\n  DISPLAY()\n"),
+            "wrong error: {}",
+            err
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}